@@ -1,6 +1,7 @@
-use anyhow;
 use std::path::{Path, PathBuf};
 
+use crate::{Metadata, OpenOptions, VfsError, VfsFile, Walk};
+
 /// FsBackend defines a common API for all virtual file systems (vfs) in the crate.
 /// Some functions here use `path` as a parameter or return value.
 /// In all cases, `path` will refer to the virtual file system. The exception
@@ -30,6 +31,32 @@ pub trait FsBackend {
     /// Checks if `path` is a regular file.
     fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool>;
 
+    /// Checks if `path` is a symbolic link (the link itself is checked, not its target).
+    ///
+    /// The default implementation is for backends without links: any existing path is not one.
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = utils::normalize(self.cwd().join(path));
+        if !self.exists(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(false)
+    }
+
+    /// Returns metadata (type, length, timestamps, permissions, owner) of a file or directory.
+    ///
+    /// The default implementation returns an error of kind `Unsupported`.
+    fn metadata<P: AsRef<Path>>(&self, _path: P) -> Result<Metadata> {
+        Err(utils::unsupported("metadata()"))
+    }
+
+    /// Sets the Unix permission bits (`mode & 0o7777`) of a file or directory, following
+    /// symbolic links. They are returned by `metadata()` as `Metadata::mode()`.
+    ///
+    /// The default implementation returns an error of kind `Unsupported`.
+    fn set_permissions<P: AsRef<Path>>(&mut self, _path: P, _mode: u32) -> Result<()> {
+        Err(utils::unsupported("set_permissions()"))
+    }
+
     /// Returns an iterator over directory entries.
     /// `path` is a directory, or CWD if None.
     fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>>;
@@ -37,6 +64,30 @@ pub trait FsBackend {
     /// Returns a recursive iterator over the directory tree starting from a given path.
     fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>>;
 
+    /// Returns all entries matching the glob `pattern`, sorted. A relative pattern is resolved
+    /// against the CWD; the tree of the backend is matched, not the host.
+    ///
+    /// Supported syntax: `*` (any characters within a component), `?` (one character),
+    /// `[abc]`, `[a-z]` and `[!a-z]` (a character class), `**` as a whole component (any
+    /// number of directories, including none) and `{a,b}` (alternation, may be nested).
+    /// Wildcards match names starting with `.` as well. `.` and `..` are resolved before
+    /// matching. Links found in the tree are returned, but not walked into.
+    ///
+    /// An error is returned if `pattern` is empty or has an unclosed `[` or `{`.
+    ///
+    /// The default implementation walks `tree()` once, under the deepest directory shared by
+    /// the parts without wildcards of all brace alternatives.
+    fn glob(&self, pattern: &str) -> Result<Vec<PathBuf>> {
+        crate::glob::glob(self, pattern)
+    }
+
+    /// Returns a builder of a recursive walk starting at `path` (a directory or a file),
+    /// yielding entries with their depth and type. Unlike `tree()`, the walk can be limited in
+    /// depth, sorted, pruned and can follow links (see `Walk`).
+    fn walk<P: AsRef<Path>>(&self, path: P) -> Walk<'_, Self> {
+        Walk::new(self, path)
+    }
+
     /// Creates directory and all it parents, if necessary.
     fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()>;
 
@@ -52,6 +103,101 @@ pub trait FsBackend {
     /// Appends bytes to the end of an existing file, preserving its old contents.
     fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()>;
 
+    /// Opens a file with the given `options` (see `OpenOptions`) and returns a handle
+    /// implementing `std::io::Read`, `Write` and `Seek`, so large files can be streamed.
+    ///
+    /// The default implementation returns an error of kind `Unsupported`.
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        _path: P,
+        _options: &OpenOptions,
+    ) -> Result<impl VfsFile + '_> {
+        Err::<std::io::Cursor<Vec<u8>>, _>(utils::unsupported("open()"))
+    }
+
+    /// Moves a file or a whole directory subtree from `from` to `to`.
+    /// Missing parents of `to` are created. An error is returned if `from` does not exist
+    /// or is the root, if `to` already exists, or if `to` is inside `from`.
+    ///
+    /// The default implementation returns an error of kind `Unsupported`.
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, _from: P, _to: Q) -> Result<()> {
+        Err(utils::unsupported("rename()"))
+    }
+
+    /// Copies a file or a whole directory tree from `from` to `to`.
+    /// Missing parents of `to` are created. An error is returned if `from` does not exist
+    /// or is the root, if `to` already exists, or if `to` is inside `from`.
+    ///
+    /// Links are copied as links (with the same target).
+    ///
+    /// The default implementation recreates the tree with `mkdir()`, `mkfile()` and `symlink()`.
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        let (from, to) = utils::check_transfer(self, from, to)?;
+        for path in utils::subtree(self, &from)? {
+            let target = utils::rebase(&path, &from, &to);
+            if self.is_symlink(&path)? {
+                let link_target = self.read_link(&path)?;
+                self.symlink(link_target, &target)?;
+            } else if self.is_dir(&path)? {
+                self.mkdir(&target)?;
+            } else {
+                let content = self.read(&path)?;
+                self.mkfile(&target, Some(&content))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates a symbolic link at `link` pointing to `target`. `target` is stored as is:
+    /// an absolute one is an inner VFS path, a relative one is relative to the directory of
+    /// `link`. It does not have to exist, but it must not point outside the root.
+    /// Missing parents of `link` are created.
+    ///
+    /// All other methods follow links (inside VFS) in every path component, except `rm()`,
+    /// `rename()`, `symlink()`, `read_link()` and `is_symlink()`, which work on the link itself.
+    ///
+    /// The default implementation returns an error of kind `Unsupported`.
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, _target: P, _link: Q) -> Result<()> {
+        Err(utils::unsupported("symlink()"))
+    }
+
+    /// Returns the target of the symbolic link at `path`, as it was given to `symlink()`.
+    ///
+    /// The default implementation returns an error of kind `Unsupported`.
+    fn read_link<P: AsRef<Path>>(&self, _path: P) -> Result<PathBuf> {
+        Err(utils::unsupported("read_link()"))
+    }
+
+    /// Creates a hard link `new` to the file `existing`: both paths then share one content,
+    /// a change made through either of them is seen through the other. Removing one of them
+    /// keeps the content alive for the rest. Missing parents of `new` are created.
+    ///
+    /// `existing` is resolved following symbolic links, `new` must not exist.
+    /// An error is returned if `existing` does not exist or is a directory.
+    ///
+    /// The default implementation returns an error of kind `Unsupported`.
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, _existing: P, _new: Q) -> Result<()> {
+        Err(utils::unsupported("hard_link()"))
+    }
+
+    /// Returns the inner absolute path of `path` with all symbolic links resolved.
+    /// An error is returned if `path` does not exist, if a link points outside the root or if
+    /// links form a loop.
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let inner = utils::normalize(self.cwd().join(path));
+        let resolved = utils::resolve(&inner, true, |path| {
+            if matches!(self.is_symlink(path), Ok(true)) {
+                self.read_link(path).map(Some)
+            } else {
+                Ok(None)
+            }
+        })?;
+        if !self.exists(&resolved) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(resolved)
+    }
+
     /// Removes a file or directory at the specified path.
     fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()>;
 
@@ -59,12 +205,350 @@ pub trait FsBackend {
     fn cleanup(&mut self) -> bool;
 }
 
-pub type Result<T> = std::result::Result<T, anyhow::Error>;
+pub type Result<T> = std::result::Result<T, VfsError>;
+
+/// An object-safe companion of `FsBackend`, so backends chosen at runtime can be stored as
+/// `Box<dyn DynFsBackend>` (or `Arc<dyn DynFsBackend>` for read-only use).
+///
+/// Methods take `&Path` and return boxed iterators and handles; they are prefixed with `dyn_`
+/// to not clash with the ones of `FsBackend`. It is implemented for every `FsBackend`, and
+/// `Box<dyn DynFsBackend>` implements `FsBackend` in turn, so a boxed backend can be used with
+/// the usual API (and passed wherever an `FsBackend` is expected).
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{DirFS, DynFsBackend, FsBackend, MapFS};
+///
+/// let in_memory = true; // e.g. taken from config
+/// let mut fs: Box<dyn DynFsBackend> = if in_memory {
+///     Box::new(MapFS::new())
+/// } else {
+///     Box::new(DirFS::new("/var/lib/app").unwrap())
+/// };
+/// fs.mkfile("/config.toml", Some(b"debug = true")).unwrap();
+/// ```
+pub trait DynFsBackend {
+    fn dyn_root(&self) -> &Path;
+    fn dyn_cwd(&self) -> &Path;
+    fn dyn_to_host(&self, inner_path: &Path) -> Result<PathBuf>;
+    fn dyn_cd(&mut self, path: &Path) -> Result<()>;
+    fn dyn_exists(&self, path: &Path) -> bool;
+    fn dyn_is_dir(&self, path: &Path) -> Result<bool>;
+    fn dyn_is_file(&self, path: &Path) -> Result<bool>;
+    fn dyn_is_symlink(&self, path: &Path) -> Result<bool>;
+    fn dyn_metadata(&self, path: &Path) -> Result<Metadata>;
+    fn dyn_set_permissions(&mut self, path: &Path, mode: u32) -> Result<()>;
+    fn dyn_ls(&self, path: &Path) -> Result<Box<dyn Iterator<Item = &Path> + '_>>;
+    fn dyn_tree(&self, path: &Path) -> Result<Box<dyn Iterator<Item = &Path> + '_>>;
+    fn dyn_glob(&self, pattern: &str) -> Result<Vec<PathBuf>>;
+    fn dyn_mkdir(&mut self, path: &Path) -> Result<()>;
+    fn dyn_mkfile(&mut self, file_path: &Path, content: Option<&[u8]>) -> Result<()>;
+    fn dyn_read(&self, path: &Path) -> Result<Vec<u8>>;
+    fn dyn_write(&mut self, path: &Path, content: &[u8]) -> Result<()>;
+    fn dyn_append(&mut self, path: &Path, content: &[u8]) -> Result<()>;
+    fn dyn_open(&mut self, path: &Path, options: &OpenOptions) -> Result<Box<dyn VfsFile + '_>>;
+    fn dyn_rename(&mut self, from: &Path, to: &Path) -> Result<()>;
+    fn dyn_copy(&mut self, from: &Path, to: &Path) -> Result<()>;
+    fn dyn_symlink(&mut self, target: &Path, link: &Path) -> Result<()>;
+    fn dyn_read_link(&self, path: &Path) -> Result<PathBuf>;
+    fn dyn_hard_link(&mut self, existing: &Path, new: &Path) -> Result<()>;
+    fn dyn_canonicalize(&self, path: &Path) -> Result<PathBuf>;
+    fn dyn_rm(&mut self, path: &Path) -> Result<()>;
+    fn dyn_cleanup(&mut self) -> bool;
+}
+
+impl<B: FsBackend> DynFsBackend for B {
+    fn dyn_root(&self) -> &Path {
+        FsBackend::root(self)
+    }
+
+    fn dyn_cwd(&self) -> &Path {
+        FsBackend::cwd(self)
+    }
+
+    fn dyn_to_host(&self, inner_path: &Path) -> Result<PathBuf> {
+        FsBackend::to_host(self, inner_path)
+    }
+
+    fn dyn_cd(&mut self, path: &Path) -> Result<()> {
+        FsBackend::cd(self, path)
+    }
+
+    fn dyn_exists(&self, path: &Path) -> bool {
+        FsBackend::exists(self, path)
+    }
+
+    fn dyn_is_dir(&self, path: &Path) -> Result<bool> {
+        FsBackend::is_dir(self, path)
+    }
+
+    fn dyn_is_file(&self, path: &Path) -> Result<bool> {
+        FsBackend::is_file(self, path)
+    }
+
+    fn dyn_is_symlink(&self, path: &Path) -> Result<bool> {
+        FsBackend::is_symlink(self, path)
+    }
+
+    fn dyn_metadata(&self, path: &Path) -> Result<Metadata> {
+        FsBackend::metadata(self, path)
+    }
+
+    fn dyn_set_permissions(&mut self, path: &Path, mode: u32) -> Result<()> {
+        FsBackend::set_permissions(self, path, mode)
+    }
+
+    fn dyn_ls(&self, path: &Path) -> Result<Box<dyn Iterator<Item = &Path> + '_>> {
+        Ok(Box::new(FsBackend::ls(self, path.to_path_buf())?))
+    }
+
+    fn dyn_tree(&self, path: &Path) -> Result<Box<dyn Iterator<Item = &Path> + '_>> {
+        Ok(Box::new(FsBackend::tree(self, path.to_path_buf())?))
+    }
+
+    fn dyn_glob(&self, pattern: &str) -> Result<Vec<PathBuf>> {
+        self.glob(pattern)
+    }
+
+    fn dyn_mkdir(&mut self, path: &Path) -> Result<()> {
+        FsBackend::mkdir(self, path)
+    }
+
+    fn dyn_mkfile(&mut self, file_path: &Path, content: Option<&[u8]>) -> Result<()> {
+        FsBackend::mkfile(self, file_path, content)
+    }
+
+    fn dyn_read(&self, path: &Path) -> Result<Vec<u8>> {
+        FsBackend::read(self, path)
+    }
+
+    fn dyn_write(&mut self, path: &Path, content: &[u8]) -> Result<()> {
+        FsBackend::write(self, path, content)
+    }
+
+    fn dyn_append(&mut self, path: &Path, content: &[u8]) -> Result<()> {
+        FsBackend::append(self, path, content)
+    }
+
+    fn dyn_open(&mut self, path: &Path, options: &OpenOptions) -> Result<Box<dyn VfsFile + '_>> {
+        Ok(Box::new(FsBackend::open(
+            self,
+            path.to_path_buf(),
+            options,
+        )?))
+    }
+
+    fn dyn_rename(&mut self, from: &Path, to: &Path) -> Result<()> {
+        FsBackend::rename(self, from, to)
+    }
+
+    fn dyn_copy(&mut self, from: &Path, to: &Path) -> Result<()> {
+        FsBackend::copy(self, from, to)
+    }
+
+    fn dyn_symlink(&mut self, target: &Path, link: &Path) -> Result<()> {
+        FsBackend::symlink(self, target, link)
+    }
+
+    fn dyn_read_link(&self, path: &Path) -> Result<PathBuf> {
+        FsBackend::read_link(self, path)
+    }
+
+    fn dyn_hard_link(&mut self, existing: &Path, new: &Path) -> Result<()> {
+        FsBackend::hard_link(self, existing, new)
+    }
+
+    fn dyn_canonicalize(&self, path: &Path) -> Result<PathBuf> {
+        FsBackend::canonicalize(self, path)
+    }
+
+    fn dyn_rm(&mut self, path: &Path) -> Result<()> {
+        FsBackend::rm(self, path)
+    }
+
+    fn dyn_cleanup(&mut self) -> bool {
+        FsBackend::cleanup(self)
+    }
+}
+
+impl<T: DynFsBackend + ?Sized> FsBackend for Box<T> {
+    fn root(&self) -> &Path {
+        (**self).dyn_root()
+    }
+
+    fn cwd(&self) -> &Path {
+        (**self).dyn_cwd()
+    }
+
+    fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+        (**self).dyn_to_host(inner_path.as_ref())
+    }
+
+    fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        (**self).dyn_cd(path.as_ref())
+    }
+
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        (**self).dyn_exists(path.as_ref())
+    }
+
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        (**self).dyn_is_dir(path.as_ref())
+    }
+
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        (**self).dyn_is_file(path.as_ref())
+    }
+
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        (**self).dyn_is_symlink(path.as_ref())
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        (**self).dyn_metadata(path.as_ref())
+    }
+
+    fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        (**self).dyn_set_permissions(path.as_ref(), mode)
+    }
+
+    fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        (**self).dyn_ls(path.as_ref())
+    }
+
+    fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        (**self).dyn_tree(path.as_ref())
+    }
+
+    fn glob(&self, pattern: &str) -> Result<Vec<PathBuf>> {
+        (**self).dyn_glob(pattern)
+    }
+
+    fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        (**self).dyn_mkdir(path.as_ref())
+    }
+
+    fn mkfile<P: AsRef<Path>>(&mut self, file_path: P, content: Option<&[u8]>) -> Result<()> {
+        (**self).dyn_mkfile(file_path.as_ref(), content)
+    }
+
+    fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        (**self).dyn_read(path.as_ref())
+    }
+
+    fn write<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        (**self).dyn_write(path.as_ref(), content)
+    }
+
+    fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        (**self).dyn_append(path.as_ref(), content)
+    }
+
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<impl VfsFile + '_> {
+        (**self).dyn_open(path.as_ref(), options)
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        (**self).dyn_rename(from.as_ref(), to.as_ref())
+    }
+
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        (**self).dyn_copy(from.as_ref(), to.as_ref())
+    }
+
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, target: P, link: Q) -> Result<()> {
+        (**self).dyn_symlink(target.as_ref(), link.as_ref())
+    }
+
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        (**self).dyn_read_link(path.as_ref())
+    }
+
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, existing: P, new: Q) -> Result<()> {
+        (**self).dyn_hard_link(existing.as_ref(), new.as_ref())
+    }
+
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        (**self).dyn_canonicalize(path.as_ref())
+    }
+
+    fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        (**self).dyn_rm(path.as_ref())
+    }
+
+    fn cleanup(&mut self) -> bool {
+        (**self).dyn_cleanup()
+    }
+}
+
+/// Copies a file or a whole directory tree `src_path` of `src` to `dst_path` of `dst`, walking
+/// `tree()` on the source and recreating the structure with `mkdir()` and `mkfile()`.
+/// Both paths are resolved against the CWD of their backend.
+///
+/// Directories which already exist in `dst` are reused (so a tree can be copied into `/`),
+/// an existing file is an error. Links are copied as links.
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{DirFS, FsBackend, MapFS, copy_between};
+///
+/// let mut fixture = DirFS::new("/path/to/fixtures").unwrap();
+/// fixture.set_auto_clean(false);
+/// fixture.add("/").unwrap();
+///
+/// let mut fs = MapFS::new();
+/// copy_between(&fixture, "/", &mut fs, "/").unwrap();
+/// ```
+pub fn copy_between<S, D, P, Q>(src: &S, src_path: P, dst: &mut D, dst_path: Q) -> Result<()>
+where
+    S: FsBackend,
+    D: FsBackend,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    if src_path.as_ref().as_os_str().is_empty() || dst_path.as_ref().as_os_str().is_empty() {
+        return Err(VfsError::invalid_path("", "empty"));
+    }
+    let from = utils::resolve_parent(src, src_path)?;
+    let to = utils::resolve_parent(dst, dst_path)?;
+    if !utils::lexists(src, &from) {
+        return Err(VfsError::NotFound(from));
+    }
+
+    for path in utils::subtree(src, &from)? {
+        let target = utils::rebase(&path, &from, &to);
+        if src.is_symlink(&path)? {
+            dst.symlink(src.read_link(&path)?, &target)?;
+        } else if src.is_dir(&path)? {
+            if !dst.exists(&target) {
+                dst.mkdir(&target)?;
+            } else if !dst.is_dir(&target)? {
+                return Err(VfsError::NotADirectory(target));
+            }
+        } else {
+            dst.mkfile(&target, Some(&src.read(&path)?))?;
+        }
+    }
+    Ok(())
+}
 
 pub mod utils {
-    use super::Result;
+    use super::{FsBackend, Result};
+    use crate::VfsError;
     use std::path::{Component, Path, PathBuf};
 
+    /// Returns the error of kind `Unsupported` of an `operation` the backend does not provide.
+    pub fn unsupported(operation: &str) -> VfsError {
+        VfsError::Io(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("{operation} is not supported by this backend"),
+        ))
+    }
+
     /// Normalizes an arbitrary `path` by processing all occurrences
     /// of '.' and '..' elements. Also, removes final `/`.
     pub fn normalize<P: AsRef<Path>>(path: P) -> PathBuf {
@@ -81,7 +565,7 @@ pub mod utils {
             }
         }
         // remove final /
-        if result != PathBuf::from("/") && result.ends_with("/") {
+        if result != Path::new("/") && result.ends_with("/") {
             result.pop();
         }
         result
@@ -93,14 +577,373 @@ pub mod utils {
         components.len() == 1 && components[0] == Component::RootDir
     }
 
-    /// Removes file or directory (recursively) on host.
+    /// Maximum number of symbolic links followed while resolving one path.
+    pub const MAX_LINK_HOPS: usize = 40;
+
+    /// Resolves symbolic links in the inner absolute normalized `path`, component by component.
+    /// The last component is followed only if `follow_last` is set (`rm()` or `rename()` work
+    /// on the link itself).
+    ///
+    /// `link_of` returns the target of the link at a given (already resolved) inner path, or
+    /// `None` if there is no link at that path. Missing components are kept as they are.
+    ///
+    /// An error is returned if a target points outside the root or if more than
+    /// `MAX_LINK_HOPS` links are followed (a loop).
+    pub fn resolve<F>(path: &Path, follow_last: bool, mut link_of: F) -> Result<PathBuf>
+    where
+        F: FnMut(&Path) -> Result<Option<PathBuf>>,
+    {
+        let mut resolved = PathBuf::from("/");
+        let mut pending: Vec<PathBuf> = path
+            .components()
+            .rev()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .map(|component| PathBuf::from(component.as_os_str()))
+            .collect();
+        let mut hops = 0;
+        while let Some(name) = pending.pop() {
+            let candidate = resolved.join(&name);
+            let target = if follow_last || !pending.is_empty() {
+                link_of(&candidate)?
+            } else {
+                None
+            };
+            let Some(target) = target else {
+                resolved = candidate;
+                continue;
+            };
+            hops += 1;
+            if hops > MAX_LINK_HOPS {
+                return Err(VfsError::invalid_path(
+                    path,
+                    "too many levels of symbolic links",
+                ));
+            }
+            // Restart from the root with the target followed by the rest of the path
+            let target = link_target(&resolved, &target)?;
+            pending.extend(
+                target
+                    .components()
+                    .rev()
+                    .filter(|component| matches!(component, Component::Normal(_)))
+                    .map(|component| PathBuf::from(component.as_os_str())),
+            );
+            resolved = PathBuf::from("/");
+        }
+        Ok(resolved)
+    }
+
+    /// Returns the inner absolute normalized path a link placed in the `parent` directory
+    /// points to with `target` (absolute or relative to `parent`).
+    ///
+    /// An error is returned if `target` points outside the root (e.g. `../..` from `/a`).
+    pub fn link_target(parent: &Path, target: &Path) -> Result<PathBuf> {
+        if target.as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let mut result = PathBuf::from("/");
+        for component in parent.join(target).components() {
+            match component {
+                Component::Normal(name) => result.push(name),
+                Component::ParentDir if !result.pop() => {
+                    return Err(VfsError::invalid_path(
+                        target,
+                        "the link points outside the root",
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    /// Resolves `path` of `fs` (against its CWD) following links in all components but the
+    /// last one, the way `rm()` and `rename()` see it.
+    pub fn resolve_parent<B, P>(fs: &B, path: P) -> Result<PathBuf>
+    where
+        B: FsBackend + ?Sized,
+        P: AsRef<Path>,
+    {
+        resolve(&normalize(fs.cwd().join(path)), false, |path| {
+            if matches!(fs.is_symlink(path), Ok(true)) {
+                fs.read_link(path).map(Some)
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    /// Checks if `inner_path` exists without following a link at it (a dangling link exists).
+    pub fn lexists<B: FsBackend + ?Sized>(fs: &B, inner_path: &Path) -> bool {
+        fs.exists(inner_path) || matches!(fs.is_symlink(inner_path), Ok(true))
+    }
+
+    /// Removes file, link or directory (recursively) on host. Links are never followed.
     pub fn rm_on_host<P: AsRef<Path>>(host_path: P) -> Result<()> {
         let host_path = host_path.as_ref();
-        if host_path.is_dir() {
+        if !host_path.is_symlink() && host_path.is_dir() {
             std::fs::remove_dir_all(host_path)?
         } else {
             std::fs::remove_file(host_path)?
         }
         Ok(())
     }
+
+    /// Creates a symbolic link at `host_link` pointing to `host_target` on the host.
+    /// `is_dir` tells the kind of the target, which matters on Windows only.
+    pub fn symlink_on_host(host_target: &Path, host_link: &Path, is_dir: bool) -> Result<()> {
+        #[cfg(unix)]
+        {
+            let _ = is_dir;
+            std::os::unix::fs::symlink(host_target, host_link)?;
+        }
+        #[cfg(windows)]
+        {
+            if is_dir {
+                std::os::windows::fs::symlink_dir(host_target, host_link)?;
+            } else {
+                std::os::windows::fs::symlink_file(host_target, host_link)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts permission bits into host permissions of `host_path`. On platforms other than
+    /// Unix only the read-only flag is derived from `mode` (no write bit means read-only).
+    pub fn host_permissions(host_path: &Path, mode: u32) -> Result<std::fs::Permissions> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = host_path;
+            Ok(std::fs::Permissions::from_mode(mode & 0o7777))
+        }
+        #[cfg(not(unix))]
+        {
+            let mut permissions = std::fs::metadata(host_path)?.permissions();
+            permissions.set_readonly(mode & 0o222 == 0);
+            Ok(permissions)
+        }
+    }
+
+    /// Validates the arguments of `FsBackend::rename()` and `FsBackend::copy()` against `fs`
+    /// and returns them
+    /// as inner absolute normalized paths (`from`, `to`).
+    pub fn check_transfer<B, P, Q>(fs: &B, from: P, to: Q) -> Result<(PathBuf, PathBuf)>
+    where
+        B: FsBackend + ?Sized,
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        if from.as_os_str().is_empty() || to.as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let from = resolve_parent(fs, from)?;
+        let to = resolve_parent(fs, to)?;
+        if is_virtual_root(&from) || is_virtual_root(&to) {
+            return Err(VfsError::RootProtected(PathBuf::from("/")));
+        }
+        if !lexists(fs, &from) {
+            return Err(VfsError::NotFound(from));
+        }
+        if lexists(fs, &to) {
+            return Err(VfsError::AlreadyExists(to));
+        }
+        if to.starts_with(&from) {
+            return Err(VfsError::invalid_path(
+                to,
+                "the target is inside the source",
+            ));
+        }
+        if let Some(parent) = to.ancestors().skip(1).find(|p| fs.exists(p))
+            && !fs.is_dir(parent)?
+        {
+            return Err(VfsError::NotADirectory(parent.to_path_buf()));
+        }
+        Ok((from, to))
+    }
+
+    /// Returns `inner_path` (if it is a directory) and all its descendants (as `tree()` does),
+    /// parents before children. A link is returned alone, its target is not walked.
+    pub fn subtree<B: FsBackend + ?Sized>(fs: &B, inner_path: &Path) -> Result<Vec<PathBuf>> {
+        if fs.is_symlink(inner_path)? {
+            return Ok(vec![inner_path.to_path_buf()]);
+        }
+        let mut paths = Vec::new();
+        if fs.is_dir(inner_path)? {
+            paths.push(inner_path.to_path_buf());
+        }
+        paths.extend(fs.tree(inner_path)?.map(Path::to_path_buf));
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Replaces the `from` prefix of `path` with `to` (`path` is returned as is, if it is not
+    /// inside `from`).
+    pub fn rebase(path: &Path, from: &Path, to: &Path) -> PathBuf {
+        match path.strip_prefix(from) {
+            Ok(rest) if rest.as_os_str().is_empty() => to.to_path_buf(),
+            Ok(rest) => to.join(rest),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirFS, MapFS};
+    use std::sync::Arc;
+    use tempdir::TempDir;
+
+    fn create_backend(in_memory: bool, root: &Path) -> Box<dyn DynFsBackend> {
+        if in_memory {
+            Box::new(MapFS::new())
+        } else {
+            Box::new(DirFS::new(root).unwrap())
+        }
+    }
+
+    mod dyn_backend {
+        use super::*;
+        use std::io::Read;
+
+        #[test]
+        fn test_boxed_backends() -> Result<()> {
+            let temp_dir = TempDir::new("dyn_test")?;
+            for in_memory in [true, false] {
+                let mut fs = create_backend(in_memory, temp_dir.path());
+                fs.mkfile("/docs/a.txt", Some(b"A"))?;
+                fs.cd("/docs")?;
+                fs.append("a.txt", b"B")?;
+                fs.copy("a.txt", "b.txt")?;
+
+                let listed: Vec<_> = fs.ls("/docs")?.collect();
+                assert_eq!(
+                    listed,
+                    vec![Path::new("/docs/a.txt"), Path::new("/docs/b.txt")]
+                );
+                assert_eq!(fs.metadata("b.txt")?.len(), 2);
+
+                let mut content = String::new();
+                fs.open("b.txt", OpenOptions::new().read(true))?
+                    .read_to_string(&mut content)?;
+                assert_eq!(content, "AB");
+
+                fs.rm("/docs")?;
+                assert_eq!(fs.tree("/")?.count(), 0);
+            }
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_shared_and_generic_use() -> Result<()> {
+            let mut src = create_backend(true, Path::new("/"));
+            src.mkfile("/a.txt", Some(b"A"))?;
+
+            let mut dst: Box<dyn DynFsBackend> = Box::new(MapFS::new());
+            copy_between(&src, "/", &mut dst, "/copy")?;
+            assert_eq!(dst.read("/copy/a.txt")?, b"A");
+
+            let shared: Arc<dyn DynFsBackend> = Arc::from(src);
+            assert_eq!(shared.dyn_read(Path::new("/a.txt"))?, b"A");
+
+            Ok(())
+        }
+    }
+
+    mod default_methods {
+        use super::*;
+        use std::io::ErrorKind;
+
+        /// A backend implementing only the required methods.
+        struct Minimal(MapFS);
+
+        impl FsBackend for Minimal {
+            fn root(&self) -> &Path {
+                self.0.root()
+            }
+            fn cwd(&self) -> &Path {
+                self.0.cwd()
+            }
+            fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+                self.0.to_host(inner_path)
+            }
+            fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+                self.0.cd(path)
+            }
+            fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+                self.0.exists(path)
+            }
+            fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+                self.0.is_dir(path)
+            }
+            fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+                self.0.is_file(path)
+            }
+            fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+                self.0.ls(path)
+            }
+            fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+                self.0.tree(path)
+            }
+            fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+                self.0.mkdir(path)
+            }
+            fn mkfile<P: AsRef<Path>>(&mut self, path: P, content: Option<&[u8]>) -> Result<()> {
+                self.0.mkfile(path, content)
+            }
+            fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+                self.0.read(path)
+            }
+            fn write<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+                self.0.write(path, content)
+            }
+            fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+                self.0.append(path, content)
+            }
+            fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+                self.0.rm(path)
+            }
+            fn cleanup(&mut self) -> bool {
+                self.0.cleanup()
+            }
+        }
+
+        #[test]
+        fn test_defaults_of_optional_methods() -> Result<()> {
+            let mut fs = Minimal(MapFS::new());
+            fs.mkfile("/docs/a.txt", Some(b"A"))?;
+
+            assert!(!fs.is_symlink("/docs/a.txt")?);
+            assert!(matches!(
+                fs.is_symlink("/b.txt"),
+                Err(VfsError::NotFound(_))
+            ));
+            assert_eq!(
+                fs.canonicalize("/docs/../docs/a.txt")?,
+                Path::new("/docs/a.txt")
+            );
+            fs.copy("/docs", "/copy")?;
+            assert_eq!(fs.read("/copy/a.txt")?, b"A");
+
+            let unsupported = [
+                fs.metadata("/docs/a.txt").err(),
+                fs.set_permissions("/docs/a.txt", 0o600).err(),
+                fs.open("/docs/a.txt", OpenOptions::new().read(true)).err(),
+                fs.rename("/docs/a.txt", "/b.txt").err(),
+                fs.symlink("/docs/a.txt", "/link").err(),
+                fs.read_link("/docs/a.txt").err(),
+                fs.hard_link("/docs/a.txt", "/b.txt").err(),
+            ];
+            for err in unsupported {
+                assert_eq!(err.map(|err| err.kind()), Some(ErrorKind::Unsupported));
+            }
+            assert!(fs.exists("/docs/a.txt"));
+            assert!(!fs.exists("/b.txt"));
+
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,199 @@
+//! This module provides the error type shared by all virtual file systems (VFS) in the crate.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf, StripPrefixError};
+
+/// The error type of all VFS operations.
+///
+/// Every variant describing a problem with a particular entry carries the offending path
+/// (usually an inner VFS path, or a host path for errors of construction), so callers can
+/// match on the variant instead of the message.
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{FsBackend, MapFS, VfsError};
+///
+/// let fs = MapFS::new();
+/// match fs.read("/missing.txt") {
+///     Err(VfsError::NotFound(path)) => println!("{} is missing", path.display()),
+///     Err(err) => println!("{err}"),
+///     Ok(content) => println!("{} bytes", content.len()),
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VfsError {
+    /// The path does not exist.
+    NotFound(PathBuf),
+    /// The path already exists.
+    AlreadyExists(PathBuf),
+    /// The path is a directory, but a file was expected.
+    IsADirectory(PathBuf),
+    /// The path (or one of its parents) is not a directory, but a directory was expected.
+    NotADirectory(PathBuf),
+    /// The operation is not permitted for the path.
+    PermissionDenied(PathBuf),
+    /// The path is not acceptable for the operation (empty, relative, unsafe, etc.).
+    InvalidPath { path: PathBuf, reason: String },
+    /// The operation would remove or change the root of VFS.
+    RootProtected(PathBuf),
+    /// An I/O error of the host file system.
+    Io(io::Error),
+    /// Any other error (corrupted data, wrong key, unsupported format, etc.).
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl VfsError {
+    /// Creates `VfsError::InvalidPath` for `path` with the given `reason`.
+    pub fn invalid_path<P: AsRef<Path>, S: Into<String>>(path: P, reason: S) -> Self {
+        Self::InvalidPath {
+            path: path.as_ref().to_path_buf(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Creates `VfsError::Other` from any error or message.
+    pub fn other<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> Self {
+        Self::Other(error.into())
+    }
+
+    /// Returns the offending path, if the error has one.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::NotFound(path)
+            | Self::AlreadyExists(path)
+            | Self::IsADirectory(path)
+            | Self::NotADirectory(path)
+            | Self::PermissionDenied(path)
+            | Self::InvalidPath { path, .. }
+            | Self::RootProtected(path) => Some(path),
+            Self::Io(_) | Self::Other(_) => None,
+        }
+    }
+
+    /// Returns the corresponding `std::io::ErrorKind`.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Self::NotFound(_) => io::ErrorKind::NotFound,
+            Self::AlreadyExists(_) => io::ErrorKind::AlreadyExists,
+            Self::IsADirectory(_) => io::ErrorKind::IsADirectory,
+            Self::NotADirectory(_) => io::ErrorKind::NotADirectory,
+            Self::PermissionDenied(_) | Self::RootProtected(_) => io::ErrorKind::PermissionDenied,
+            Self::InvalidPath { .. } => io::ErrorKind::InvalidInput,
+            Self::Io(err) => err.kind(),
+            Self::Other(_) => io::ErrorKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for VfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(path) => write!(f, "{} does not exist", path.display()),
+            Self::AlreadyExists(path) => write!(f, "path already exists: {}", path.display()),
+            Self::IsADirectory(path) => write!(f, "{} is a directory", path.display()),
+            Self::NotADirectory(path) => write!(f, "{} is not a directory", path.display()),
+            Self::PermissionDenied(path) => write!(f, "Permission denied: {}", path.display()),
+            Self::InvalidPath { path, reason } if path.as_os_str().is_empty() => {
+                write!(f, "invalid path: {reason}")
+            }
+            Self::InvalidPath { path, reason } => {
+                write!(f, "invalid path: {reason}: {}", path.display())
+            }
+            Self::RootProtected(path) => write!(
+                f,
+                "invalid path: the root cannot be removed or changed: {}",
+                path.display()
+            ),
+            Self::Io(err) => err.fmt(f),
+            Self::Other(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for VfsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Other(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VfsError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<StripPrefixError> for VfsError {
+    fn from(err: StripPrefixError) -> Self {
+        Self::Other(Box::new(err))
+    }
+}
+
+#[cfg(feature = "zip")]
+impl From<zip::result::ZipError> for VfsError {
+    fn from(err: zip::result::ZipError) -> Self {
+        match err {
+            zip::result::ZipError::Io(err) => Self::Io(err),
+            err => Self::Other(Box::new(err)),
+        }
+    }
+}
+
+impl From<VfsError> for io::Error {
+    fn from(err: VfsError) -> Self {
+        match err {
+            VfsError::Io(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            VfsError::NotFound(PathBuf::from("/a")).to_string(),
+            "/a does not exist"
+        );
+        assert_eq!(
+            VfsError::invalid_path("", "empty").to_string(),
+            "invalid path: empty"
+        );
+        assert_eq!(
+            VfsError::invalid_path("a/b", "the path must be absolute").to_string(),
+            "invalid path: the path must be absolute: a/b"
+        );
+    }
+
+    #[test]
+    fn test_kind_and_path() {
+        let err = VfsError::IsADirectory(PathBuf::from("/dir"));
+        assert_eq!(err.kind(), io::ErrorKind::IsADirectory);
+        assert_eq!(err.path(), Some(Path::new("/dir")));
+
+        let err = VfsError::from(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(err.path(), None);
+
+        assert_eq!(
+            VfsError::RootProtected(PathBuf::from("/")).kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        assert_eq!(VfsError::other("corrupted").kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_into_io_error() {
+        let err: io::Error = VfsError::AlreadyExists(PathBuf::from("/a")).into();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(err.to_string(), "path already exists: /a");
+    }
+}
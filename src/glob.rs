@@ -0,0 +1,556 @@
+//! This module provides glob patterns matched against inner VFS paths (see `FsBackend::glob()`).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+
+use crate::VfsError;
+use crate::core::{FsBackend, Result, utils};
+
+/// One component of a parsed pattern.
+#[derive(Debug)]
+enum Segment {
+    /// `**`: any number of components, including none.
+    AnyDepth,
+    /// A single component matched token by token.
+    Name(Vec<Token>),
+}
+
+#[derive(Debug)]
+enum Token {
+    Char(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnySeq,
+    /// `[...]`, `[!...]` or `[^...]`
+    Class {
+        is_negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Self::Char(expected) => *expected == c,
+            Self::AnyChar => true,
+            Self::AnySeq => unreachable!("`*` is matched by `match_name()`"),
+            Self::Class { is_negated, ranges } => {
+                ranges.iter().any(|&(from, to)| from <= c && c <= to) != *is_negated
+            }
+        }
+    }
+}
+
+/// Returns all entries of `fs` matching `pattern`, sorted (see `FsBackend::glob()`).
+pub(crate) fn glob<B: FsBackend + ?Sized>(fs: &B, pattern: &str) -> Result<Vec<PathBuf>> {
+    if pattern.is_empty() {
+        return Err(VfsError::invalid_path("", "empty"));
+    }
+    let mut found = BTreeSet::new();
+    // alternatives with wildcards, grouped by the directory they are matched under (resolved)
+    // and the path it is reported with
+    let mut groups: BTreeMap<(PathBuf, PathBuf), Vec<Vec<Segment>>> = BTreeMap::new();
+    for alternative in expand_braces(pattern)? {
+        let inner = utils::normalize(fs.cwd().join(alternative));
+        let names: Vec<String> = inner
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+
+        // Entries are looked up under the longest prefix without wildcards only
+        let literal_count = names.iter().take_while(|name| !is_wildcard(name)).count();
+        let base: PathBuf = Path::new("/").join(names[..literal_count].iter().collect::<PathBuf>());
+        if literal_count == names.len() {
+            if utils::lexists(fs, &base) && !utils::is_virtual_root(&base) {
+                found.insert(base);
+            }
+            continue;
+        }
+        let segments = names[literal_count..]
+            .iter()
+            .map(|name| parse_segment(name, pattern))
+            .collect::<Result<Vec<_>>>()?;
+        if !fs.exists(&base) || !fs.is_dir(&base)? {
+            continue;
+        }
+
+        // `tree()` lists the target of a linked base, results keep the path of the pattern
+        let resolved = fs.canonicalize(&base)?;
+        groups.entry((resolved, base)).or_default().push(segments);
+    }
+
+    // All groups are matched in a single walk of the tree under their common ancestor
+    let resolved_dirs = groups.keys().map(|(resolved, _)| resolved.clone());
+    let Some(common) = resolved_dirs.reduce(|common, resolved| {
+        common
+            .ancestors()
+            .find(|ancestor| resolved.starts_with(ancestor))
+            .unwrap() // safe unwrap(): both are absolute
+            .to_path_buf()
+    }) else {
+        return Ok(found.into_iter().collect());
+    };
+    for path in fs.tree(&common)? {
+        for ((resolved, base), alternatives) in &groups {
+            let Ok(rest) = path.strip_prefix(resolved) else {
+                continue;
+            };
+            let rest: Vec<String> = rest
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            if alternatives
+                .iter()
+                .any(|segments| match_segments(segments, &rest))
+            {
+                found.insert(utils::rebase(path, resolved, base));
+            }
+        }
+    }
+    Ok(found.into_iter().collect())
+}
+
+/// A pattern matched against relative paths (e.g. include and exclude patterns of an import).
+/// It has the syntax of `FsBackend::glob()`, but `.` and `..` are not resolved.
+#[derive(Debug)]
+pub(crate) struct Pattern {
+    alternatives: Vec<Vec<Segment>>,
+}
+
+impl Pattern {
+    /// Parses `pattern`. An error is returned if it is empty or has an unclosed `[` or `{`.
+    pub(crate) fn new(pattern: &str) -> Result<Self> {
+        if pattern.is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let alternatives = expand_braces(pattern)?
+            .iter()
+            .map(|alternative| {
+                alternative
+                    .split('/')
+                    .filter(|name| !name.is_empty() && *name != ".")
+                    .map(|name| parse_segment(name, pattern))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { alternatives })
+    }
+
+    /// Checks if the relative `path` matches the pattern.
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        let names: Vec<String> = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        self.alternatives
+            .iter()
+            .any(|segments| match_segments(segments, &names))
+    }
+}
+
+fn is_wildcard(name: &str) -> bool {
+    name.contains(['*', '?', '['])
+}
+
+/// Expands brace alternation (`{a,b}`, nested ones included) into plain patterns.
+fn expand_braces(pattern: &str) -> Result<Vec<String>> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let Some(open) = find_open_brace(&chars) else {
+        return Ok(vec![pattern.to_string()]);
+    };
+
+    let mut depth = 0;
+    let mut parts = Vec::new();
+    let mut start = open + 1;
+    let mut close = None;
+    let mut i = open + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '[' => i = skip_class(&chars, i),
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            '}' => {
+                parts.push(&chars[start..i]);
+                close = Some(i);
+                break;
+            }
+            ',' if depth == 0 => {
+                parts.push(&chars[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    let Some(close) = close else {
+        return Err(VfsError::invalid_path(
+            pattern,
+            "unclosed `{` in the pattern",
+        ));
+    };
+
+    let prefix: String = chars[..open].iter().collect();
+    let suffix: String = chars[close + 1..].iter().collect();
+    let mut expanded = Vec::new();
+    for part in parts {
+        let part: String = part.iter().collect();
+        // the suffix may hold more groups, so the whole pattern is expanded again
+        expanded.extend(expand_braces(&format!("{prefix}{part}{suffix}"))?);
+    }
+    Ok(expanded)
+}
+
+/// Returns the index of the first `{` outside of character classes.
+fn find_open_brace(chars: &[char]) -> Option<usize> {
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '[' {
+            i = skip_class(chars, i);
+        } else if chars[i] == '{' {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the index of `]` closing the class opened at `open` (or `open` itself, if the class
+/// is not closed, so `[` is reported by `parse_segment()`).
+fn skip_class(chars: &[char], open: usize) -> usize {
+    let mut i = open + 1;
+    if matches!(chars.get(i), Some('!' | '^')) {
+        i += 1;
+    }
+    if chars.get(i) == Some(&']') {
+        i += 1; // `]` right after the opening is a literal
+    }
+    while i < chars.len() {
+        if chars[i] == ']' {
+            return i;
+        }
+        i += 1;
+    }
+    open
+}
+
+/// Parses one component of the pattern. `pattern` is used for error reporting only.
+fn parse_segment(name: &str, pattern: &str) -> Result<Segment> {
+    if name == "**" {
+        return Ok(Segment::AnyDepth);
+    }
+    let chars: Vec<char> = name.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => {
+                if !matches!(tokens.last(), Some(Token::AnySeq)) {
+                    tokens.push(Token::AnySeq);
+                }
+            }
+            '?' => tokens.push(Token::AnyChar),
+            '[' => {
+                let close = skip_class(&chars, i);
+                if close == i {
+                    return Err(VfsError::invalid_path(
+                        pattern,
+                        "unclosed `[` in the pattern",
+                    ));
+                }
+                tokens.push(parse_class(&chars[i + 1..close]));
+                i = close;
+            }
+            c => tokens.push(Token::Char(c)),
+        }
+        i += 1;
+    }
+    Ok(Segment::Name(tokens))
+}
+
+/// Parses the contents of `[...]` (without the brackets).
+fn parse_class(chars: &[char]) -> Token {
+    let is_negated = matches!(chars.first(), Some('!' | '^'));
+    let chars = if is_negated { &chars[1..] } else { chars };
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if i + 2 < chars.len() && chars[i + 1] == '-' {
+            ranges.push((chars[i], chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((chars[i], chars[i]));
+            i += 1;
+        }
+    }
+    Token::Class { is_negated, ranges }
+}
+
+/// Checks if `names` match `segments`. Every `**` may take any number of names, so the
+/// matching is done over a table of (segment, name) suffixes, each name is matched against
+/// each segment at most once.
+fn match_segments(segments: &[Segment], names: &[String]) -> bool {
+    // is_match[j] tells if `segments[i..]` matches `names[j..]`, for the current `i`
+    let mut is_match = vec![false; names.len() + 1];
+    is_match[names.len()] = true;
+    for segment in segments.iter().rev() {
+        let mut next = vec![false; names.len() + 1];
+        for j in (0..=names.len()).rev() {
+            next[j] = match segment {
+                Segment::AnyDepth => is_match[j] || (j < names.len() && next[j + 1]),
+                Segment::Name(tokens) => {
+                    j < names.len() && is_match[j + 1] && {
+                        let chars: Vec<char> = names[j].chars().collect();
+                        match_name(tokens, &chars)
+                    }
+                }
+            };
+        }
+        is_match = next;
+    }
+    is_match[0]
+}
+
+/// Checks if `name` matches `tokens`. When a later token fails, only the last `*` is
+/// retried (taking one more character), which is enough, since any earlier `*` could only
+/// take less.
+fn match_name(tokens: &[Token], name: &[char]) -> bool {
+    let (mut t, mut n) = (0, 0);
+    let mut backtrack = None; // (token after the last `*`, first character it may take)
+    while n < name.len() {
+        match tokens.get(t) {
+            Some(Token::AnySeq) => {
+                t += 1;
+                backtrack = Some((t, n));
+                continue;
+            }
+            Some(token) if token.matches(name[n]) => {
+                t += 1;
+                n += 1;
+                continue;
+            }
+            _ => {}
+        }
+        let Some((star_t, star_n)) = backtrack else {
+            return false;
+        };
+        t = star_t;
+        n = star_n + 1;
+        backtrack = Some((star_t, n));
+    }
+    tokens[t..].iter().all(|token| matches!(token, Token::AnySeq))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirFS, MapFS};
+    use tempdir::TempDir;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        expand_braces(pattern).unwrap().iter().any(|alternative| {
+            let segments: Vec<_> = alternative
+                .split('/')
+                .map(|name| parse_segment(name, pattern).unwrap())
+                .collect();
+            let names: Vec<String> = path.split('/').map(String::from).collect();
+            match_segments(&segments, &names)
+        })
+    }
+
+    fn create_fixtures() -> Result<MapFS> {
+        let mut fs = MapFS::new();
+        fs.mkfile("/package.json", Some(b"{}"))?;
+        fs.mkfile("/src/main.rs", None)?;
+        fs.mkfile("/src/lib.rs", None)?;
+        fs.mkfile("/tests/data/a.json", None)?;
+        fs.mkfile("/tests/data/nested/b.json", None)?;
+        fs.mkfile("/tests/data/c.txt", None)?;
+        fs.mkfile("/.hidden.json", None)?;
+        Ok(fs)
+    }
+
+    fn paths(list: &[&str]) -> Vec<PathBuf> {
+        list.iter().map(PathBuf::from).collect()
+    }
+
+    mod patterns {
+        use super::*;
+
+        #[test]
+        fn test_wildcards() {
+            assert!(matches("*.rs", "main.rs"));
+            assert!(matches("*", ".hidden"));
+            assert!(!matches("*.rs", "src/main.rs"));
+            assert!(matches("?.txt", "a.txt"));
+            assert!(!matches("?.txt", "ab.txt"));
+            assert!(matches("a*b*c", "a-b-b-c"));
+            assert!(!matches("a*b*c", "a-b-b-"));
+        }
+
+        #[test]
+        fn test_no_exponential_backtracking() {
+            let name = "a".repeat(60);
+            assert!(!matches("*a*a*a*a*a*a*a*a*a*a*a*ab", &name));
+            assert!(matches("*a*a*a*a*a*a*a*a*a*a*a*a", &name));
+
+            let path = vec!["a"; 40].join("/");
+            assert!(!matches(&format!("{}/b", vec!["**"; 20].join("/")), &path));
+            assert!(matches(&format!("{}/a", vec!["**"; 20].join("/")), &path));
+        }
+
+        #[test]
+        fn test_classes() {
+            assert!(matches("file[0-9].txt", "file7.txt"));
+            assert!(!matches("file[0-9].txt", "filex.txt"));
+            assert!(matches("file[!0-9].txt", "filex.txt"));
+            assert!(matches("file[^0-9].txt", "filex.txt"));
+            assert!(matches("[]x]", "]"));
+            assert!(matches("[a-]", "-"));
+            assert!(matches("[*]", "*"));
+            assert!(!matches("[*]", "a"));
+        }
+
+        #[test]
+        fn test_any_depth() {
+            assert!(matches("**/*.json", "a.json"));
+            assert!(matches("**/*.json", "a/b/c.json"));
+            assert!(matches("a/**/z", "a/z"));
+            assert!(matches("a/**/z", "a/b/c/z"));
+            assert!(!matches("a/**/z", "b/z"));
+            assert!(matches("**", "a/b"));
+        }
+
+        #[test]
+        fn test_braces() {
+            assert!(matches("*.{json,toml}", "Cargo.toml"));
+            assert!(!matches("*.{json,toml}", "a.yaml"));
+            assert!(matches("{src,tests/{unit,e2e}}/*.rs", "tests/e2e/a.rs"));
+            assert!(matches("{a,b}{1,2}", "b1"));
+            assert!(matches("[{]", "{"));
+            assert_eq!(expand_braces("{a,b}{1,2}").unwrap().len(), 4);
+        }
+
+        #[test]
+        fn test_invalid_patterns() {
+            assert!(matches!(
+                expand_braces("{a,b"),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            assert!(matches!(
+                parse_segment("[a-z", "[a-z"),
+                Err(VfsError::InvalidPath { .. })
+            ));
+        }
+    }
+
+    mod backends {
+        use super::*;
+
+        #[test]
+        fn test_glob_tree() -> Result<()> {
+            let fs = create_fixtures()?;
+
+            assert_eq!(
+                fs.glob("/**/*.json")?,
+                paths(&[
+                    "/.hidden.json",
+                    "/package.json",
+                    "/tests/data/a.json",
+                    "/tests/data/nested/b.json",
+                ])
+            );
+            assert_eq!(
+                fs.glob("/tests/*/*.{json,txt}")?,
+                paths(&["/tests/data/a.json", "/tests/data/c.txt"])
+            );
+            assert_eq!(fs.glob("/src")?, paths(&["/src"]));
+            assert_eq!(fs.glob("/src/*")?, paths(&["/src/lib.rs", "/src/main.rs"]));
+            assert!(fs.glob("/missing/**")?.is_empty());
+            assert!(fs.glob("/package.json/*")?.is_empty());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_glob_relative_to_cwd() -> Result<()> {
+            let mut fs = create_fixtures()?;
+            fs.cd("/tests")?;
+
+            assert_eq!(fs.glob("data/*.json")?, paths(&["/tests/data/a.json"]));
+            assert_eq!(fs.glob("../src/m*")?, paths(&["/src/main.rs"]));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_glob_through_links() -> Result<()> {
+            let mut fs = create_fixtures()?;
+            fs.symlink("/tests/data", "/fixtures")?;
+
+            assert_eq!(fs.glob("/fixtures/*.json")?, paths(&["/fixtures/a.json"]));
+            // links are matched, but not walked
+            assert_eq!(fs.glob("/f*")?, paths(&["/fixtures"]));
+            assert!(
+                !fs.glob("/**/b.json")?
+                    .iter()
+                    .any(|p| p.starts_with("/fixtures"))
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_glob_dir_fs() -> Result<()> {
+            let temp_dir = TempDir::new("glob")?;
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/a/x.json", None)?;
+            fs.mkfile("/a/b/y.json", None)?;
+            // untracked host files are not matched
+            std::fs::write(temp_dir.path().join("a/host.json"), b"")?;
+
+            assert_eq!(fs.glob("**/*.json")?, paths(&["/a/b/y.json", "/a/x.json"]));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_glob_alternatives_of_different_bases() -> Result<()> {
+            let mut fs = create_fixtures()?;
+            fs.symlink("/tests/data", "/fixtures")?;
+
+            assert_eq!(
+                fs.glob("/{src,tests/data,fixtures}/*.{rs,json}")?,
+                paths(&[
+                    "/fixtures/a.json",
+                    "/src/lib.rs",
+                    "/src/main.rs",
+                    "/tests/data/a.json",
+                ])
+            );
+            assert_eq!(
+                fs.glob("/{src,missing}/{m,l}*")?,
+                paths(&["/src/lib.rs", "/src/main.rs"])
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_glob_errors() {
+            let fs = MapFS::new();
+            assert!(matches!(fs.glob(""), Err(VfsError::InvalidPath { .. })));
+            assert!(matches!(
+                fs.glob("/{a,b"),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            assert!(matches!(fs.glob("/[a-"), Err(VfsError::InvalidPath { .. })));
+        }
+    }
+}
@@ -15,7 +15,23 @@
 //! - **Clarity**: Detailed error messages and up-to-date documentation.
 
 mod core;
+mod error;
+mod glob;
 mod vfs;
+mod walk;
 
-pub use core::{FsBackend, Result};
-pub use vfs::{DirFS, Entry, EntryType, MapFS};
+pub use core::{DynFsBackend, FsBackend, Result, copy_between};
+pub use error::VfsError;
+#[cfg(feature = "encryption")]
+pub use vfs::EncryptedFS;
+#[cfg(feature = "tar")]
+pub use vfs::TarFS;
+#[cfg(feature = "zip")]
+pub use vfs::ZipFS;
+#[cfg(feature = "async")]
+pub use vfs::{AsyncAdapter, AsyncFsBackend, AsyncVfsFile};
+pub use vfs::{
+    DirFS, Entry, EntryType, ImportOptions, Inode, InodeId, LogFS, MapFS, Metadata, MountFS,
+    OpenOptions, OverlayFS, SharedFile, SharedFs, SnapshotId, VfsFile,
+};
+pub use walk::{Walk, WalkEntry, WalkIter};
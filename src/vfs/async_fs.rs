@@ -0,0 +1,1073 @@
+//! This module provides `AsyncFsBackend`, an async counterpart of `FsBackend`, together with
+//! its native implementation for `DirFS` (on top of `tokio::fs`) and `AsyncAdapter`, which makes
+//! any sync backend usable from async code.
+//!
+//! Available with the `async` feature.
+//!
+//! ### Key Features:
+//! - **Same model**: paths, errors and VFS state behave exactly as in `FsBackend`.
+//! - **Index-only methods stay sync**: `cd()`, `exists()`, `ls()`, `tree()` etc. never touch
+//!   the storage, so there is nothing to await.
+//! - **Streaming**: `open()` returns an `AsyncRead + AsyncWrite + AsyncSeek` handle, so large
+//!   files can be processed incrementally.
+
+use std::future::Future;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use crate::VfsError;
+use crate::core::{FsBackend, Result, utils};
+use crate::{DirFS, EntryType, Metadata, OpenOptions, VfsFile};
+
+/// Async counterpart of `FsBackend`.
+///
+/// Methods that only consult the VFS index (`root()`, `cwd()`, `to_host()`, `cd()`,
+/// `exists()`, `is_dir()`, `is_file()`, `is_symlink()`, `read_link()`, `canonicalize()`,
+/// `ls()`, `tree()`) are sync; everything that touches the storage returns a `Send` future.
+///
+/// ### Example:
+/// ```no_run
+/// use tokio::io::AsyncWriteExt;
+/// use vfs_kit::{AsyncFsBackend, DirFS, OpenOptions};
+///
+/// # async fn run() -> vfs_kit::Result<()> {
+/// let mut fs = DirFS::new(std::env::temp_dir().join("my_vfs"))?;
+/// fs.mkdir("/logs").await?;
+///
+/// let mut file = fs
+///     .open("/logs/big.log", OpenOptions::new().append(true).create(true))
+///     .await?;
+/// for i in 0..1000 {
+///     file.write_all(format!("line {i}\n").as_bytes()).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub trait AsyncFsBackend {
+    /// Returns root path.
+    fn root(&self) -> &Path;
+
+    /// Returns current working directory related to the vfs root.
+    fn cwd(&self) -> &Path;
+
+    /// Returns a hypothetical "host-path" joining `root` and `inner_path`.
+    fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf>;
+
+    /// Changes the current working directory.
+    fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()>;
+
+    /// Checks if a `path` exists in the vfs.
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool;
+
+    /// Checks if `path` is a directory.
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool>;
+
+    /// Checks if `path` is a regular file.
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool>;
+
+    /// Checks if `path` is a symbolic link (the link itself is not followed).
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool>;
+
+    /// Returns the target of the symbolic link at `path` as it was created.
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf>;
+
+    /// Returns the inner absolute path of `path` with all symbolic links resolved.
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf>;
+
+    /// Returns an iterator over directory entries at a specific depth (shallow listing).
+    fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>>;
+
+    /// Returns a recursive iterator over the directory tree starting from a given path.
+    fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>>;
+
+    /// Returns metadata of the file or directory at `path`.
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> impl Future<Output = Result<Metadata>> + Send;
+
+    /// Sets the permission bits of a file or directory (see `FsBackend::set_permissions()`).
+    fn set_permissions<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        mode: u32,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Creates directory and all it parents (if needed).
+    fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> impl Future<Output = Result<()>> + Send;
+
+    /// Creates new file in vfs.
+    fn mkfile<P: AsRef<Path>>(
+        &mut self,
+        file_path: P,
+        content: Option<&[u8]>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Reads the entire contents of a file into a byte vector.
+    fn read<P: AsRef<Path>>(&self, path: P) -> impl Future<Output = Result<Vec<u8>>> + Send;
+
+    /// Writes bytes to an existing file, replacing its entire contents.
+    fn write<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        content: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Appends bytes to the end of an existing file, preserving its old contents.
+    fn append<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        content: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Opens a file with the given `options` (see `FsBackend::open()`) and returns an async
+    /// handle, which reads and writes the file incrementally.
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> impl Future<Output = Result<impl AsyncVfsFile + '_>> + Send;
+
+    /// Moves a file or a whole directory subtree from `from` to `to`.
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        from: P,
+        to: Q,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Copies a file or a whole directory subtree from `from` to `to`.
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        from: P,
+        to: Q,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Creates a symbolic link at `link` pointing to `target` (see `FsBackend::symlink()`).
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        target: P,
+        link: Q,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Creates a hard link `new` to the file `existing` (see `FsBackend::hard_link()`).
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        existing: P,
+        new: Q,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Removes a file or directory at the specified path.
+    fn rm<P: AsRef<Path>>(&mut self, path: P) -> impl Future<Output = Result<()>> + Send;
+
+    /// Removes all artifacts (dirs and files) in vfs, but preserve its root.
+    fn cleanup(&mut self) -> impl Future<Output = bool> + Send;
+}
+
+/// A file handle returned by `AsyncFsBackend::open()`.
+///
+/// Implemented for everything that is `AsyncRead + AsyncWrite + AsyncSeek + Unpin + Send`, so
+/// the `tokio::io` extension traits can be used on it directly.
+pub trait AsyncVfsFile: AsyncRead + AsyncWrite + AsyncSeek + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + AsyncSeek + Unpin + Send> AsyncVfsFile for T {}
+
+impl AsyncFsBackend for DirFS {
+    fn root(&self) -> &Path {
+        FsBackend::root(self)
+    }
+
+    fn cwd(&self) -> &Path {
+        FsBackend::cwd(self)
+    }
+
+    fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+        FsBackend::to_host(self, inner_path)
+    }
+
+    fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        FsBackend::cd(self, path)
+    }
+
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        FsBackend::exists(self, path)
+    }
+
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        FsBackend::is_dir(self, path)
+    }
+
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        FsBackend::is_file(self, path)
+    }
+
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        FsBackend::is_symlink(self, path)
+    }
+
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        FsBackend::read_link(self, path)
+    }
+
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        FsBackend::canonicalize(self, path)
+    }
+
+    fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        FsBackend::ls(self, path)
+    }
+
+    fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        FsBackend::tree(self, path)
+    }
+
+    /// Returns metadata of `path`, read from the host file system (`tokio::fs::metadata()`).
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> impl Future<Output = Result<Metadata>> + Send {
+        let inner = self.resolve(path, true);
+        async move {
+            let inner = inner?;
+            if !self.is_live(&inner) {
+                return Err(VfsError::NotFound(inner));
+            }
+            let host = FsBackend::to_host(self, &inner)?;
+            Ok(Metadata::from(tokio::fs::metadata(host).await?))
+        }
+    }
+
+    /// Sets the permission bits on the host (`tokio::fs::set_permissions()`).
+    fn set_permissions<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        mode: u32,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let inner = self.resolve(path, true);
+        async move {
+            let inner = inner?;
+            if !self.is_live(&inner) {
+                return Err(VfsError::NotFound(inner));
+            }
+            let host = FsBackend::to_host(self, &inner)?;
+            let permissions = utils::host_permissions(&host, mode)?;
+            tokio::fs::set_permissions(host, permissions).await?;
+            Ok(())
+        }
+    }
+
+    fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> impl Future<Output = Result<()>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move {
+            if path.as_os_str().is_empty() {
+                return Err(VfsError::invalid_path("", "empty"));
+            }
+            let inner_path = self.resolve(path, false)?;
+            if self.is_live(&inner_path) {
+                return Err(VfsError::AlreadyExists(inner_path));
+            }
+
+            // Missing directories, from the target up to the first existing parent
+            let missing: Vec<PathBuf> = inner_path
+                .ancestors()
+                .take_while(|&dir| !FsBackend::exists(self, dir))
+                .map(Path::to_path_buf)
+                .collect();
+            for dir in missing.into_iter().rev() {
+                tokio::fs::create_dir(FsBackend::to_host(self, &dir)?).await?;
+                self.track(&dir, EntryType::Directory);
+            }
+            Ok(())
+        }
+    }
+
+    fn mkfile<P: AsRef<Path>>(
+        &mut self,
+        file_path: P,
+        content: Option<&[u8]>,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let file_path = self.resolve(file_path, false);
+        async move {
+            let file_path = file_path?;
+            if self.is_live(&file_path) {
+                return Err(VfsError::AlreadyExists(file_path));
+            }
+            if let Some(parent) = file_path.parent()
+                && !FsBackend::exists(self, parent)
+            {
+                AsyncFsBackend::mkdir(self, parent).await?;
+            }
+            let host = FsBackend::to_host(self, &file_path)?;
+            let mut fd = tokio::fs::File::create(host).await?;
+            self.track(&file_path, EntryType::File);
+            if let Some(content) = content {
+                fd.write_all(content).await?;
+                // tokio completes writes in the background, wait for them
+                fd.flush().await?;
+            }
+            Ok(())
+        }
+    }
+
+    fn read<P: AsRef<Path>>(&self, path: P) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        let inner = self.resolve(path, true);
+        async move {
+            let inner = inner?;
+            if FsBackend::is_dir(self, &inner)? {
+                // checks for existent too
+                return Err(VfsError::IsADirectory(inner));
+            }
+            Ok(tokio::fs::read(FsBackend::to_host(self, &inner)?).await?)
+        }
+    }
+
+    fn write<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        content: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send {
+        let inner = self.resolve(path, true);
+        async move {
+            let inner = inner?;
+            if FsBackend::is_dir(self, &inner)? {
+                // checks for existent too
+                return Err(VfsError::IsADirectory(inner));
+            }
+            tokio::fs::write(FsBackend::to_host(self, &inner)?, content).await?;
+            Ok(())
+        }
+    }
+
+    fn append<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        content: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send {
+        let inner = self.resolve(path, true);
+        async move {
+            let inner = inner?;
+            if FsBackend::is_dir(self, &inner)? {
+                // checks for existent too
+                return Err(VfsError::IsADirectory(inner));
+            }
+            let mut file = tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(FsBackend::to_host(self, &inner)?)
+                .await?;
+            file.write_all(content).await?;
+            file.flush().await?;
+            Ok(())
+        }
+    }
+
+    /// Opens a file with the given `options` and returns the underlying `tokio::fs::File`.
+    /// A created file is tracked by VFS the same way `mkfile()` does it.
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> impl Future<Output = Result<impl AsyncVfsFile + '_>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move {
+            if path.as_os_str().is_empty() {
+                return Err(VfsError::invalid_path("", "empty"));
+            }
+            let inner = self.resolve(path, true)?;
+            options.check(&inner)?;
+
+            if FsBackend::exists(self, &inner) {
+                if options.is_create_new() {
+                    return Err(VfsError::AlreadyExists(inner));
+                }
+                if FsBackend::is_dir(self, &inner)? {
+                    return Err(VfsError::IsADirectory(inner));
+                }
+            } else if options.is_create() || options.is_create_new() {
+                AsyncFsBackend::mkfile(self, &inner, None).await?;
+            } else {
+                return Err(VfsError::NotFound(inner));
+            }
+
+            let file = tokio::fs::OpenOptions::new()
+                .read(options.is_readable())
+                .write(options.is_writable())
+                .append(options.is_append())
+                .truncate(options.is_truncate())
+                .open(FsBackend::to_host(self, &inner)?)
+                .await?;
+            Ok(file)
+        }
+    }
+
+    /// Moves a file or a whole directory subtree with `tokio::fs::rename()`
+    /// (see `FsBackend::rename()` for the rules).
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        from: P,
+        to: Q,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let transfer = utils::check_transfer(self, from, to);
+        async move {
+            let (from, to) = transfer?;
+            let host_to = FsBackend::to_host(self, &to)?;
+            if tokio::fs::try_exists(&host_to).await? {
+                return Err(VfsError::AlreadyExists(to));
+            }
+            if let Some(parent) = to.parent()
+                && !FsBackend::exists(self, parent)
+            {
+                AsyncFsBackend::mkdir(self, parent).await?;
+            }
+            tokio::fs::rename(FsBackend::to_host(self, &from)?, host_to).await?;
+            self.retrack(&from, &to);
+            Ok(())
+        }
+    }
+
+    /// Copies a file or a whole directory subtree, files are copied with `tokio::fs::copy()`
+    /// (see `FsBackend::copy()` for the rules).
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        from: P,
+        to: Q,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let transfer = utils::check_transfer(self, from, to)
+            .and_then(|(from, to)| Ok((utils::subtree(self, &from)?, from, to)));
+        async move {
+            let (paths, from, to) = transfer?;
+            for path in paths {
+                let target = utils::rebase(&path, &from, &to);
+                if FsBackend::is_symlink(self, &path)? {
+                    let link_target = FsBackend::read_link(self, &path)?;
+                    AsyncFsBackend::symlink(self, link_target, &target).await?;
+                } else if FsBackend::is_dir(self, &path)? {
+                    AsyncFsBackend::mkdir(self, &target).await?;
+                } else {
+                    AsyncFsBackend::mkfile(self, &target, None).await?;
+                    let host_from = FsBackend::to_host(self, &path)?;
+                    tokio::fs::copy(host_from, FsBackend::to_host(self, &target)?).await?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Creates a symbolic link on the host (see `FsBackend::symlink()` of `DirFS`).
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        target: P,
+        link: Q,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let target = target.as_ref().to_path_buf();
+        let link = link.as_ref().to_path_buf();
+        async move {
+            if link.as_os_str().is_empty() {
+                return Err(VfsError::invalid_path("", "empty"));
+            }
+            let link = self.resolve(link, false)?;
+            if self.is_live(&link) {
+                return Err(VfsError::AlreadyExists(link));
+            }
+            let parent = link.parent().unwrap(); // safe unwrap(): the root always exists
+            let resolved = utils::link_target(parent, &target)?;
+            let (host_target, host_link) = self.host_link(&target, &link)?;
+            if tokio::fs::symlink_metadata(&host_link).await.is_ok() {
+                return Err(VfsError::AlreadyExists(link));
+            }
+            if !FsBackend::exists(self, parent) {
+                AsyncFsBackend::mkdir(self, parent).await?;
+            }
+            #[cfg(unix)]
+            tokio::fs::symlink(&host_target, &host_link).await?;
+            #[cfg(windows)]
+            if FsBackend::is_dir(self, &resolved).unwrap_or(false) {
+                tokio::fs::symlink_dir(&host_target, &host_link).await?;
+            } else {
+                tokio::fs::symlink_file(&host_target, &host_link).await?;
+            }
+            #[cfg(not(windows))]
+            let _ = resolved;
+            self.track_link(&link, &target);
+            Ok(())
+        }
+    }
+
+    /// Creates a hard link on the host (see `FsBackend::hard_link()` of `DirFS`).
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        existing: P,
+        new: Q,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let existing = self.resolve(existing, true);
+        let new = new.as_ref().to_path_buf();
+        async move {
+            let existing = existing?;
+            if new.as_os_str().is_empty() {
+                return Err(VfsError::invalid_path("", "empty"));
+            }
+            if FsBackend::is_dir(self, &existing)? {
+                // checks for existent too
+                return Err(VfsError::IsADirectory(existing));
+            }
+            let new = self.resolve(new, false)?;
+            if self.is_live(&new) {
+                return Err(VfsError::AlreadyExists(new));
+            }
+            if let Some(parent) = new.parent()
+                && !FsBackend::exists(self, parent)
+            {
+                AsyncFsBackend::mkdir(self, parent).await?;
+            }
+            let host_existing = FsBackend::to_host(self, &existing)?;
+            tokio::fs::hard_link(host_existing, FsBackend::to_host(self, &new)?).await?;
+            self.track(&new, EntryType::File);
+            Ok(())
+        }
+    }
+
+    fn rm<P: AsRef<Path>>(&mut self, path: P) -> impl Future<Output = Result<()>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move {
+            if path.as_os_str().is_empty() {
+                return Err(VfsError::invalid_path("", "empty"));
+            }
+            if utils::is_virtual_root(&path) {
+                return Err(VfsError::RootProtected(PathBuf::from("/")));
+            }
+            let inner_path = self.resolve(path, false)?; // a link is removed, not its target
+            if !self.is_live(&inner_path) {
+                return Err(VfsError::NotFound(inner_path));
+            }
+            let host_path = FsBackend::to_host(self, &inner_path)?;
+            if tokio::fs::symlink_metadata(&host_path).await.is_ok() {
+                rm_on_host(&host_path).await?;
+            }
+            self.untrack(&inner_path);
+            Ok(())
+        }
+    }
+
+    async fn cleanup(&mut self) -> bool {
+        let mut is_ok = true;
+
+        // Children go first, so every directory is empty (or already gone) when reached
+        let paths: Vec<PathBuf> = FsBackend::tree(self, "/")
+            .map(|paths| paths.map(Path::to_path_buf).collect())
+            .unwrap_or_default();
+        for path in paths.into_iter().rev() {
+            if let Ok(host) = FsBackend::to_host(self, &path) {
+                if rm_on_host(&host).await.is_ok() {
+                    self.untrack(&path);
+                } else {
+                    is_ok = false;
+                    eprintln!("Unable to remove: {}", host.display());
+                }
+            }
+        }
+
+        is_ok
+    }
+}
+
+/// Removes a file or a directory with all its contents from the host file system.
+async fn rm_on_host(host_path: &Path) -> io::Result<()> {
+    if tokio::fs::symlink_metadata(host_path).await?.is_dir() {
+        tokio::fs::remove_dir_all(host_path).await
+    } else {
+        tokio::fs::remove_file(host_path).await
+    }
+}
+
+/// Makes any sync `FsBackend` usable as `AsyncFsBackend`.
+///
+/// Every operation runs the corresponding sync method inline, when its future is polled, so
+/// the adapter suits in-memory backends (`MapFS`, `OverlayFS` over them, etc.), which never
+/// block for long. Wrap `DirFS` directly instead, it implements `AsyncFsBackend` natively.
+///
+/// File handles returned by `open()` reopen the file on every read, write or seek and keep
+/// only the current position, so they do not hold a borrow of the file contents between calls.
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{AsyncAdapter, AsyncFsBackend, MapFS};
+///
+/// # async fn run() -> vfs_kit::Result<()> {
+/// let mut fs = AsyncAdapter::new(MapFS::new());
+/// fs.mkfile("/note.txt", Some(b"Hello")).await?;
+/// assert_eq!(fs.read("/note.txt").await?, b"Hello");
+///
+/// let fs: MapFS = fs.into_inner();
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncAdapter<B> {
+    inner: B,
+}
+
+impl<B: FsBackend> AsyncAdapter<B> {
+    /// Wraps the sync `inner` backend.
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+
+    /// Returns a reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped backend.
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    /// Unwraps the adapter, returning the wrapped backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: FsBackend + Send + Sync> AsyncFsBackend for AsyncAdapter<B> {
+    fn root(&self) -> &Path {
+        self.inner.root()
+    }
+
+    fn cwd(&self) -> &Path {
+        self.inner.cwd()
+    }
+
+    fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+        self.inner.to_host(inner_path)
+    }
+
+    fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.inner.cd(path)
+    }
+
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.inner.is_dir(path)
+    }
+
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.inner.is_file(path)
+    }
+
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.inner.is_symlink(path)
+    }
+
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        self.inner.read_link(path)
+    }
+
+    fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        self.inner.canonicalize(path)
+    }
+
+    fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        self.inner.ls(path)
+    }
+
+    fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        self.inner.tree(path)
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> impl Future<Output = Result<Metadata>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move { self.inner.metadata(path) }
+    }
+
+    fn set_permissions<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        mode: u32,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move { self.inner.set_permissions(path, mode) }
+    }
+
+    fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> impl Future<Output = Result<()>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move { self.inner.mkdir(path) }
+    }
+
+    fn mkfile<P: AsRef<Path>>(
+        &mut self,
+        file_path: P,
+        content: Option<&[u8]>,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let file_path = file_path.as_ref().to_path_buf();
+        async move { self.inner.mkfile(file_path, content) }
+    }
+
+    fn read<P: AsRef<Path>>(&self, path: P) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move { self.inner.read(path) }
+    }
+
+    fn write<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        content: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move { self.inner.write(path, content) }
+    }
+
+    fn append<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        content: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move { self.inner.append(path, content) }
+    }
+
+    /// Applies `options` by opening the file once with the sync backend, then returns a handle
+    /// which reopens it (without `create`/`truncate`) on every operation.
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> impl Future<Output = Result<impl AsyncVfsFile + '_>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move {
+            self.inner.open(&path, options).map(drop)?;
+            let path = self.inner.canonicalize(path)?;
+            let mut options = options.clone();
+            options.create(false).create_new(false).truncate(false);
+            Ok(AdapterFile {
+                fs: &mut self.inner,
+                path,
+                pos: 0,
+                options,
+            })
+        }
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        from: P,
+        to: Q,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (from, to) = (from.as_ref().to_path_buf(), to.as_ref().to_path_buf());
+        async move { self.inner.rename(from, to) }
+    }
+
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        from: P,
+        to: Q,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (from, to) = (from.as_ref().to_path_buf(), to.as_ref().to_path_buf());
+        async move { self.inner.copy(from, to) }
+    }
+
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        target: P,
+        link: Q,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (target, link) = (target.as_ref().to_path_buf(), link.as_ref().to_path_buf());
+        async move { self.inner.symlink(target, link) }
+    }
+
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        existing: P,
+        new: Q,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let (existing, new) = (existing.as_ref().to_path_buf(), new.as_ref().to_path_buf());
+        async move { self.inner.hard_link(existing, new) }
+    }
+
+    fn rm<P: AsRef<Path>>(&mut self, path: P) -> impl Future<Output = Result<()>> + Send {
+        let path = path.as_ref().to_path_buf();
+        async move { self.inner.rm(path) }
+    }
+
+    async fn cleanup(&mut self) -> bool {
+        self.inner.cleanup()
+    }
+}
+
+/// Async file handle of `AsyncAdapter`: remembers the position and performs every operation
+/// on a freshly opened sync handle.
+struct AdapterFile<'a, B: FsBackend> {
+    fs: &'a mut B,
+    path: PathBuf, // inner absolute normalized path
+    pos: u64,
+    options: OpenOptions,
+}
+
+impl<B: FsBackend> AdapterFile<'_, B> {
+    /// Opens the file at the current position, runs `op` on it and remembers the new position.
+    fn with_file<T>(
+        &mut self,
+        op: impl FnOnce(&mut dyn VfsFile) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut file = self.fs.open(&self.path, &self.options)?;
+        file.seek(SeekFrom::Start(self.pos))?;
+        let result = op(&mut file)?;
+        file.flush()?;
+        self.pos = file.stream_position()?;
+        Ok(result)
+    }
+}
+
+impl<B: FsBackend> AsyncRead for AdapterFile<'_, B> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let result = self.get_mut().with_file(|file| {
+            let n = file.read(buf.initialize_unfilled())?;
+            buf.advance(n);
+            Ok(())
+        });
+        Poll::Ready(result)
+    }
+}
+
+impl<B: FsBackend> AsyncWrite for AdapterFile<'_, B> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.get_mut().with_file(|file| file.write(buf)))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // Every write is flushed immediately
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<B: FsBackend> AsyncSeek for AdapterFile<'_, B> {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        self.get_mut()
+            .with_file(|file| file.seek(position))
+            .map(drop)
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MapFS;
+    use tempdir::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    mod dir_fs {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_dir_fs_basic_operations() -> Result<()> {
+            let temp_dir = TempDir::new("async")?;
+            let mut fs = DirFS::new(temp_dir.path())?;
+
+            AsyncFsBackend::mkdir(&mut fs, "/a/b").await?;
+            assert!(AsyncFsBackend::is_dir(&fs, "/a")?);
+            assert!(temp_dir.path().join("a/b").is_dir());
+
+            AsyncFsBackend::mkfile(&mut fs, "/a/b/f.txt", Some(b"Hello")).await?;
+            AsyncFsBackend::append(&mut fs, "/a/b/f.txt", b", world").await?;
+            assert_eq!(
+                AsyncFsBackend::read(&fs, "/a/b/f.txt").await?,
+                b"Hello, world"
+            );
+            assert_eq!(AsyncFsBackend::metadata(&fs, "/a/b/f.txt").await?.len(), 12);
+
+            AsyncFsBackend::write(&mut fs, "/a/b/f.txt", b"Bye").await?;
+            AsyncFsBackend::copy(&mut fs, "/a", "/c").await?;
+            AsyncFsBackend::rename(&mut fs, "/a", "/d").await?;
+            assert!(!AsyncFsBackend::exists(&fs, "/a"));
+            assert_eq!(AsyncFsBackend::read(&fs, "/c/b/f.txt").await?, b"Bye");
+            assert_eq!(AsyncFsBackend::read(&fs, "/d/b/f.txt").await?, b"Bye");
+
+            AsyncFsBackend::rm(&mut fs, "/d").await?;
+            assert!(!temp_dir.path().join("d").exists());
+            assert!(AsyncFsBackend::cleanup(&mut fs).await);
+            assert_eq!(AsyncFsBackend::tree(&fs, "/")?.count(), 0);
+            assert_eq!(std::fs::read_dir(temp_dir.path())?.count(), 0);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_dir_fs_errors() -> Result<()> {
+            let temp_dir = TempDir::new("async")?;
+            let mut fs = DirFS::new(temp_dir.path())?;
+            AsyncFsBackend::mkdir(&mut fs, "/dir").await?;
+
+            assert!(matches!(
+                AsyncFsBackend::read(&fs, "/missing").await,
+                Err(VfsError::NotFound(_))
+            ));
+            assert!(matches!(
+                AsyncFsBackend::read(&fs, "/dir").await,
+                Err(VfsError::IsADirectory(_))
+            ));
+            assert!(matches!(
+                AsyncFsBackend::mkdir(&mut fs, "/dir").await,
+                Err(VfsError::AlreadyExists(_))
+            ));
+            assert!(matches!(
+                AsyncFsBackend::rm(&mut fs, "/").await,
+                Err(VfsError::RootProtected(_))
+            ));
+            assert!(matches!(
+                AsyncFsBackend::rename(&mut fs, "/dir", "/dir/sub").await,
+                Err(VfsError::InvalidPath { .. })
+            ));
+
+            Ok(())
+        }
+
+        #[cfg(unix)]
+        #[tokio::test]
+        async fn test_dir_fs_symlinks() -> Result<()> {
+            let temp_dir = TempDir::new("async")?;
+            let mut fs = DirFS::new(temp_dir.path())?;
+            AsyncFsBackend::mkfile(&mut fs, "/data/f.txt", Some(b"data")).await?;
+
+            AsyncFsBackend::symlink(&mut fs, "data", "/link").await?;
+            assert!(AsyncFsBackend::is_symlink(&fs, "/link")?);
+            assert!(temp_dir.path().join("link").is_symlink());
+            assert_eq!(AsyncFsBackend::read(&fs, "/link/f.txt").await?, b"data");
+            assert_eq!(
+                AsyncFsBackend::canonicalize(&fs, "/link/f.txt")?,
+                Path::new("/data/f.txt")
+            );
+
+            AsyncFsBackend::copy(&mut fs, "/link", "/copy").await?;
+            assert_eq!(AsyncFsBackend::read_link(&fs, "/copy")?, Path::new("data"));
+
+            AsyncFsBackend::rm(&mut fs, "/link").await?;
+            assert!(!temp_dir.path().join("link").is_symlink());
+            assert!(temp_dir.path().join("data/f.txt").exists());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_dir_fs_streaming() -> Result<()> {
+            let temp_dir = TempDir::new("async")?;
+            let mut fs = DirFS::new(temp_dir.path())?;
+
+            let options = OpenOptions::new().write(true).create_new(true).clone();
+            let mut file = AsyncFsBackend::open(&mut fs, "/big.bin", &options).await?;
+            for i in 0..100u8 {
+                file.write_all(&[i; 1024]).await?;
+            }
+            file.flush().await?;
+            drop(file);
+            assert!(AsyncFsBackend::is_file(&fs, "/big.bin")?);
+
+            let options = OpenOptions::new().read(true).clone();
+            let mut file = AsyncFsBackend::open(&mut fs, "/big.bin", &options).await?;
+            file.seek(SeekFrom::Start(50 * 1024)).await?;
+            let mut chunk = vec![0; 1024];
+            file.read_exact(&mut chunk).await?;
+            assert_eq!(chunk, [50; 1024]);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_dir_fs_open_options() -> Result<()> {
+            let temp_dir = TempDir::new("async")?;
+            let mut fs = DirFS::new(temp_dir.path())?;
+            AsyncFsBackend::mkfile(&mut fs, "/f.txt", Some(b"Hello")).await?;
+
+            let options = OpenOptions::new().write(true).truncate(true).clone();
+            AsyncFsBackend::open(&mut fs, "/f.txt", &options).await?;
+            assert!(AsyncFsBackend::read(&fs, "/f.txt").await?.is_empty());
+
+            let options = OpenOptions::new().write(true).create_new(true).clone();
+            assert!(matches!(
+                AsyncFsBackend::open(&mut fs, "/f.txt", &options).await,
+                Err(VfsError::AlreadyExists(_))
+            ));
+            let options = OpenOptions::new().read(true).clone();
+            assert!(matches!(
+                AsyncFsBackend::open(&mut fs, "/missing", &options).await,
+                Err(VfsError::NotFound(_))
+            ));
+
+            Ok(())
+        }
+    }
+
+    mod adapter {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_adapter_delegates_to_inner() -> Result<()> {
+            let mut fs = AsyncAdapter::new(MapFS::new());
+            fs.mkfile("/a/f.txt", Some(b"Hello")).await?;
+            fs.append("/a/f.txt", b"!").await?;
+            fs.copy("/a", "/b").await?;
+            fs.rename("/b/f.txt", "/b/g.txt").await?;
+
+            assert_eq!(fs.read("/b/g.txt").await?, b"Hello!");
+            assert!(fs.metadata("/a").await?.is_dir());
+            assert_eq!(fs.ls("/b")?.collect::<Vec<_>>(), [Path::new("/b/g.txt")]);
+
+            fs.rm("/a").await?;
+            let inner = fs.into_inner();
+            assert!(!inner.exists("/a"));
+            assert!(inner.exists("/b/g.txt"));
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_adapter_streaming() -> Result<()> {
+            let mut fs = AsyncAdapter::new(MapFS::new());
+            fs.mkfile("/f.txt", Some(b"Hello, world")).await?;
+
+            let options = OpenOptions::new().read(true).write(true).clone();
+            let mut file = fs.open("/f.txt", &options).await?;
+            file.seek(SeekFrom::Start(7)).await?;
+            file.write_all(b"there").await?;
+            file.seek(SeekFrom::Start(0)).await?;
+            let mut content = String::new();
+            file.read_to_string(&mut content).await?;
+            drop(file);
+
+            assert_eq!(content, "Hello, there");
+            assert_eq!(fs.read("/f.txt").await?, b"Hello, there");
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_adapter_open_applies_options_once() -> Result<()> {
+            let mut fs = AsyncAdapter::new(MapFS::new());
+
+            let options = OpenOptions::new().append(true).create(true).clone();
+            let mut file = fs.open("/log.txt", &options).await?;
+            file.write_all(b"one\n").await?;
+            file.write_all(b"two\n").await?;
+            drop(file);
+            assert_eq!(fs.read("/log.txt").await?, b"one\ntwo\n");
+
+            let options = OpenOptions::new().read(true).clone();
+            let mut file = fs.open("/log.txt", &options).await?;
+            assert!(file.write_all(b"three").await.is_err());
+
+            Ok(())
+        }
+    }
+}
@@ -13,10 +13,14 @@ use std::collections::BTreeMap;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
-use anyhow::anyhow;
-
+use crate::VfsError;
 use crate::core::{FsBackend, Result, utils};
-use crate::{Entry, EntryType};
+use crate::vfs::file::prepare_open;
+use crate::{Entry, EntryType, Metadata, OpenOptions, VfsFile};
+
+mod journal;
+
+use journal::{Journal, Transaction};
 
 /// A virtual filesystem (VFS) implementation that maps to a real directory on the host system.
 ///
@@ -25,10 +29,11 @@ use crate::{Entry, EntryType};
 /// all operations defined in `FsBackend` trait.
 ///
 /// ### Usage notes:
-/// - `DirFS` does not follow symlinks; `rm()` removes the link, not the target.
+/// - Host symlinks are tracked as links and followed only inside the root; `rm()` removes
+///   the link, not the target.
 /// - Permissions are not automatically adjusted; ensure `root` is writable.
-/// - Not thread‑safe in current version (wrap in `Mutex` if needed).
-/// - Errors are returned via `anyhow::Result` with descriptive messages.
+/// - Not thread‑safe by itself (share it between threads with `SharedFs`).
+/// - Errors are returned as `VfsError` carrying the offending path.
 ///
 /// ### Example:
 /// ```
@@ -50,25 +55,32 @@ pub struct DirFS {
     entries: BTreeMap<PathBuf, Entry>,  // inner absolute normalized paths
     created_root_parents: Vec<PathBuf>, // host-related absolute normalized paths
     is_auto_clean: bool,
+    journal: Option<Journal>, // changes of the running transaction
 }
 
 impl DirFS {
     /// Creates a new DirFs instance with the root directory at `path`.
     /// Checks permissions to create and write into `path`.
+    /// A transaction interrupted in `path` is completed if it was committed, otherwise
+    /// its staged changes are dropped (see `transaction()`).
     /// * `path` is an absolute host path. If path not exists it will be created.
+    ///
     /// If `path` is not absolute or path is not a directory, error returns.
     /// By default, the `is_auto_clean` flag is set to `true`.
     pub fn new<P: AsRef<Path>>(root: P) -> Result<Self> {
         let root = root.as_ref();
 
         if root.as_os_str().is_empty() {
-            return Err(anyhow!("invalid root path: empty"));
+            return Err(VfsError::invalid_path("", "empty"));
         }
         if root.is_relative() {
-            return Err(anyhow!("the root path must be absolute"));
+            return Err(VfsError::invalid_path(
+                root,
+                "the root path must be absolute",
+            ));
         }
         if root.exists() && !root.is_dir() {
-            return Err(anyhow!("{:?} is not a directory", root));
+            return Err(VfsError::NotADirectory(root.to_path_buf()));
         }
 
         let root = utils::normalize(root);
@@ -80,15 +92,19 @@ impl DirFS {
 
         // check permissions
         if !Self::check_permissions(&root) {
-            return Err(anyhow!("Access denied: {:?}", root));
+            return Err(VfsError::PermissionDenied(root));
         }
 
+        // finish a transaction interrupted by a crash
+        journal::recover(&root)?;
+
         Ok(Self {
             root,
             cwd: PathBuf::from("/"),
             entries: BTreeMap::new(),
             created_root_parents,
             is_auto_clean: true,
+            journal: None,
         })
     }
 
@@ -99,19 +115,64 @@ impl DirFS {
         self.is_auto_clean = clean;
     }
 
+    /// Runs `f` as a transaction: the changes it makes through `FsBackend` methods are applied
+    /// all-or-nothing. If `f` returns an error or panics, every change is rolled back and the
+    /// host directory is left as it was; otherwise the changes are committed.
+    ///
+    /// The host tree is not touched until the commit: new and changed files and new directories
+    /// are staged in the hidden directory `.vfs-kit-transaction` under the root, and the changes
+    /// are recorded. A commit saves them into a manifest there and then applies them, moving the
+    /// staged entries into place with renames; a rollback just drops the directory. If the process
+    /// dies mid-commit, the next `DirFS::new()` (or transaction) on the root completes it;
+    /// a transaction which did not reach its commit is discarded. So the root must not be used
+    /// by another `DirFS` while a transaction runs.
+    ///
+    /// Until the commit, `metadata()` of entries which are not staged shows them as they are on
+    /// the host, and a hard link created in the transaction shows the contents of the file at the
+    /// moment of linking.
+    ///
+    /// Transactions may be nested: a failed inner transaction rolls back its own changes only.
+    ///
+    /// ### Example:
+    /// ```no_run
+    /// use vfs_kit::{DirFS, FsBackend};
+    ///
+    /// let mut fs = DirFS::new(std::env::temp_dir().join("generated")).unwrap();
+    /// fs.transaction(|fs| {
+    ///     fs.mkfile("/src/lib.rs", Some(b"mod api;"))?;
+    ///     fs.mkfile("/src/api.rs", Some(b"pub fn call() {}"))
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn transaction<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let tx = Transaction::begin(self)?;
+        match f(tx.fs) {
+            Ok(value) => tx.commit().map(|_| value),
+            Err(err) => match tx.rollback() {
+                Ok(()) => Err(err),
+                Err(rollback_err) => Err(VfsError::other(format!(
+                    "{err}; the rollback failed as well: {rollback_err}"
+                ))),
+            },
+        }
+    }
+
     /// Adds an existing artifact (file or directory) to the VFS.
     /// The artifact must exist and be located in the VFS root directory.
     /// If artifact is directory - all its childs will be added recursively.
     /// Once added, it will be managed by the VFS (e.g., deleted upon destruction).
     /// * `path` is an inner VFS path.
+    ///
+    /// Host symbolic links are added as links (never followed). A link pointing outside the
+    /// root is refused with an error.
     pub fn add<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        let inner = self.to_inner(&path);
+        let inner = self.resolve(&path, false)?;
         let host = self.to_host(&inner)?;
-        if !host.exists() {
-            return Err(anyhow!(
-                "No such file or directory: {}",
-                path.as_ref().display()
-            ));
+        if !host.exists() && !host.is_symlink() {
+            return Err(VfsError::NotFound(inner));
         }
         self.add_recursive(&inner, &host)
     }
@@ -129,7 +190,7 @@ impl DirFS {
     /// # Returns
     ///
     /// * `Ok(())` - If the path was successfully removed (or was not tracked in the first place).
-    /// * `Err(anyhow::Error)` - If:
+    /// * `Err(VfsError)` - If:
     ///   * The path is not tracked by the VFS.
     ///   * The path is the root directory (`/`), which cannot be forgotten.
     ///
@@ -170,27 +231,26 @@ impl DirFS {
     /// * If the path does not exist in the VFS, the method returns an error
     ///   (unlike `remove` in some systems that may silently succeed).
     pub fn forget<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        let inner = self.to_inner(&path);
-        if !self.exists(&inner) {
-            return Err(anyhow!("{:?} path is not tracked by VFS", path.as_ref()));
+        let inner = self.resolve(&path, false)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
         }
         if utils::is_virtual_root(&inner) {
-            return Err(anyhow!("cannot forget root directory"));
+            return Err(VfsError::RootProtected(inner));
         }
 
-        if let Some(entry) = self.entries.remove(&inner) {
-            if entry.is_dir() {
-                let childs: Vec<_> = self
-                    .entries
-                    .iter()
-                    .map(|(path, _)| path)
-                    .filter(|&path| path.starts_with(&inner))
-                    .cloned()
-                    .collect();
+        if let Some(entry) = self.entries.remove(&inner)
+            && entry.is_dir()
+        {
+            let childs: Vec<_> = self
+                .entries
+                .keys()
+                .filter(|&path| path.starts_with(&inner))
+                .cloned()
+                .collect();
 
-                for child in childs {
-                    self.entries.remove(&child);
-                }
+            for child in childs {
+                self.entries.remove(&child);
             }
         }
 
@@ -201,8 +261,90 @@ impl DirFS {
         utils::normalize(self.cwd.join(inner_path))
     }
 
+    /// Converts `path` into an inner absolute path with symbolic links resolved
+    /// (see `utils::resolve()`).
+    pub(crate) fn resolve<P: AsRef<Path>>(&self, path: P, follow_last: bool) -> Result<PathBuf> {
+        utils::resolve(&self.to_inner(path), follow_last, |inner| {
+            Ok(self
+                .entries
+                .get(inner)
+                .and_then(Entry::link_target)
+                .map(Path::to_path_buf))
+        })
+    }
+
+    /// Checks if the resolved `inner_path` is tracked (a link is not followed).
+    pub(crate) fn is_live(&self, inner_path: &Path) -> bool {
+        utils::is_virtual_root(inner_path) || self.entries.contains_key(inner_path)
+    }
+
+    /// Returns the host path keeping the contents of the tracked `inner_path`, which is
+    /// a staged copy for an entry changed in a running transaction.
+    fn source(&self, inner_path: &Path) -> Result<PathBuf> {
+        match &self.journal {
+            Some(journal) => Ok(journal.source(inner_path)),
+            None => self.to_host(inner_path),
+        }
+    }
+
+    /// Checks if something exists at `inner_path` on the host, not counting what a running
+    /// transaction has removed or moved away.
+    fn is_on_host(&self, inner_path: &Path) -> Result<bool> {
+        let host = self.to_host(inner_path)?;
+        let exists = host.is_symlink() || std::fs::exists(&host)?;
+        Ok(exists
+            && !self
+                .journal
+                .as_ref()
+                .is_some_and(|journal| journal.is_vacated(inner_path)))
+    }
+
+    /// Creates a host link at `host_link` pointing to the VFS `target` of a link at `link`
+    /// (absolute targets are converted into host paths).
+    pub(crate) fn host_link(&self, target: &Path, link: &Path) -> Result<(PathBuf, PathBuf)> {
+        let host_target = if target.is_absolute() {
+            self.to_host(target)?
+        } else {
+            target.to_path_buf()
+        };
+        Ok((host_target, self.to_host(link)?))
+    }
+
+    /// Starts tracking `inner_path` (already created on the host).
+    pub(crate) fn track(&mut self, inner_path: &Path, entry_type: EntryType) {
+        self.entries
+            .insert(inner_path.to_path_buf(), Entry::new(entry_type));
+    }
+
+    /// Starts tracking a symbolic link at `inner_path` pointing to `target`.
+    pub(crate) fn track_link(&mut self, inner_path: &Path, target: &Path) {
+        self.entries
+            .insert(inner_path.to_path_buf(), Entry::new_symlink(target));
+    }
+
+    /// Stops tracking `inner_path` and all its descendants.
+    pub(crate) fn untrack(&mut self, inner_path: &Path) {
+        self.entries.retain(|path, _| !path.starts_with(inner_path));
+    }
+
+    /// Moves tracked `from` and all its descendants (and CWD, if it is inside) to `to`.
+    pub(crate) fn retrack(&mut self, from: &Path, to: &Path) {
+        let moved: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|&pb| pb.starts_with(from))
+            .cloned()
+            .collect();
+        for path in moved {
+            let entry = self.entries.remove(&path).unwrap(); // safe unwrap()
+            self.entries.insert(utils::rebase(&path, from, to), entry);
+        }
+        self.cwd = utils::rebase(&self.cwd, from, to);
+    }
+
     /// Make directories recursively.
     /// * `path` is an absolute host path.
+    ///
     /// Returns vector of created directories.
     fn mkdir_all<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
         let host_path = path.as_ref().to_path_buf();
@@ -241,17 +383,49 @@ impl DirFS {
     fn check_permissions<P: AsRef<Path>>(path: P) -> bool {
         let path = path.as_ref();
         let filename = path.join(".access");
-        if let Err(_) = std::fs::write(&filename, b"check") {
+        if std::fs::write(&filename, b"check").is_err() {
             return false;
         }
-        if let Err(_) = std::fs::remove_file(filename) {
+        if std::fs::remove_file(filename).is_err() {
             return false;
         }
         true
     }
 
+    /// Converts the target of the host link at `host_path` into a VFS link target: relative
+    /// targets are kept as is, absolute ones are made relative to the root.
+    ///
+    /// An error is returned if the target is outside the root.
+    fn link_from_host(&self, inner_path: &Path, host_path: &Path) -> Result<PathBuf> {
+        let target = std::fs::read_link(host_path)?;
+        let target = if target.is_absolute() {
+            let Ok(rest) = utils::normalize(&target)
+                .strip_prefix(&self.root)
+                .map(Path::to_path_buf)
+            else {
+                return Err(VfsError::invalid_path(
+                    inner_path,
+                    "the link points outside the root",
+                ));
+            };
+            Path::new("/").join(rest)
+        } else {
+            target
+        };
+        let parent = inner_path.parent().unwrap_or(Path::new("/"));
+        utils::link_target(parent, &target)?;
+        Ok(target)
+    }
+
     /// Recursively adds a directory and all its entries to the VFS.
+    /// Symbolic links are added as links, their targets are not walked.
     fn add_recursive(&mut self, inner_path: &Path, host_path: &Path) -> Result<()> {
+        if host_path.is_symlink() {
+            let target = self.link_from_host(inner_path, host_path)?;
+            self.entries
+                .insert(inner_path.to_path_buf(), Entry::new_symlink(target));
+            return Ok(());
+        }
         let entry_type = if host_path.is_dir() {
             EntryType::Directory
         } else {
@@ -294,11 +468,12 @@ impl FsBackend for DirFS {
 
     /// Changes the current working directory.
     /// * `path` can be in relative or absolute form, but in both cases it must exist in VFS.
+    ///
     /// An error is returned if the specified `path` does not exist.
     fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        let target = self.to_inner(path);
+        let target = self.resolve(path, true)?;
         if !self.is_dir(&target)? {
-            return Err(anyhow!("{} not a directory", target.display()));
+            return Err(VfsError::NotADirectory(target));
         }
         self.cwd = target;
         Ok(())
@@ -307,30 +482,64 @@ impl FsBackend for DirFS {
     /// Checks if a `path` exists in the VFS.
     /// The `path` can be in relative or absolute form.
     fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
-        let inner = self.to_inner(path);
-        utils::is_virtual_root(&inner) || self.entries.contains_key(&inner)
+        self.resolve(path, true)
+            .is_ok_and(|inner| self.is_live(&inner))
     }
 
     /// Checks if `path` is a directory.
     fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
-        let path = path.as_ref();
-        let inner = self.to_inner(path);
-        if !self.exists(&inner) {
-            return Err(anyhow!("{} does not exist", path.display()));
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
         }
         Ok(utils::is_virtual_root(&inner) || self.entries[&inner].is_dir())
     }
 
     /// Checks if `path` is a regular file.
     fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
-        let path = path.as_ref();
-        let inner = self.to_inner(path);
-        if !self.exists(&inner) {
-            return Err(anyhow!("{} does not exist", path.display()));
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
         }
         Ok(!utils::is_virtual_root(&inner) && self.entries[&inner].is_file())
     }
 
+    /// Checks if `path` is a symbolic link.
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve(path, false)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(!utils::is_virtual_root(&inner) && self.entries[&inner].is_symlink())
+    }
+
+    /// Returns metadata of `path`, read from the host file system (`std::fs::metadata()`).
+    /// * `path` must exist in VFS
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        let host = self.source(&inner)?;
+        Ok(Metadata::from(std::fs::metadata(host)?))
+    }
+
+    /// Sets the permission bits of `path` on the host (`std::fs::set_permissions()`), so they
+    /// are enforced by the host. On platforms other than Unix only the read-only flag is set
+    /// (when no write bit is given).
+    fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        if let Some(journal) = &mut self.journal {
+            return journal.set_mode(&inner, mode);
+        }
+        let host = self.to_host(&inner)?;
+        std::fs::set_permissions(&host, utils::host_permissions(&host, mode)?)?;
+        Ok(())
+    }
+
     /// Returns an iterator over directory entries at a specific depth (shallow listing).
     ///
     /// This method lists only the **immediate children** of the given directory,
@@ -344,7 +553,7 @@ impl FsBackend for DirFS {
     /// * `Ok(impl Iterator<Item = &Path>)` - Iterator over entries of immediate children
     ///   (relative to VFS root). The yielded paths are *inside* the target directory
     ///   but do not include deeper nesting.
-    /// * `Err(anyhow::Error)` - If the specified path does not exist in VFS.
+    /// * `Err(VfsError)` - If the specified path does not exist in VFS.
     ///
     /// # Example:
     ///```no_run
@@ -375,11 +584,11 @@ impl FsBackend for DirFS {
     /// - **Performance:** The filtering is done in‑memory; no additional filesystem I/O occurs
     ///   during iteration.
     fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
-        let inner_path = self.to_inner(path);
+        let inner_path = self.resolve(path, true)?;
         if !self.exists(&inner_path) {
-            return Err(anyhow!("{} does not exist", inner_path.display()));
+            return Err(VfsError::NotFound(inner_path));
         }
-        let is_file =  self.is_file(&inner_path)?;
+        let is_file = self.is_file(&inner_path)?;
         let component_count = if is_file {
             inner_path.components().count()
         } else {
@@ -387,8 +596,8 @@ impl FsBackend for DirFS {
         };
         Ok(self
             .entries
-            .iter()
-            .map(|(pb, _)| pb.as_path())
+            .keys()
+            .map(|pb| pb.as_path())
             .filter(move |&path| {
                 path.starts_with(&inner_path)
                     && (path != inner_path || is_file)
@@ -407,7 +616,7 @@ impl FsBackend for DirFS {
     /// # Returns
     /// * `Ok(impl Iterator<Item = &Path>)` - Iterator over all entries *within* the tree
     ///   (relative to VFS root), excluding the root of the traversal.
-    /// * `Err(anyhow::Error)` - If:
+    /// * `Err(VfsError)` - If:
     ///   - The specified path does not exist in VFS.
     ///   - The path is not a directory (implicitly checked via `exists` and tree structure).
     ///
@@ -443,30 +652,29 @@ impl FsBackend for DirFS {
     /// - Symbolic links are treated as regular entries (no follow/resolve).
     /// - Use `DirFS` methods (e.g., `is_file()`, `is_dir()`) for yielded items for type checks.
     fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
-        let inner_path = self.to_inner(path);
+        let inner_path = self.resolve(path, true)?;
         if !self.exists(&inner_path) {
-            return Err(anyhow!("{} does not exist", inner_path.display()));
+            return Err(VfsError::NotFound(inner_path));
         }
         let is_file = self.is_file(&inner_path)?;
         Ok(self
             .entries
-            .iter()
-            .map(|(pb, _)| pb.as_path())
-            .filter(move |&path| path.starts_with(&inner_path)
-                && (path != inner_path || is_file)))
+            .keys()
+            .map(|pb| pb.as_path())
+            .filter(move |&path| path.starts_with(&inner_path) && (path != inner_path || is_file)))
     }
 
     /// Creates directory and all it parents (if needed).
     /// * `path` - inner vfs path.
     fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         if path.as_ref().as_os_str().is_empty() {
-            return Err(anyhow!("invalid path: empty"));
+            return Err(VfsError::invalid_path("", "empty"));
         }
 
-        let inner_path = self.to_inner(path);
+        let inner_path = self.resolve(path, false)?;
 
-        if self.exists(&inner_path) {
-            return Err(anyhow!("path already exists: {}", inner_path.display()));
+        if self.is_live(&inner_path) {
+            return Err(VfsError::AlreadyExists(inner_path));
         }
 
         // Looking for the first existing parent
@@ -490,10 +698,14 @@ impl FsBackend for DirFS {
         for component in need_to_create {
             built.push(component);
             if !self.exists(&built) {
-                let host = self.to_host(&built)?;
-                std::fs::create_dir(&host)?;
-                self.entries
-                    .insert(built.clone(), Entry::new(EntryType::Directory));
+                if self.journal.is_some() && self.is_on_host(&built)? {
+                    return Err(VfsError::AlreadyExists(built));
+                }
+                match &mut self.journal {
+                    Some(journal) => journal.create_dir(&built)?,
+                    None => std::fs::create_dir(self.to_host(&built)?)?,
+                }
+                self.track(&built, EntryType::Directory);
             }
         }
 
@@ -502,22 +714,29 @@ impl FsBackend for DirFS {
 
     /// Creates new file in VFS.
     /// * `file_path` must be inner VFS path. It must contain the name of the file,
-    /// optionally preceded by parent directory.
+    ///   optionally preceded by parent directory.
+    ///
     /// If the parent directory does not exist, it will be created.
     fn mkfile<P: AsRef<Path>>(&mut self, file_path: P, content: Option<&[u8]>) -> Result<()> {
-        let file_path = self.to_inner(file_path);
-        if self.exists(&file_path) {
-            return Err(anyhow!("{} already exist", file_path.display()));
+        let file_path = self.resolve(file_path, false)?;
+        if self.is_live(&file_path) {
+            return Err(VfsError::AlreadyExists(file_path));
         }
-        if let Some(parent) = file_path.parent() {
-            if !self.exists(parent) {
-                self.mkdir(parent)?;
-            }
+        if let Some(parent) = file_path.parent()
+            && !self.exists(parent)
+        {
+            self.mkdir(parent)?;
         }
         let host = self.to_host(&file_path)?;
+        if self.journal.is_some() && self.is_on_host(&file_path)? && host.is_dir() {
+            return Err(VfsError::IsADirectory(file_path));
+        }
+        let host = match &mut self.journal {
+            Some(journal) => journal.create_file(&file_path)?,
+            None => host, // an untracked host file is truncated
+        };
         let mut fd = std::fs::File::create(host)?;
-        self.entries
-            .insert(file_path.clone(), Entry::new(EntryType::File));
+        self.track(&file_path, EntryType::File);
         if let Some(content) = content {
             fd.write_all(content)?;
         }
@@ -529,9 +748,9 @@ impl FsBackend for DirFS {
     ///
     /// # Returns
     /// * `Ok(Vec<u8>)` - File content as a byte vector if successful.
-    /// * `Err(anyhow::Error)` - If any of the following occurs:
-    ///   - File does not exist in VFS (`file does not exist: ...`)
-    ///   - Path points to a directory (`... is a directory`)
+    /// * `Err(VfsError)` - If any of the following occurs:
+    ///   - File does not exist in VFS (`VfsError::NotFound`)
+    ///   - Path points to a directory (`VfsError::IsADirectory`)
     ///   - Permission issues when accessing the host file
     ///   - I/O errors during reading
     ///
@@ -539,13 +758,13 @@ impl FsBackend for DirFS {
     /// - Does **not** follow symbolic links on the host filesystem (reads the link itself).
     /// - Returns an empty vector for empty files.
     fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
-        let inner = self.to_inner(&path);
+        let inner = self.resolve(path, true)?;
         if self.is_dir(&inner)? {
             // checks for existent too
-            return Err(anyhow!("{} is a directory", path.as_ref().display()));
+            return Err(VfsError::IsADirectory(inner));
         }
         let mut content = Vec::new();
-        let host = self.to_host(&inner)?;
+        let host = self.source(&inner)?;
         std::fs::File::open(&host)?.read_to_end(&mut content)?;
 
         Ok(content)
@@ -557,9 +776,9 @@ impl FsBackend for DirFS {
     ///
     /// # Returns
     /// * `Ok(())` - If the write operation succeeded.
-    /// * `Err(anyhow::Error)` - If any of the following occurs:
-    ///   - File does not exist in VFS (`file does not exist: ...`)
-    ///   - Path points to a directory (`... is a directory`)
+    /// * `Err(VfsError)` - If any of the following occurs:
+    ///   - File does not exist in VFS (`VfsError::NotFound`)
+    ///   - Path points to a directory (`VfsError::IsADirectory`)
     ///   - Permission issues when accessing the host file
     ///   - I/O errors during writing (e.g., disk full, invalid path)
     ///
@@ -569,12 +788,15 @@ impl FsBackend for DirFS {
     /// - **Atomic operation**: Uses `std::fs::write()` which replaces the file in one step.
     /// - **Permissions**: The file retains its original permissions (no chmod is performed).
     fn write<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
-        let inner = self.to_inner(&path);
+        let inner = self.resolve(path, true)?;
         if self.is_dir(&inner)? {
             // checks for existent too
-            return Err(anyhow!("{} is a directory", path.as_ref().display()));
+            return Err(VfsError::IsADirectory(inner));
         }
-        let host = self.to_host(&inner)?;
+        let host = match &mut self.journal {
+            Some(journal) => journal.modify_file(&inner, false)?,
+            None => self.to_host(&inner)?,
+        };
         std::fs::write(&host, content)?;
 
         Ok(())
@@ -588,9 +810,9 @@ impl FsBackend for DirFS {
     ///
     /// # Returns
     /// * `Ok(())` - If the append operation succeeded.
-    /// * `Err(anyhow::Error)` - If any of the following occurs:
-    ///   - File does not exist in VFS (`file does not exist: ...`)
-    ///   - Path points to a directory (`... is a directory`)
+    /// * `Err(VfsError)` - If any of the following occurs:
+    ///   - File does not exist in VFS (`VfsError::NotFound`)
+    ///   - Path points to a directory (`VfsError::IsADirectory`)
     ///   - Permission issues when accessing the host file
     ///   - I/O errors during writing (e.g., disk full, invalid path)
     ///
@@ -599,25 +821,147 @@ impl FsBackend for DirFS {
     /// - **File creation**: Does NOT create the file if it doesn't exist (returns error).
     /// - **Permissions**: The file retains its original permissions.
     fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
-        let inner = self.to_inner(&path);
+        let inner = self.resolve(path, true)?;
         if self.is_dir(&inner)? {
             // checks for existent too
-            return Err(anyhow!("{} is a directory", path.as_ref().display()));
+            return Err(VfsError::IsADirectory(inner));
         }
         // Open file in append mode and write content
         use std::fs::OpenOptions;
-        let host = self.to_host(&inner)?;
-        let mut file = OpenOptions::new().write(true).append(true).open(&host)?;
+        let host = match &mut self.journal {
+            Some(journal) => journal.modify_file(&inner, true)?,
+            None => self.to_host(&inner)?,
+        };
+        let mut file = OpenOptions::new().append(true).open(&host)?;
 
         file.write_all(content)?;
 
         Ok(())
     }
 
+    /// Opens a file with the given `options` and returns the underlying `std::fs::File`.
+    /// A created file is tracked by VFS the same way `mkfile()` does it.
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<impl VfsFile + '_> {
+        let inner = prepare_open(self, path, options)?;
+        let host = match &mut self.journal {
+            Some(journal) if options.is_writable() => journal.modify_file(&inner, true)?,
+            _ => self.source(&inner)?,
+        };
+        let file = std::fs::OpenOptions::new()
+            .read(options.is_readable())
+            .write(options.is_writable())
+            .append(options.is_append())
+            .open(host)?;
+        Ok(file)
+    }
+
+    /// Moves a file or a whole directory subtree from `from` to `to` with `std::fs::rename()`
+    /// and rekeys all tracked descendants. Missing parents of `to` are created.
+    ///
+    /// An error is returned if `from` does not exist or is the root, if `to` already exists
+    /// (in VFS or on the host), or if `to` is inside `from`.
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        let (from, to) = utils::check_transfer(self, from, to)?;
+        if self.is_on_host(&to)? {
+            return Err(VfsError::AlreadyExists(to));
+        }
+        if let Some(parent) = to.parent()
+            && !self.exists(parent)
+        {
+            self.mkdir(parent)?;
+        }
+        match &mut self.journal {
+            Some(journal) => journal.rename(&from, &to),
+            None => std::fs::rename(self.to_host(&from)?, self.to_host(&to)?)?,
+        }
+        self.retrack(&from, &to);
+
+        Ok(())
+    }
+
+    /// Creates a symbolic link on the host at `link` pointing to `target`
+    /// (see `FsBackend::symlink()`). An absolute `target` is stored on the host as the absolute
+    /// host path inside the root, a relative one is stored as is.
+    /// Missing parents of `link` are created.
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, target: P, link: Q) -> Result<()> {
+        if link.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let target = target.as_ref();
+        let link = self.resolve(link, false)?;
+        if self.is_live(&link) {
+            return Err(VfsError::AlreadyExists(link));
+        }
+        let parent = link.parent().unwrap(); // safe unwrap(): the root always exists
+        let resolved = utils::link_target(parent, target)?;
+        if self.is_on_host(&link)? {
+            return Err(VfsError::AlreadyExists(link));
+        }
+        if !self.exists(parent) {
+            self.mkdir(parent)?;
+        }
+        let is_dir = self.is_dir(&resolved).unwrap_or(false);
+        match &mut self.journal {
+            Some(journal) => journal.symlink(target, &link, is_dir),
+            None => {
+                let (host_target, host_link) = self.host_link(target, &link)?;
+                utils::symlink_on_host(&host_target, &host_link, is_dir)?;
+            }
+        }
+        self.track_link(&link, target);
+        Ok(())
+    }
+
+    /// Returns the target of the symbolic link at `path` (as tracked by VFS).
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let inner = self.resolve(path, false)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        self.entries
+            .get(&inner)
+            .and_then(Entry::link_target)
+            .map(Path::to_path_buf)
+            .ok_or_else(|| VfsError::invalid_path(inner, "not a symbolic link"))
+    }
+
+    /// Creates a host hard link `new` to the file `existing` with `std::fs::hard_link()`
+    /// (see `FsBackend::hard_link()`). Missing parents of `new` are created.
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, existing: P, new: Q) -> Result<()> {
+        if new.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let existing = self.resolve(existing, true)?;
+        if self.is_dir(&existing)? {
+            // checks for existent too
+            return Err(VfsError::IsADirectory(existing));
+        }
+        let new = self.resolve(new, false)?;
+        if self.is_live(&new) {
+            return Err(VfsError::AlreadyExists(new));
+        }
+        if let Some(parent) = new.parent()
+            && !self.exists(parent)
+        {
+            self.mkdir(parent)?;
+        }
+        match &mut self.journal {
+            Some(journal) => journal.hard_link(&existing, &new),
+            None => std::fs::hard_link(self.to_host(&existing)?, self.to_host(&new)?)?,
+        }
+        self.track(&new, EntryType::File);
+        Ok(())
+    }
+
     /// Removes a file or directory at the specified path.
     ///
     /// - `path`: can be absolute (starting with '/') or relative to the current working
-    /// directory (cwd). If the path is a directory, all its contents are removed recursively.
+    ///   directory (cwd). If the path is a directory, all its contents are removed recursively.
+    ///   A symbolic link is removed itself, its target is kept.
     ///
     /// Returns:
     /// - `Ok(())` on successful removal.
@@ -627,44 +971,50 @@ impl FsBackend for DirFS {
     ///   - a filesystem error occurs.
     fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         if path.as_ref().as_os_str().is_empty() {
-            return Err(anyhow!("invalid path: empty"));
+            return Err(VfsError::invalid_path("", "empty"));
         }
         if utils::is_virtual_root(&path) {
-            return Err(anyhow!("invalid path: the root cannot be removed"));
+            return Err(VfsError::RootProtected(PathBuf::from("/")));
         }
 
-        let inner_path = self.to_inner(path); // Convert to VFS-internal normalized path
+        let inner_path = self.resolve(path, false)?; // a link is removed, not its target
         let host_path = self.to_host(&inner_path)?; // Map to real filesystem path
 
         // Check if the path exists in the virtual filesystem
-        if !self.exists(&inner_path) {
-            return Err(anyhow!("{} does not exist", inner_path.display()));
+        if !self.is_live(&inner_path) {
+            return Err(VfsError::NotFound(inner_path));
         }
 
-        // Remove from the real filesystem
-        if std::fs::exists(&host_path)? {
-            utils::rm_on_host(&host_path)?;
+        // Remove from the real filesystem (in a transaction, on commit)
+        match &mut self.journal {
+            Some(journal) => journal.remove(&inner_path),
+            None if host_path.is_symlink() || std::fs::exists(&host_path)? => {
+                utils::rm_on_host(&host_path)?
+            }
+            None => {}
         }
 
-        // Update internal state: collect all entries that start with `inner_path`
-        let removed: Vec<PathBuf> = self
-            .entries
-            .iter()
-            .map(|(entry_path, _)| entry_path)
-            .filter(|&p| p.starts_with(&inner_path)) // Match prefix (includes subpaths)
-            .cloned()
-            .collect();
-
-        // Remove all matched entries from the set
-        for p in &removed {
-            self.entries.remove(p);
-        }
+        // Update internal state: untrack all entries that start with `inner_path`
+        self.untrack(&inner_path);
 
         Ok(())
     }
 
     /// Removes all artifacts (dirs and files) in vfs, but preserve its root.
+    /// In a transaction, they are removed on commit.
     fn cleanup(&mut self) -> bool {
+        if let Some(journal) = &mut self.journal {
+            let entries = &self.entries;
+            let outermost = entries
+                .keys()
+                .filter(|path| !path.ancestors().skip(1).any(|p| entries.contains_key(p)));
+            for path in outermost {
+                journal.remove(path);
+            }
+            self.entries.clear();
+            return true;
+        }
+
         let mut is_ok = true;
 
         let mut sorted_paths_to_remove = Vec::new();
@@ -1493,6 +1843,7 @@ mod tests {
         }
 
         #[test]
+        #[allow(clippy::op_ref)]
         fn test_tree_no_root_inclusion() -> Result<()> {
             let temp_dir = setup_test_env();
             let mut fs = DirFS::new(temp_dir.path())?;
@@ -1847,6 +2198,7 @@ mod tests {
         use super::*;
 
         #[test]
+        #[allow(clippy::bool_assert_comparison)]
         fn test_mkfile_simple_creation() {
             let temp_dir = setup_test_env();
             let root = temp_dir.path();
@@ -2328,6 +2680,7 @@ mod tests {
         }
 
         #[test]
+        #[allow(clippy::useless_conversion)]
         fn test_append_unicode_path() -> Result<()> {
             let temp_dir = setup_test_env();
             let mut fs = DirFS::new(temp_dir.path())?;
@@ -2445,12 +2798,8 @@ mod tests {
             let mut fs = DirFS::new(temp_dir.path())?;
 
             let result = fs.add("/nonexistent.txt");
-            assert!(result.is_err());
             assert!(
-                result
-                    .unwrap_err()
-                    .to_string()
-                    .contains("No such file or directory")
+                matches!(result, Err(VfsError::NotFound(path)) if path == Path::new("/nonexistent.txt"))
             );
 
             Ok(())
@@ -2685,12 +3034,8 @@ mod tests {
             let mut fs = DirFS::new(temp_dir.path())?;
 
             let result = fs.forget("/not/found.txt");
-            assert!(result.is_err());
             assert!(
-                result
-                    .unwrap_err()
-                    .to_string()
-                    .contains("path is not tracked by VFS")
+                matches!(result, Err(VfsError::NotFound(path)) if path == Path::new("/not/found.txt"))
             );
 
             Ok(())
@@ -2722,13 +3067,7 @@ mod tests {
             let mut fs = DirFS::new(temp_dir.path())?;
 
             let result = fs.forget("/");
-            assert!(result.is_err());
-            assert!(
-                result
-                    .unwrap_err()
-                    .to_string()
-                    .contains("cannot forget root directory")
-            );
+            assert!(matches!(result, Err(VfsError::RootProtected(_))));
 
             assert!(fs.exists("/"));
 
@@ -2906,11 +3245,7 @@ mod tests {
 
             // Attempt to remove root '/'
             let result = fs.rm("/");
-            assert!(result.is_err());
-            assert_eq!(
-                result.unwrap_err().to_string(),
-                "invalid path: the root cannot be removed"
-            );
+            assert!(matches!(result, Err(VfsError::RootProtected(_))));
 
             // Root should still exist
             assert!(fs.exists("/"));
@@ -3093,6 +3428,582 @@ mod tests {
         }
     }
 
+    mod open {
+        use super::*;
+        use crate::OpenOptions;
+        use std::io::{Seek, SeekFrom};
+
+        #[test]
+        fn test_open_streams_host_file() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let root = temp_dir.path();
+            let mut fs = DirFS::new(root)?;
+
+            let mut file = fs.open("/data/big.bin", OpenOptions::new().write(true).create(true))?;
+            for chunk in 0..4u8 {
+                file.write_all(&[chunk; 1024])?;
+            }
+            drop(file);
+            assert!(fs.is_file("/data/big.bin")?); // created files are tracked
+            assert_eq!(std::fs::metadata(root.join("data/big.bin"))?.len(), 4096);
+
+            let mut file = fs.open("/data/big.bin", OpenOptions::new().read(true))?;
+            file.seek(SeekFrom::Start(3072))?;
+            let mut tail = Vec::new();
+            file.read_to_end(&mut tail)?;
+            assert_eq!(tail, vec![3u8; 1024]);
+
+            Ok(())
+        }
+    }
+
+    mod rename {
+        use super::*;
+
+        #[test]
+        fn test_rename_dir_on_host() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let root = temp_dir.path();
+            let mut fs = DirFS::new(root)?;
+            fs.mkfile("/src/a.txt", Some(b"A"))?;
+            fs.mkfile("/src/sub/b.txt", Some(b"B"))?;
+
+            fs.rename("/src", "/dst/moved")?;
+
+            assert!(!fs.exists("/src"));
+            assert!(!root.join("src").exists());
+            assert_eq!(fs.read("/dst/moved/sub/b.txt")?, b"B");
+            assert_eq!(std::fs::read(root.join("dst/moved/a.txt"))?, b"A");
+            assert_eq!(fs.tree("/")?.count(), 5);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rename_to_untracked_host_path() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let root = temp_dir.path();
+            std::fs::write(root.join("host.txt"), b"host")?;
+            let mut fs = DirFS::new(root)?;
+            fs.mkfile("/a.txt", Some(b"A"))?;
+
+            assert!(matches!(
+                fs.rename("/a.txt", "/host.txt"),
+                Err(VfsError::AlreadyExists(_))
+            ));
+            assert_eq!(std::fs::read(root.join("host.txt"))?, b"host");
+            assert!(fs.exists("/a.txt"));
+
+            Ok(())
+        }
+    }
+
+    mod copy {
+        use super::*;
+        use crate::{MapFS, copy_between};
+
+        #[test]
+        fn test_copy_on_host() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let root = temp_dir.path();
+            let mut fs = DirFS::new(root)?;
+            fs.mkfile("/src/a.txt", Some(b"A"))?;
+
+            fs.copy("/src", "/dst")?;
+
+            assert_eq!(std::fs::read(root.join("dst/a.txt"))?, b"A");
+            assert!(fs.exists("/src/a.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_copy_between_host_and_memory() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let root = temp_dir.path();
+            std::fs::create_dir_all(root.join("fixture/sub"))?;
+            std::fs::write(root.join("fixture/a.txt"), b"A")?;
+            std::fs::write(root.join("fixture/sub/b.txt"), b"B")?;
+
+            let mut fixture = DirFS::new(root.join("fixture"))?;
+            fixture.set_auto_clean(false);
+            fixture.add("/")?;
+
+            let mut mem = MapFS::new();
+            copy_between(&fixture, "/", &mut mem, "/")?;
+            assert_eq!(mem.read("/sub/b.txt")?, b"B");
+
+            mem.mkfile("/sub/c.txt", Some(b"C"))?;
+            let mut out = DirFS::new(root.join("out"))?;
+            copy_between(&mem, "/sub", &mut out, "/result")?;
+            assert_eq!(std::fs::read(root.join("out/result/b.txt"))?, b"B");
+            assert_eq!(std::fs::read(root.join("out/result/c.txt"))?, b"C");
+
+            assert!(matches!(
+                copy_between(&mem, "/sub", &mut out, "/result"),
+                Err(VfsError::AlreadyExists(_))
+            ));
+            assert!(matches!(
+                copy_between(&mem, "/missing", &mut out, "/"),
+                Err(VfsError::NotFound(_))
+            ));
+
+            Ok(())
+        }
+    }
+
+    mod metadata {
+        use super::*;
+
+        #[test]
+        fn test_metadata_of_file_and_dir() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/dir/file.txt", Some(b"Hello"))?;
+
+            let meta = fs.metadata("/dir/file.txt")?;
+            assert!(meta.is_file());
+            assert_eq!(meta.len(), 5);
+            assert!(!meta.is_readonly());
+            assert_eq!(
+                meta.modified(),
+                std::fs::metadata(temp_dir.path().join("dir/file.txt"))?
+                    .modified()
+                    .ok()
+            );
+
+            fs.cd("/dir")?;
+            assert!(fs.metadata(".")?.is_dir());
+            assert!(fs.metadata("/")?.is_dir());
+
+            Ok(())
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn test_set_permissions_on_host() -> Result<()> {
+            use std::os::unix::fs::PermissionsExt;
+
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/script.sh", Some(b"#!/bin/sh"))?;
+
+            fs.set_permissions("/script.sh", 0o750)?;
+
+            let host = std::fs::metadata(temp_dir.path().join("script.sh"))?;
+            assert_eq!(host.permissions().mode() & 0o7777, 0o750);
+            let meta = fs.metadata("/script.sh")?;
+            assert_eq!(meta.mode(), Some(0o750));
+            assert!(meta.uid().is_some());
+            assert!(matches!(
+                fs.set_permissions("/missing", 0o644),
+                Err(VfsError::NotFound(_))
+            ));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_metadata_of_untracked_path() -> Result<()> {
+            let temp_dir = setup_test_env();
+            std::fs::write(temp_dir.path().join("host.txt"), b"host")?;
+            let fs = DirFS::new(temp_dir.path())?;
+
+            assert!(matches!(
+                fs.metadata("/host.txt"),
+                Err(VfsError::NotFound(path)) if path == Path::new("/host.txt")
+            ));
+
+            Ok(())
+        }
+    }
+
+    #[cfg(unix)]
+    mod symlinks {
+        use super::*;
+        use std::os::unix::fs::symlink;
+
+        #[test]
+        fn test_symlink_on_host() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/data/file.txt", Some(b"content"))?;
+
+            fs.symlink("data", "/relative")?;
+            fs.symlink("/data/file.txt", "/links/absolute")?;
+
+            let host = temp_dir.path();
+            assert_eq!(
+                std::fs::read_link(host.join("relative"))?,
+                Path::new("data")
+            );
+            assert_eq!(
+                std::fs::read_link(host.join("links/absolute"))?,
+                host.join("data/file.txt")
+            );
+            assert_eq!(fs.read("/relative/file.txt")?, b"content");
+            assert_eq!(fs.read("/links/absolute")?, b"content");
+            assert_eq!(
+                fs.read_link("/links/absolute")?,
+                Path::new("/data/file.txt")
+            );
+            assert!(fs.symlink("../outside", "/escape").is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_add_does_not_follow_host_links() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let outside = setup_test_env();
+            let host = temp_dir.path();
+            std::fs::create_dir(host.join("data"))?;
+            std::fs::write(host.join("data/file.txt"), b"content")?;
+            std::fs::write(outside.path().join("secret.txt"), b"secret")?;
+            symlink("data", host.join("inside"))?;
+            symlink(host.join("data/file.txt"), host.join("absolute"))?;
+
+            let mut fs = DirFS::new(host)?;
+            fs.add("/")?;
+
+            assert!(fs.is_symlink("/inside")?);
+            assert_eq!(fs.read_link("/absolute")?, Path::new("/data/file.txt"));
+            assert_eq!(fs.read("/inside/file.txt")?, b"content");
+            // the target is tracked once, under its own path
+            assert!(
+                !fs.tree("/")?
+                    .any(|path| path == Path::new("/inside/file.txt"))
+            );
+
+            symlink(outside.path(), host.join("escape"))?;
+            assert!(fs.add("/escape").is_err());
+            assert!(!fs.exists("/escape"));
+            symlink("../..", host.join("data/up"))?;
+            assert!(fs.add("/data/up").is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rm_removes_link_only() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/dir/file.txt", Some(b"content"))?;
+            fs.symlink("dir", "/link")?;
+
+            fs.rm("/link")?;
+
+            assert!(!temp_dir.path().join("link").is_symlink());
+            assert!(temp_dir.path().join("dir/file.txt").exists());
+            assert_eq!(fs.read("/dir/file.txt")?, b"content");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_copy_keeps_links() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/src/file.txt", Some(b"content"))?;
+            fs.symlink("file.txt", "/src/link.txt")?;
+
+            fs.copy("/src", "/dst")?;
+
+            assert!(fs.is_symlink("/dst/link.txt")?);
+            assert_eq!(fs.read("/dst/link.txt")?, b"content");
+            assert!(temp_dir.path().join("dst/link.txt").is_symlink());
+
+            Ok(())
+        }
+    }
+
+    #[cfg(unix)]
+    mod hard_links {
+        use super::*;
+        use std::os::unix::fs::MetadataExt;
+
+        #[test]
+        fn test_hard_link_on_host() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/data/file.txt", Some(b"content"))?;
+
+            fs.hard_link("/data/file.txt", "/backup/file.txt")?;
+
+            let host = temp_dir.path();
+            let original = std::fs::metadata(host.join("data/file.txt"))?;
+            assert_eq!(original.nlink(), 2);
+            assert_eq!(
+                original.ino(),
+                std::fs::metadata(host.join("backup/file.txt"))?.ino()
+            );
+            assert!(fs.is_file("/backup/file.txt")?);
+
+            fs.write("/backup/file.txt", b"changed")?;
+            assert_eq!(fs.read("/data/file.txt")?, b"changed");
+
+            fs.rm("/data")?;
+            assert_eq!(fs.read("/backup/file.txt")?, b"changed");
+            assert!(matches!(
+                fs.hard_link("/backup", "/other"),
+                Err(VfsError::IsADirectory(_))
+            ));
+
+            Ok(())
+        }
+    }
+
+    mod transactions {
+        use super::*;
+
+        fn staging(temp_dir: &TempDir) -> PathBuf {
+            temp_dir.path().join(journal::STAGING_DIR)
+        }
+
+        #[test]
+        fn test_commit() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/old.txt", Some(b"old"))?;
+
+            let count = fs.transaction(|fs| {
+                fs.mkfile("/src/lib.rs", Some(b"mod api;"))?;
+                fs.write("/old.txt", b"new")?;
+                fs.rm("/old.txt")?;
+                Ok(fs.tree("/")?.count())
+            })?;
+
+            assert_eq!(count, 2);
+            assert_eq!(fs.read("/src/lib.rs")?, b"mod api;");
+            assert!(!temp_dir.path().join("old.txt").exists());
+            assert!(!staging(&temp_dir).exists());
+            assert!(!fs.exists(format!("/{}", journal::STAGING_DIR)));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rollback_on_error() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/docs/readme.md", Some(b"# Docs"))?;
+            fs.mkfile("/docs/todo.txt", Some(b"nothing"))?;
+            fs.mkfile("/log.txt", Some(b"1"))?;
+            let before: Vec<PathBuf> = fs.tree("/")?.map(Path::to_path_buf).collect();
+
+            let result = fs.transaction(|fs| {
+                fs.write("/docs/readme.md", b"# Changed")?;
+                fs.append("/log.txt", b"2")?;
+                fs.rm("/docs/todo.txt")?;
+                fs.mkfile("/out/generated.rs", None)?;
+                fs.rename("/docs", "/moved/docs")?;
+                fs.cd("/moved")?;
+                fs.read("/missing.txt")
+            });
+
+            assert!(matches!(result, Err(VfsError::NotFound(_))));
+            let host = temp_dir.path();
+            assert_eq!(std::fs::read(host.join("docs/readme.md"))?, b"# Docs");
+            assert_eq!(std::fs::read(host.join("docs/todo.txt"))?, b"nothing");
+            assert_eq!(std::fs::read(host.join("log.txt"))?, b"1");
+            assert!(!host.join("out").exists());
+            assert!(!host.join("moved").exists());
+            assert!(!staging(&temp_dir).exists());
+            let after: Vec<PathBuf> = fs.tree("/")?.map(Path::to_path_buf).collect();
+            assert_eq!(after, before);
+            assert_eq!(fs.cwd(), Path::new("/"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rollback_on_panic() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/a.txt", Some(b"a"))?;
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                fs.transaction(|fs| {
+                    fs.write("/a.txt", b"changed")?;
+                    fs.mkfile("/b.txt", None)?;
+                    panic!("generator failed");
+                    #[allow(unreachable_code)]
+                    Ok(())
+                })
+            }));
+
+            assert!(result.is_err());
+            assert_eq!(fs.read("/a.txt")?, b"a");
+            assert!(!fs.exists("/b.txt"));
+            assert!(!temp_dir.path().join("b.txt").exists());
+            assert!(!staging(&temp_dir).exists());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_nested_rollback() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+
+            fs.transaction(|fs| {
+                fs.mkfile("/outer.txt", None)?;
+                let inner = fs.transaction(|fs| {
+                    fs.mkfile("/inner.txt", None)?;
+                    fs.rm("/outer.txt")?;
+                    fs.rm("/missing.txt")
+                });
+                assert!(inner.is_err());
+                assert!(fs.exists("/outer.txt"));
+                assert!(!fs.exists("/inner.txt"));
+                assert!(staging(&temp_dir).exists());
+                Ok(())
+            })?;
+
+            assert!(temp_dir.path().join("outer.txt").exists());
+            assert!(!temp_dir.path().join("inner.txt").exists());
+            assert!(!staging(&temp_dir).exists());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_host_untouched_until_commit() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let host = temp_dir.path().to_path_buf();
+            let mut fs = DirFS::new(&host)?;
+            fs.mkfile("/a.txt", Some(b"a"))?;
+            fs.mkfile("/docs/readme.md", Some(b"# Docs"))?;
+
+            fs.transaction(|fs| {
+                fs.mkfile("/gen/lib.rs", Some(b"mod api;"))?;
+                fs.append("/a.txt", b"b")?;
+                fs.rename("/docs", "/moved")?;
+                fs.write("/moved/readme.md", b"# Moved")?;
+                fs.rm("/a.txt")?;
+                fs.mkfile("/a.txt", Some(b"new"))?;
+
+                assert_eq!(fs.read("/gen/lib.rs")?, b"mod api;");
+                assert_eq!(fs.read("/moved/readme.md")?, b"# Moved");
+                assert_eq!(fs.read("/a.txt")?, b"new");
+                assert!(fs.is_dir("/gen")?);
+                assert!(!host.join("gen").exists());
+                assert!(!host.join("moved").exists());
+                assert_eq!(std::fs::read(host.join("a.txt"))?, b"a");
+                assert_eq!(std::fs::read(host.join("docs/readme.md"))?, b"# Docs");
+                Ok(())
+            })?;
+
+            assert_eq!(std::fs::read(host.join("gen/lib.rs"))?, b"mod api;");
+            assert_eq!(std::fs::read(host.join("moved/readme.md"))?, b"# Moved");
+            assert_eq!(std::fs::read(host.join("a.txt"))?, b"new");
+            assert!(!host.join("docs").exists());
+            assert!(!staging(&temp_dir).exists());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_cleanup_in_transaction() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/docs/readme.md", Some(b"# Docs"))?;
+            fs.mkfile("/a.txt", None)?;
+
+            let result = fs.transaction(|fs| {
+                assert!(fs.cleanup());
+                assert!(!fs.exists("/docs"));
+                fs.rm("/missing.txt")
+            });
+            assert!(result.is_err());
+            assert_eq!(fs.read("/docs/readme.md")?, b"# Docs");
+            assert!(temp_dir.path().join("a.txt").exists());
+
+            fs.transaction(|fs| {
+                assert!(fs.cleanup());
+                fs.mkfile("/b.txt", None)
+            })?;
+            assert!(!temp_dir.path().join("docs").exists());
+            assert!(!temp_dir.path().join("a.txt").exists());
+            assert_eq!(fs.tree("/")?.collect::<Vec<_>>(), vec![Path::new("/b.txt")]);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_leftover_staging_is_discarded() -> Result<()> {
+            let temp_dir = setup_test_env();
+            std::fs::create_dir(staging(&temp_dir))?;
+            std::fs::write(staging(&temp_dir).join("1"), b"staged")?;
+
+            let mut fs = DirFS::new(temp_dir.path())?;
+            assert!(!staging(&temp_dir).exists());
+            fs.transaction(|fs| fs.mkfile("/a.txt", None))?;
+            assert!(temp_dir.path().join("a.txt").exists());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_staging_path_taken() -> Result<()> {
+            let temp_dir = setup_test_env();
+            std::fs::write(staging(&temp_dir), b"not a directory")?;
+
+            let result = DirFS::new(temp_dir.path());
+            assert!(matches!(result, Err(VfsError::AlreadyExists(_))));
+            assert!(staging(&temp_dir).is_file());
+
+            Ok(())
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn test_commit_keeps_hard_links() -> Result<()> {
+            use std::os::unix::fs::MetadataExt;
+
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/a.txt", Some(b"shared"))?;
+            fs.hard_link("/a.txt", "/b.txt")?;
+
+            fs.transaction(|fs| fs.write("/b.txt", b"changed"))?;
+
+            assert_eq!(fs.read("/a.txt")?, b"changed");
+            let meta = std::fs::metadata(temp_dir.path().join("a.txt"))?;
+            assert_eq!(meta.nlink(), 2);
+
+            Ok(())
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn test_rollback_keeps_hard_links_and_permissions() -> Result<()> {
+            use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+            let temp_dir = setup_test_env();
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/a.txt", Some(b"shared"))?;
+            fs.hard_link("/a.txt", "/b.txt")?;
+            fs.set_permissions("/a.txt", 0o640)?;
+
+            let result = fs.transaction(|fs| {
+                fs.write("/b.txt", b"changed")?;
+                fs.set_permissions("/a.txt", 0o600)?;
+                fs.symlink("/a.txt", "/link")?;
+                fs.rm("/missing")
+            });
+
+            assert!(result.is_err());
+            assert_eq!(fs.read("/a.txt")?, b"shared");
+            let meta = std::fs::metadata(temp_dir.path().join("a.txt"))?;
+            assert_eq!(meta.nlink(), 2);
+            assert_eq!(meta.permissions().mode() & 0o7777, 0o640);
+            assert!(!temp_dir.path().join("link").is_symlink());
+
+            Ok(())
+        }
+    }
+
     // Helper function: Creates a temporary directory for tests
     fn setup_test_env() -> TempDir {
         TempDir::new("dirfs_test").unwrap()
@@ -0,0 +1,715 @@
+//! Transactions of `DirFS`: changes are staged in a hidden directory under the root and applied
+//! to the host tree on commit.
+//!
+//! While a transaction runs, the host tree is not touched. New and changed files (and new
+//! directories) are written into the staging directory, and every change is recorded as an
+//! operation. On commit, the operations are saved into a manifest in the staging directory and
+//! then applied in order, moving the staged entries into place with renames. Each applied
+//! operation is counted in a progress file, so a commit interrupted by a crash is completed by
+//! `recover()`; a staging directory without a manifest belongs to a transaction which was never
+//! committed and is discarded.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::DirFS;
+use crate::Entry;
+use crate::VfsError;
+use crate::core::{Result, utils};
+
+/// Name of the hidden directory under the root keeping the staged entries.
+pub(super) const STAGING_DIR: &str = ".vfs-kit-transaction";
+
+const MANIFEST_FILE: &str = "manifest";
+const MANIFEST_TMP_FILE: &str = "manifest.tmp";
+const PROGRESS_FILE: &str = "progress";
+const MANIFEST_MAGIC: &[u8; 8] = b"VFSKTXN1";
+
+const PUT: u8 = 1;
+const REMOVE: u8 = 2;
+const RENAME: u8 = 3;
+const SYMLINK: u8 = 4;
+const HARD_LINK: u8 = 5;
+const SET_MODE: u8 = 6;
+
+/// A change of the host tree, applied on commit. Paths are inner paths as they are at the
+/// moment of the change, so operations are applied in order.
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    /// Moves the staged file or directory `staged` (a name in the staging directory) to `path`.
+    Put {
+        path: PathBuf,
+        staged: String,
+    },
+    Remove(PathBuf),
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Symlink {
+        target: PathBuf,
+        link: PathBuf,
+        is_dir: bool,
+    },
+    HardLink {
+        existing: PathBuf,
+        new: PathBuf,
+    },
+    SetMode {
+        path: PathBuf,
+        mode: u32,
+    },
+}
+
+/// Changes of a transaction and the view of the host tree they make.
+#[derive(Debug, Clone, Default)]
+struct Changes {
+    ops: Vec<Op>,
+    staged: BTreeMap<PathBuf, PathBuf>, // inner path -> host path of its contents
+    moved: Vec<(PathBuf, PathBuf)>,     // inner path -> host path it was moved from
+    vacated: BTreeSet<PathBuf>,         // inner paths removed or moved away
+}
+
+/// The staging directory and the changes made since the outermost running transaction started.
+pub(super) struct Journal {
+    root: PathBuf,
+    staging: PathBuf, // host path of the staging directory
+    changes: Changes,
+    next_staged: u64,
+}
+
+impl Journal {
+    /// Creates the staging directory under the host `root`, recovering a leftover one first.
+    fn begin(root: &Path) -> Result<Self> {
+        recover(root)?;
+        let staging = root.join(STAGING_DIR);
+        std::fs::create_dir(&staging)?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            staging,
+            changes: Changes::default(),
+            next_staged: 0,
+        })
+    }
+
+    /// Returns the host path keeping the current contents of the tracked `inner` path.
+    pub(super) fn source(&self, inner: &Path) -> PathBuf {
+        if let Some(staged) = self.changes.staged.get(inner) {
+            return staged.clone();
+        }
+        for (to, from) in self.changes.moved.iter().rev() {
+            if let Ok(rest) = inner.strip_prefix(to) {
+                return from.join(rest);
+            }
+        }
+        host_path(&self.root, inner)
+    }
+
+    /// Checks if the host entry at `inner` (or one of its parents) is removed or moved away
+    /// by the transaction.
+    pub(super) fn is_vacated(&self, inner: &Path) -> bool {
+        inner
+            .ancestors()
+            .any(|path| self.changes.vacated.contains(path))
+    }
+
+    /// Stages a new directory at `inner`.
+    pub(super) fn create_dir(&mut self, inner: &Path) -> Result<()> {
+        let (name, staged) = self.next_staged();
+        std::fs::create_dir(&staged)?;
+        self.put(inner, name, staged);
+        Ok(())
+    }
+
+    /// Stages a new empty file at `inner` and returns its host path.
+    pub(super) fn create_file(&mut self, inner: &Path) -> Result<PathBuf> {
+        let (name, staged) = self.next_staged();
+        File::create(&staged)?;
+        self.put(inner, name, staged.clone());
+        Ok(staged)
+    }
+
+    /// Stages a copy of the file at `inner` to be modified and returns its host path. Unless
+    /// `keep_content` is set, the copy is empty. The copy gets the permissions of the file.
+    pub(super) fn modify_file(&mut self, inner: &Path, keep_content: bool) -> Result<PathBuf> {
+        let source = self.source(inner);
+        // the file must be writable, as it is modified in place outside transactions
+        std::fs::OpenOptions::new().write(true).open(&source)?;
+        let (name, staged) = self.next_staged();
+        if keep_content {
+            std::fs::copy(&source, &staged)?;
+        } else {
+            File::create(&staged)?;
+            std::fs::set_permissions(&staged, std::fs::metadata(&source)?.permissions())?;
+        }
+        self.put(inner, name, staged.clone());
+        Ok(staged)
+    }
+
+    /// Records that `inner` is removed.
+    pub(super) fn remove(&mut self, inner: &Path) {
+        self.changes.ops.push(Op::Remove(inner.to_path_buf()));
+        self.changes
+            .staged
+            .retain(|path, _| !path.starts_with(inner));
+        self.changes.vacated.insert(inner.to_path_buf());
+    }
+
+    /// Records that `from` is renamed to `to`.
+    pub(super) fn rename(&mut self, from: &Path, to: &Path) {
+        let source = self.source(from);
+        self.changes.ops.push(Op::Rename {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+        let staged = std::mem::take(&mut self.changes.staged);
+        self.changes.staged = staged
+            .into_iter()
+            .map(|(path, staged)| (utils::rebase(&path, from, to), staged))
+            .collect();
+        self.changes.moved.push((to.to_path_buf(), source));
+        self.changes.vacated.insert(from.to_path_buf());
+    }
+
+    /// Records that a symbolic link `link` pointing to the VFS `target` is created.
+    pub(super) fn symlink(&mut self, target: &Path, link: &Path, is_dir: bool) {
+        self.changes.ops.push(Op::Symlink {
+            target: target.to_path_buf(),
+            link: link.to_path_buf(),
+            is_dir,
+        });
+    }
+
+    /// Records that a hard link `new` to the file `existing` is created. Until the commit,
+    /// `new` shows the contents `existing` has at the moment.
+    pub(super) fn hard_link(&mut self, existing: &Path, new: &Path) {
+        let source = self.source(existing);
+        self.changes.ops.push(Op::HardLink {
+            existing: existing.to_path_buf(),
+            new: new.to_path_buf(),
+        });
+        self.changes.staged.insert(new.to_path_buf(), source);
+    }
+
+    /// Records that the permissions of `inner` are set. A staged entry gets them at once.
+    pub(super) fn set_mode(&mut self, inner: &Path, mode: u32) -> Result<()> {
+        let source = self.source(inner);
+        if source.starts_with(&self.staging) {
+            std::fs::set_permissions(&source, utils::host_permissions(&source, mode)?)?;
+        }
+        self.changes.ops.push(Op::SetMode {
+            path: inner.to_path_buf(),
+            mode: mode & 0o7777,
+        });
+        Ok(())
+    }
+
+    /// Saves the manifest of the operations. Once it is saved, the transaction is committed:
+    /// the operations are applied now or by `recover()` after an interruption.
+    fn save(&self) -> Result<()> {
+        let mut buf = Vec::from(*MANIFEST_MAGIC);
+        for op in &self.changes.ops {
+            op.write(&mut buf)?;
+            if let Op::Put { staged, .. } = op
+                && let Ok(file) = File::open(self.staging.join(staged))
+            {
+                file.sync_all()?;
+            }
+        }
+        let tmp_path = self.staging.join(MANIFEST_TMP_FILE);
+        {
+            let mut tmp = File::create(&tmp_path)?;
+            tmp.write_all(&buf)?;
+            tmp.sync_all()?;
+        }
+        std::fs::rename(tmp_path, self.staging.join(MANIFEST_FILE))?;
+        Ok(())
+    }
+
+    /// Applies the saved operations to the host tree and removes the staging directory.
+    fn apply(self) -> Result<()> {
+        apply(&self.root, &self.changes.ops, 0)?;
+        utils::rm_on_host(&self.staging)
+    }
+
+    /// Removes the staging directory, leaving the host tree as it was.
+    fn discard(self) -> Result<()> {
+        utils::rm_on_host(&self.staging)
+    }
+
+    fn put(&mut self, inner: &Path, name: String, staged: PathBuf) {
+        self.changes.ops.push(Op::Put {
+            path: inner.to_path_buf(),
+            staged: name,
+        });
+        self.changes.staged.insert(inner.to_path_buf(), staged);
+    }
+
+    fn next_staged(&mut self) -> (String, PathBuf) {
+        self.next_staged += 1;
+        let name = self.next_staged.to_string();
+        let staged = self.staging.join(&name);
+        (name, staged)
+    }
+}
+
+/// Finishes a transaction interrupted in the host `root`: a committed one is applied,
+/// the staged entries of any other one are dropped.
+///
+/// An error is returned if the staging path is not a directory (it is left as is) or
+/// the committed changes cannot be applied.
+pub(super) fn recover(root: &Path) -> Result<()> {
+    let staging = root.join(STAGING_DIR);
+    if !staging.is_symlink() && !std::fs::exists(&staging)? {
+        return Ok(());
+    }
+    if staging.is_symlink() || !staging.is_dir() {
+        return Err(VfsError::AlreadyExists(Path::new("/").join(STAGING_DIR)));
+    }
+    let manifest = staging.join(MANIFEST_FILE);
+    if std::fs::exists(&manifest)? {
+        let ops = Op::read_all(&std::fs::read(manifest)?)?;
+        let done = match std::fs::metadata(staging.join(PROGRESS_FILE)) {
+            Ok(meta) => meta.len() as usize,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err.into()),
+        };
+        apply(root, &ops, done)?;
+    }
+    utils::rm_on_host(&staging)
+}
+
+/// Applies `ops` to the host tree in `root`, skipping the first `done` ones, and counts each
+/// applied one in the progress file.
+///
+/// An operation interrupted before it was counted is applied again, so each of them is skipped
+/// if its result is already there.
+fn apply(root: &Path, ops: &[Op], done: usize) -> Result<()> {
+    let staging = root.join(STAGING_DIR);
+    let mut progress = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(staging.join(PROGRESS_FILE))?;
+    for op in ops.iter().skip(done) {
+        op.apply(root, &staging)?;
+        progress.write_all(&[1])?;
+        progress.sync_data()?;
+    }
+    Ok(())
+}
+
+/// Converts the inner absolute normalized path `inner` into a host path under `root`.
+fn host_path(root: &Path, inner: &Path) -> PathBuf {
+    root.join(inner.strip_prefix("/").unwrap_or(inner))
+}
+
+/// Checks if `host` is a file with more than one hard link.
+fn is_shared_file(host: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::symlink_metadata(host).is_ok_and(|meta| meta.is_file() && meta.nlink() > 1)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = host;
+        false
+    }
+}
+
+impl Op {
+    fn apply(&self, root: &Path, staging: &Path) -> Result<()> {
+        let exists = |host: &Path| host.is_symlink() || host.exists();
+        match self {
+            Op::Put { path, staged } => {
+                let staged = staging.join(staged);
+                if !exists(&staged) {
+                    return Ok(()); // applied before an interruption
+                }
+                let host = host_path(root, path);
+                if is_shared_file(&host) {
+                    // copying keeps the file and so its other hard links
+                    std::fs::copy(&staged, &host)?;
+                    std::fs::remove_file(staged)?;
+                } else {
+                    std::fs::rename(staged, host)?;
+                }
+            }
+            Op::Remove(path) => {
+                let host = host_path(root, path);
+                if exists(&host) {
+                    utils::rm_on_host(host)?;
+                }
+            }
+            Op::Rename { from, to } => {
+                let from = host_path(root, from);
+                if exists(&from) {
+                    std::fs::rename(from, host_path(root, to))?;
+                }
+            }
+            Op::Symlink {
+                target,
+                link,
+                is_dir,
+            } => {
+                let link = host_path(root, link);
+                if !exists(&link) {
+                    let target = if target.is_absolute() {
+                        host_path(root, &utils::normalize(target))
+                    } else {
+                        target.clone()
+                    };
+                    utils::symlink_on_host(&target, &link, *is_dir)?;
+                }
+            }
+            Op::HardLink { existing, new } => {
+                let new = host_path(root, new);
+                if !exists(&new) {
+                    std::fs::hard_link(host_path(root, existing), new)?;
+                }
+            }
+            Op::SetMode { path, mode } => {
+                let host = host_path(root, path);
+                std::fs::set_permissions(&host, utils::host_permissions(&host, *mode)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&self, buf: &mut Vec<u8>) -> Result<()> {
+        match self {
+            Op::Put { path, staged } => {
+                buf.push(PUT);
+                write_path(buf, path)?;
+                write_path(buf, Path::new(staged))?;
+            }
+            Op::Remove(path) => {
+                buf.push(REMOVE);
+                write_path(buf, path)?;
+            }
+            Op::Rename { from, to } => {
+                buf.push(RENAME);
+                write_path(buf, from)?;
+                write_path(buf, to)?;
+            }
+            Op::Symlink {
+                target,
+                link,
+                is_dir,
+            } => {
+                buf.push(SYMLINK);
+                write_path(buf, target)?;
+                write_path(buf, link)?;
+                buf.push(u8::from(*is_dir));
+            }
+            Op::HardLink { existing, new } => {
+                buf.push(HARD_LINK);
+                write_path(buf, existing)?;
+                write_path(buf, new)?;
+            }
+            Op::SetMode { path, mode } => {
+                buf.push(SET_MODE);
+                write_path(buf, path)?;
+                buf.extend_from_slice(&mode.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    fn read_all(bytes: &[u8]) -> Result<Vec<Op>> {
+        let Some(mut reader) = bytes.strip_prefix(MANIFEST_MAGIC) else {
+            return Err(corrupted("not a manifest"));
+        };
+        let mut ops = Vec::new();
+        while !reader.is_empty() {
+            ops.push(Op::read(&mut reader)?);
+        }
+        Ok(ops)
+    }
+
+    fn read(reader: &mut &[u8]) -> Result<Op> {
+        let op = match read_u8(reader)? {
+            PUT => Op::Put {
+                path: read_path(reader)?,
+                staged: read_path(reader)?.to_string_lossy().into_owned(),
+            },
+            REMOVE => Op::Remove(read_path(reader)?),
+            RENAME => Op::Rename {
+                from: read_path(reader)?,
+                to: read_path(reader)?,
+            },
+            SYMLINK => Op::Symlink {
+                target: read_path(reader)?,
+                link: read_path(reader)?,
+                is_dir: read_u8(reader)? != 0,
+            },
+            HARD_LINK => Op::HardLink {
+                existing: read_path(reader)?,
+                new: read_path(reader)?,
+            },
+            SET_MODE => Op::SetMode {
+                path: read_path(reader)?,
+                mode: read_u32(reader)?,
+            },
+            _ => return Err(corrupted("unknown operation")),
+        };
+        Ok(op)
+    }
+}
+
+fn corrupted(reason: &str) -> VfsError {
+    VfsError::other(format!("corrupted transaction manifest: {reason}"))
+}
+
+fn write_path(buf: &mut Vec<u8>, path: &Path) -> Result<()> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| VfsError::invalid_path(path, "not a valid UTF-8 string"))?;
+    let len = u32::try_from(path.len())
+        .map_err(|_| VfsError::invalid_path(path, "the path is too long"))?;
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(path.as_bytes());
+    Ok(())
+}
+
+fn read_path(reader: &mut &[u8]) -> Result<PathBuf> {
+    let len = read_u32(reader)? as usize;
+    if reader.len() < len {
+        return Err(corrupted("unexpected end of data"));
+    }
+    let (bytes, rest) = reader.split_at(len);
+    *reader = rest;
+    let path = std::str::from_utf8(bytes).map_err(|_| corrupted("a path is not UTF-8"))?;
+    Ok(PathBuf::from(path))
+}
+
+fn read_u8(reader: &mut &[u8]) -> Result<u8> {
+    let (&byte, rest) = reader
+        .split_first()
+        .ok_or_else(|| corrupted("unexpected end of data"))?;
+    *reader = rest;
+    Ok(byte)
+}
+
+fn read_u32(reader: &mut &[u8]) -> Result<u32> {
+    let (bytes, rest) = reader
+        .split_first_chunk()
+        .ok_or_else(|| corrupted("unexpected end of data"))?;
+    *reader = rest;
+    Ok(u32::from_le_bytes(*bytes))
+}
+
+/// A running transaction; rolls back if it is dropped before `commit()` or `rollback()`
+/// (e.g. on panic).
+pub(super) struct Transaction<'a> {
+    pub(super) fs: &'a mut DirFS,
+    changes: Changes, // changes of the journal at the start
+    entries: BTreeMap<PathBuf, Entry>,
+    cwd: PathBuf,
+    is_outer: bool,
+    is_finished: bool,
+}
+
+impl<'a> Transaction<'a> {
+    /// Starts a transaction, nested into the running one, if any.
+    pub(super) fn begin(fs: &'a mut DirFS) -> Result<Self> {
+        let is_outer = fs.journal.is_none();
+        if is_outer {
+            fs.journal = Some(Journal::begin(&fs.root)?);
+        }
+        Ok(Self {
+            changes: fs
+                .journal
+                .as_ref()
+                .map(|journal| journal.changes.clone())
+                .unwrap_or_default(),
+            entries: fs.entries.clone(),
+            cwd: fs.cwd.clone(),
+            is_outer,
+            is_finished: false,
+            fs,
+        })
+    }
+
+    /// Keeps the changes. They are applied to the host when the outermost transaction commits.
+    ///
+    /// If the manifest cannot be saved, the transaction is rolled back. If the changes cannot
+    /// be applied, the rest of them is applied on the next `DirFS::new()` or transaction.
+    pub(super) fn commit(mut self) -> Result<()> {
+        self.is_finished = true;
+        if !self.is_outer {
+            return Ok(());
+        }
+        if let Some(journal) = &self.fs.journal
+            && let Err(err) = journal.save()
+        {
+            return self.revert().and(Err(err));
+        }
+        match self.fs.journal.take() {
+            Some(journal) => journal.apply(),
+            None => Ok(()),
+        }
+    }
+
+    /// Reverts the changes made since the start of this transaction.
+    pub(super) fn rollback(mut self) -> Result<()> {
+        self.is_finished = true;
+        self.revert()
+    }
+
+    fn revert(&mut self) -> Result<()> {
+        if let Some(journal) = self.fs.journal.as_mut() {
+            journal.changes = std::mem::take(&mut self.changes);
+        }
+        self.fs.entries = std::mem::take(&mut self.entries);
+        self.fs.cwd = std::mem::take(&mut self.cwd);
+        match self.fs.journal.take() {
+            Some(journal) if self.is_outer => journal.discard(),
+            journal => {
+                self.fs.journal = journal;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if !self.is_finished
+            && let Err(err) = self.revert()
+        {
+            eprintln!("Failed to roll back a transaction: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FsBackend;
+    use tempdir::TempDir;
+
+    /// Runs `f` in a transaction which is never finished, as if the process died in it.
+    fn interrupted<F>(fs: &mut DirFS, f: F) -> Result<Journal>
+    where
+        F: FnOnce(&mut DirFS) -> Result<()>,
+    {
+        fs.journal = Some(Journal::begin(&fs.root)?);
+        f(fs)?;
+        Ok(fs.journal.take().unwrap()) // safe unwrap()
+    }
+
+    #[test]
+    fn test_uncommitted_transaction_is_discarded() -> Result<()> {
+        let temp_dir = TempDir::new("journal_test")?;
+        let host = temp_dir.path();
+        let mut fs = DirFS::new(host)?;
+        fs.set_auto_clean(false);
+        fs.mkfile("/a.txt", Some(b"a"))?;
+        interrupted(&mut fs, |fs| {
+            fs.write("/a.txt", b"changed")?;
+            fs.mkfile("/b.txt", None)
+        })?;
+        assert!(host.join(STAGING_DIR).exists());
+
+        fs.transaction(|_| Ok(()))?;
+        assert!(!host.join(STAGING_DIR).exists());
+        assert_eq!(std::fs::read(host.join("a.txt"))?, b"a");
+        assert!(!host.join("b.txt").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interrupted_commit_is_completed() -> Result<()> {
+        let temp_dir = TempDir::new("journal_test")?;
+        let host = temp_dir.path();
+        let mut fs = DirFS::new(host)?;
+        fs.set_auto_clean(false);
+        fs.mkfile("/old.txt", Some(b"old"))?;
+        fs.mkfile("/docs/readme.md", Some(b"# Docs"))?;
+        let journal = interrupted(&mut fs, |fs| {
+            fs.rm("/old.txt")?;
+            fs.mkfile("/old.txt", Some(b"new"))?;
+            fs.rename("/docs", "/moved")?;
+            fs.write("/moved/readme.md", b"# Moved")?;
+            fs.set_permissions("/moved", 0o700)
+        })?;
+        journal.save()?;
+
+        // the process dies while applying the second operation, which is not counted
+        apply(host, &journal.changes.ops[..1], 0)?;
+        journal.changes.ops[1].apply(host, &journal.staging)?;
+        drop(fs);
+
+        let fs = DirFS::new(host)?;
+        assert!(!host.join(STAGING_DIR).exists());
+        assert_eq!(std::fs::read(host.join("old.txt"))?, b"new");
+        assert_eq!(std::fs::read(host.join("moved/readme.md"))?, b"# Moved");
+        assert!(!host.join("docs").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(host.join("moved"))?.permissions().mode();
+            assert_eq!(mode & 0o7777, 0o700);
+        }
+        drop(fs);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_damaged_manifest() -> Result<()> {
+        let temp_dir = TempDir::new("journal_test")?;
+        let staging = temp_dir.path().join(STAGING_DIR);
+        std::fs::create_dir(&staging)?;
+        let mut manifest = Vec::from(*MANIFEST_MAGIC);
+        manifest.extend_from_slice(&[PUT, 200, 0, 0, 0, b'/']);
+        std::fs::write(staging.join(MANIFEST_FILE), manifest)?;
+
+        let result = DirFS::new(temp_dir.path());
+        assert!(matches!(result, Err(VfsError::Other(_))));
+        assert!(staging.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_roundtrip() -> Result<()> {
+        let ops = vec![
+            Op::Put {
+                path: PathBuf::from("/a.txt"),
+                staged: String::from("1"),
+            },
+            Op::Remove(PathBuf::from("/b")),
+            Op::Rename {
+                from: PathBuf::from("/c"),
+                to: PathBuf::from("/d/c"),
+            },
+            Op::Symlink {
+                target: PathBuf::from("../a.txt"),
+                link: PathBuf::from("/d/link"),
+                is_dir: false,
+            },
+            Op::HardLink {
+                existing: PathBuf::from("/a.txt"),
+                new: PathBuf::from("/e.txt"),
+            },
+            Op::SetMode {
+                path: PathBuf::from("/d"),
+                mode: 0o750,
+            },
+        ];
+        let mut buf = Vec::from(*MANIFEST_MAGIC);
+        for op in &ops {
+            op.write(&mut buf)?;
+        }
+        assert_eq!(Op::read_all(&buf)?, ops);
+        assert!(Op::read_all(&buf[..buf.len() - 1]).is_err());
+        assert!(Op::read_all(b"manifest").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,908 @@
+//! This module provides a transparent encryption layer over any `FsBackend`.
+//!
+//! Available with the `encryption` feature.
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::VfsError;
+use crate::core::{FsBackend, Result, utils};
+use crate::vfs::file::BufferedFile;
+use crate::{EntryType, Metadata, OpenOptions, VfsFile};
+use aes_gcm_siv::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm_siv::{Aes256GcmSiv, Nonce};
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// A wrapper over any `FsBackend` that encrypts file contents (and optionally names) on the way
+/// to the inner backend and decrypts them on the way back.
+///
+/// Contents are encrypted with AES‑256‑GCM‑SIV, an authenticated cipher, under a fresh random
+/// nonce on every `mkfile()`, `write()` and `append()`. Each file stored in the inner backend
+/// is `nonce || ciphertext || tag`, and the plaintext inner path of the file is authenticated
+/// as associated data. So any tampering, as well as moving or swapping stored files, is
+/// detected on `read()`.
+///
+/// With `with_encrypted_names()`, every path component is encrypted too and stored hex‑encoded.
+/// Names are encrypted deterministically under a fixed nonce (the same name always produces
+/// the same stored name), which is what makes lookups possible, but also means that equal
+/// names leak their equality: anyone with access to the inner backend sees which entries
+/// share a name, even across directories.
+///
+/// ### Limitations:
+/// - A stored file is not bound to a version: replacing it with an older ciphertext of the
+///   same path (a rollback) is not detected, since that needs state kept outside the inner
+///   backend.
+/// - `hard_link()` is not supported: a shared content cannot be bound to two paths.
+///
+/// ### Usage notes:
+/// - The 256‑bit key is supplied at construction and never stored.
+/// - On creation the tree of the inner backend (as returned by `inner.tree("/")`) is indexed;
+///   all paths and operations of `EncryptedFS` are plaintext inner paths.
+/// - `append()` re‑encrypts the whole file, so it costs as much as `write()`.
+/// - Encrypted names are about twice as long as plain ones (plus 32 characters), so keep
+///   the names short enough for the host file system.
+/// - Not thread‑safe by itself (share it between threads with `SharedFs`).
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{DirFS, EncryptedFS, FsBackend};
+///
+/// let key = [7u8; 32]; // load from a secret store in real code
+/// let mut fs = EncryptedFS::with_encrypted_names(DirFS::new("/tmp/ci").unwrap(), &key).unwrap();
+///
+/// fs.mkfile("/secrets/token.txt", Some(b"s3cr3t")).unwrap(); // no plaintext on the host
+/// assert_eq!(fs.read("/secrets/token.txt").unwrap(), b"s3cr3t");
+/// ```
+pub struct EncryptedFS<B: FsBackend> {
+    inner: B,
+    cipher: Aes256GcmSiv,
+    is_names_encrypted: bool,
+    cwd: PathBuf,                          // inner absolute normalized path
+    entries: BTreeMap<PathBuf, EntryType>, // plaintext inner absolute normalized paths
+}
+
+impl<B: FsBackend> EncryptedFS<B> {
+    /// Creates new EncryptedFS over `inner` that encrypts file contents with `key`.
+    ///
+    /// An error is returned if any file name of `inner` cannot be indexed.
+    pub fn new(inner: B, key: &[u8; 32]) -> Result<Self> {
+        Self::create(inner, key, false)
+    }
+
+    /// Creates new EncryptedFS over `inner` that encrypts both file contents and path
+    /// component names with `key`.
+    ///
+    /// An error is returned if any name in `inner` cannot be decrypted with `key`.
+    pub fn with_encrypted_names(inner: B, key: &[u8; 32]) -> Result<Self> {
+        Self::create(inner, key, true)
+    }
+
+    fn create(inner: B, key: &[u8; 32], is_names_encrypted: bool) -> Result<Self> {
+        let mut fs = Self {
+            inner,
+            cipher: Aes256GcmSiv::new(key.into()),
+            is_names_encrypted,
+            cwd: PathBuf::from("/"),
+            entries: BTreeMap::new(),
+        };
+        fs.reindex()?;
+        Ok(fs)
+    }
+
+    /// Returns a reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Consumes EncryptedFS returning the wrapped backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// Rebuilds the index of plaintext paths from the tree of the inner backend.
+    fn reindex(&mut self) -> Result<()> {
+        let stored: Vec<PathBuf> = self.inner.tree("/")?.map(Path::to_path_buf).collect();
+        let mut entries = BTreeMap::new();
+        for path in stored {
+            let entry_type = if self.inner.is_symlink(&path)? {
+                EntryType::Symlink
+            } else if self.inner.is_dir(&path)? {
+                EntryType::Directory
+            } else {
+                EntryType::File
+            };
+            entries.insert(self.decrypt_path(&path)?, entry_type);
+        }
+        self.entries = entries;
+        Ok(())
+    }
+
+    fn to_inner<P: AsRef<Path>>(&self, inner_path: P) -> PathBuf {
+        utils::normalize(self.cwd.join(inner_path))
+    }
+
+    /// Converts `path` into a plaintext inner absolute path with symbolic links resolved
+    /// (see `utils::resolve()`). Link targets are read from the inner backend.
+    fn resolve<P: AsRef<Path>>(&self, path: P, follow_last: bool) -> Result<PathBuf> {
+        utils::resolve(&self.to_inner(path), follow_last, |inner| {
+            if self.entries.get(inner) != Some(&EntryType::Symlink) {
+                return Ok(None);
+            }
+            let stored = self.inner.read_link(self.encrypt_path(inner)?)?;
+            self.decrypt_target(&stored).map(Some)
+        })
+    }
+
+    /// Checks if the resolved `inner_path` is indexed (a link is not followed).
+    fn is_live(&self, inner_path: &Path) -> bool {
+        utils::is_virtual_root(inner_path) || self.entries.contains_key(inner_path)
+    }
+
+    /// Converts a plaintext inner path into the path stored in the inner backend.
+    fn encrypt_path(&self, inner_path: &Path) -> Result<PathBuf> {
+        if !self.is_names_encrypted {
+            return Ok(inner_path.to_path_buf());
+        }
+        let mut stored = PathBuf::from("/");
+        for component in inner_path.components() {
+            if let Component::Normal(name) = component {
+                let name = name
+                    .to_str()
+                    .ok_or_else(|| VfsError::invalid_path(inner_path, "not a valid UTF-8 path"))?;
+                stored.push(self.encrypt_name(name)?);
+            }
+        }
+        Ok(stored)
+    }
+
+    /// Converts a path stored in the inner backend into a plaintext inner path.
+    fn decrypt_path(&self, stored: &Path) -> Result<PathBuf> {
+        if !self.is_names_encrypted {
+            return Ok(stored.to_path_buf());
+        }
+        let mut inner_path = PathBuf::from("/");
+        for component in stored.components() {
+            if let Component::Normal(name) = component {
+                let name = name
+                    .to_str()
+                    .and_then(|name| self.decrypt_name(name))
+                    .ok_or_else(|| VfsError::invalid_path(stored, "cannot decrypt name"))?;
+                inner_path.push(name);
+            }
+        }
+        Ok(inner_path)
+    }
+
+    /// Converts a plaintext link target into the target stored in the inner backend:
+    /// the same as `encrypt_path()`, but a relative target stays relative.
+    fn encrypt_target(&self, target: &Path) -> Result<PathBuf> {
+        if !self.is_names_encrypted {
+            return Ok(target.to_path_buf());
+        }
+        let mut stored = PathBuf::new();
+        for component in target.components() {
+            match component {
+                Component::Normal(name) => {
+                    let name = name
+                        .to_str()
+                        .ok_or_else(|| VfsError::invalid_path(target, "not a valid UTF-8 path"))?;
+                    stored.push(self.encrypt_name(name)?);
+                }
+                other => stored.push(other),
+            }
+        }
+        Ok(stored)
+    }
+
+    /// Converts a link target stored in the inner backend into a plaintext one.
+    fn decrypt_target(&self, stored: &Path) -> Result<PathBuf> {
+        if !self.is_names_encrypted {
+            return Ok(stored.to_path_buf());
+        }
+        let mut target = PathBuf::new();
+        for component in stored.components() {
+            match component {
+                Component::Normal(name) => {
+                    let name = name
+                        .to_str()
+                        .and_then(|name| self.decrypt_name(name))
+                        .ok_or_else(|| VfsError::invalid_path(stored, "cannot decrypt name"))?;
+                    target.push(name);
+                }
+                other => target.push(other),
+            }
+        }
+        Ok(target)
+    }
+
+    fn encrypt_name(&self, name: &str) -> Result<String> {
+        let ciphertext = self
+            .cipher
+            .encrypt(&Nonce::default(), name.as_bytes())
+            .map_err(|_| VfsError::invalid_path(name, "cannot encrypt name"))?;
+        Ok(hex_encode(&ciphertext))
+    }
+
+    fn decrypt_name(&self, name: &str) -> Option<String> {
+        let ciphertext = hex_decode(name)?;
+        let plaintext = self
+            .cipher
+            .decrypt(&Nonce::default(), ciphertext.as_slice())
+            .ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+
+    /// Encrypts `content` of the file at `inner_path`, binding it to that path.
+    fn encrypt(&self, inner_path: &Path, content: &[u8]) -> Result<Vec<u8>> {
+        let nonce = Aes256GcmSiv::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: content,
+            aad: inner_path.as_os_str().as_encoded_bytes(),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| VfsError::other("cannot encrypt content"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    fn decrypt(&self, inner_path: &Path, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(VfsError::other(format!(
+                "{} is not encrypted",
+                inner_path.display()
+            )));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: inner_path.as_os_str().as_encoded_bytes(),
+        };
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| {
+                VfsError::other(format!(
+                    "cannot decrypt {}: wrong key, moved or corrupted content",
+                    inner_path.display()
+                ))
+            })
+    }
+
+    /// Returns the plaintext inner path of an existing file.
+    fn existing_file<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let inner = self.resolve(path, true)?;
+        if self.is_dir(&inner)? {
+            // checks for existent too
+            return Err(VfsError::IsADirectory(inner));
+        }
+        Ok(inner)
+    }
+
+    /// Adds `inner_path` and all its missing parents as directories into the index.
+    fn index_dirs(&mut self, inner_path: &Path) {
+        for ancestor in inner_path.ancestors() {
+            if utils::is_virtual_root(ancestor) {
+                break;
+            }
+            self.entries
+                .entry(ancestor.to_path_buf())
+                .or_insert(EntryType::Directory);
+        }
+    }
+}
+
+impl<B: FsBackend> FsBackend for EncryptedFS<B> {
+    /// Returns root path of the inner backend.
+    fn root(&self) -> &Path {
+        self.inner.root()
+    }
+
+    /// Returns current working directory related to the vfs root.
+    fn cwd(&self) -> &Path {
+        self.cwd.as_path()
+    }
+
+    /// Returns the path on the host system of the (encrypted) entry that matches `inner_path`.
+    fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+        let inner = self.to_inner(inner_path);
+        self.inner.to_host(self.encrypt_path(&inner)?)
+    }
+
+    /// Changes the current working directory.
+    /// * `path` can be in relative or absolute form, but in both cases it must exist in VFS.
+    ///
+    /// An error is returned if the specified `path` does not exist.
+    fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let target = self.resolve(path, true)?;
+        if !self.is_dir(&target)? {
+            return Err(VfsError::NotADirectory(target));
+        }
+        self.cwd = target;
+        Ok(())
+    }
+
+    /// Checks if a `path` exists in the VFS.
+    /// The `path` can be in relative or absolute form.
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.resolve(path, true)
+            .is_ok_and(|inner| self.is_live(&inner))
+    }
+
+    /// Checks if `path` is a directory.
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(utils::is_virtual_root(&inner) || self.entries[&inner] == EntryType::Directory)
+    }
+
+    /// Checks if `path` is a regular file.
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(!utils::is_virtual_root(&inner) && self.entries[&inner] == EntryType::File)
+    }
+
+    /// Checks if `path` is a symbolic link.
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve(path, false)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(!utils::is_virtual_root(&inner) && self.entries[&inner] == EntryType::Symlink)
+    }
+
+    /// Returns metadata of `path` taken from the inner backend.
+    /// The length of a file is the length of its plaintext (without nonce and tag).
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        let mut meta = self.inner.metadata(self.encrypt_path(&inner)?)?;
+        if meta.is_file() {
+            meta.set_len(meta.len().saturating_sub((NONCE_LEN + TAG_LEN) as u64));
+        }
+        Ok(meta)
+    }
+
+    /// Sets the permission bits of the stored file or directory in the inner backend.
+    fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        self.inner.set_permissions(self.encrypt_path(&inner)?, mode)
+    }
+
+    /// Returns an iterator over directory entries at a specific depth (shallow listing).
+    /// The yielded paths are plaintext. See `MapFS::ls()` for details.
+    fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        let inner_path = self.resolve(path, true)?;
+        let is_file = self.is_file(&inner_path)?; // checks for existent too
+        let component_count = if is_file {
+            inner_path.components().count()
+        } else {
+            inner_path.components().count() + 1
+        };
+        Ok(self
+            .entries
+            .keys()
+            .map(|pb| pb.as_path())
+            .filter(move |&path| {
+                path.starts_with(&inner_path)
+                    && (path != inner_path || is_file)
+                    && path.components().count() == component_count
+            }))
+    }
+
+    /// Returns a recursive iterator over the directory tree starting from a given path.
+    /// The yielded paths are plaintext. See `MapFS::tree()` for details.
+    fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        let inner_path = self.resolve(path, true)?;
+        let is_file = self.is_file(&inner_path)?; // checks for existent too
+        Ok(self
+            .entries
+            .keys()
+            .map(|pb| pb.as_path())
+            .filter(move |&path| path.starts_with(&inner_path) && (path != inner_path || is_file)))
+    }
+
+    /// Creates directory and all it parents (if needed).
+    /// * `path` - inner vfs path.
+    fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if path.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let inner_path = self.resolve(path, false)?;
+        if self.is_live(&inner_path) {
+            return Err(VfsError::AlreadyExists(inner_path));
+        }
+        self.inner.mkdir(self.encrypt_path(&inner_path)?)?;
+        self.index_dirs(&inner_path);
+        Ok(())
+    }
+
+    /// Creates new file with encrypted `content`.
+    /// * `file_path` must be inner VFS path. It must contain the name of the file,
+    ///   optionally preceded by parent directory.
+    ///
+    /// If the parent directory does not exist, it will be created.
+    fn mkfile<P: AsRef<Path>>(&mut self, file_path: P, content: Option<&[u8]>) -> Result<()> {
+        let file_path = self.resolve(file_path, false)?;
+        if self.is_live(&file_path) {
+            return Err(VfsError::AlreadyExists(file_path));
+        }
+        let sealed = self.encrypt(&file_path, content.unwrap_or_default())?;
+        self.inner
+            .mkfile(self.encrypt_path(&file_path)?, Some(&sealed))?;
+        if let Some(parent) = file_path.parent() {
+            self.index_dirs(parent);
+        }
+        self.entries.insert(file_path, EntryType::File);
+        Ok(())
+    }
+
+    /// Reads and decrypts the entire contents of a file.
+    /// * `path` is the inner VFS path.
+    ///
+    /// An error is returned if the content was tampered with or encrypted with another key.
+    fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        let inner = self.existing_file(path)?;
+        let sealed = self.inner.read(self.encrypt_path(&inner)?)?;
+        self.decrypt(&inner, &sealed)
+    }
+
+    /// Encrypts `content` and writes it to an existing file, replacing its entire contents.
+    fn write<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let inner = self.existing_file(path)?;
+        let sealed = self.encrypt(&inner, content)?;
+        self.inner.write(self.encrypt_path(&inner)?, &sealed)
+    }
+
+    /// Appends bytes to the end of an existing file, preserving its old contents.
+    /// The whole file is decrypted and encrypted again under a new nonce.
+    fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let inner = self.existing_file(path)?;
+        let mut plaintext = self.read(&inner)?;
+        plaintext.extend_from_slice(content);
+        self.write(&inner, &plaintext)
+    }
+
+    /// Opens a file with the given `options`. The handle works on the decrypted contents,
+    /// which are encrypted and written back on `flush()` or drop.
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<impl VfsFile + '_> {
+        BufferedFile::open(self, path, options)
+    }
+
+    /// Moves a file or a whole directory subtree from `from` to `to` in the inner backend.
+    /// The moved files are encrypted again, so that they are bound to their new paths.
+    ///
+    /// An error is returned (and nothing is moved) if any moved file cannot be decrypted.
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        let (from, to) = utils::check_transfer(self, from, to)?;
+        let mut contents = Vec::new();
+        for (path, entry_type) in self.entries.range(from.clone()..) {
+            if !path.starts_with(&from) {
+                break;
+            }
+            if *entry_type == EntryType::File {
+                let sealed = self.inner.read(self.encrypt_path(path)?)?;
+                let plaintext = self.decrypt(path, &sealed)?;
+                contents.push((utils::rebase(path, &from, &to), plaintext));
+            }
+        }
+        self.inner
+            .rename(self.encrypt_path(&from)?, self.encrypt_path(&to)?)?;
+        for (path, plaintext) in contents {
+            let sealed = self.encrypt(&path, &plaintext)?;
+            self.inner.write(self.encrypt_path(&path)?, &sealed)?;
+        }
+
+        let moved: Vec<(PathBuf, EntryType)> = self
+            .entries
+            .iter()
+            .filter(|(path, _)| path.starts_with(&from))
+            .map(|(path, entry_type)| (path.clone(), *entry_type))
+            .collect();
+        self.entries.retain(|path, _| !path.starts_with(&from));
+        if let Some(parent) = to.parent() {
+            self.index_dirs(parent);
+        }
+        for (path, entry_type) in moved {
+            self.entries
+                .insert(utils::rebase(&path, &from, &to), entry_type);
+        }
+        self.cwd = utils::rebase(&self.cwd, &from, &to);
+
+        Ok(())
+    }
+
+    /// Creates a symbolic link at `link` pointing to `target` in the inner backend.
+    /// With encrypted names, the names in `target` are encrypted as well.
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, target: P, link: Q) -> Result<()> {
+        if link.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let target = target.as_ref();
+        let link = self.resolve(link, false)?;
+        if self.is_live(&link) {
+            return Err(VfsError::AlreadyExists(link));
+        }
+        let parent = link.parent().unwrap(); // safe unwrap(): the root always exists
+        utils::link_target(parent, target)?;
+        self.inner
+            .symlink(self.encrypt_target(target)?, self.encrypt_path(&link)?)?;
+        self.index_dirs(parent);
+        self.entries.insert(link, EntryType::Symlink);
+        Ok(())
+    }
+
+    /// Returns the (plaintext) target of the symbolic link at `path`.
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let inner = self.resolve(path, false)?;
+        if !self.is_symlink(&inner)? {
+            return Err(VfsError::invalid_path(inner, "not a symbolic link"));
+        }
+        let stored = self.inner.read_link(self.encrypt_path(&inner)?)?;
+        self.decrypt_target(&stored)
+    }
+
+    /// Hard links are not supported: every stored content is bound to a single path.
+    ///
+    /// An error is returned even when `existing` is a file and `new` is free.
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, existing: P, new: Q) -> Result<()> {
+        if new.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let existing = self.resolve(existing, true)?;
+        if self.is_dir(&existing)? {
+            // checks for existent too
+            return Err(VfsError::IsADirectory(existing));
+        }
+        let new = self.resolve(new, false)?;
+        if self.is_live(&new) {
+            return Err(VfsError::AlreadyExists(new));
+        }
+        Err(VfsError::other(
+            "EncryptedFS does not support hard links: contents are bound to their paths",
+        ))
+    }
+
+    /// Removes a file or directory at the specified path.
+    ///
+    /// - `path`: can be absolute (starting with '/') or relative to the current working
+    ///   directory (cwd). If the path is a directory, all its contents are removed recursively.
+    fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if path.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        if utils::is_virtual_root(&path) {
+            return Err(VfsError::RootProtected(PathBuf::from("/")));
+        }
+        let inner_path = self.resolve(path, false)?;
+        if !self.is_live(&inner_path) {
+            return Err(VfsError::NotFound(inner_path));
+        }
+        self.inner.rm(self.encrypt_path(&inner_path)?)?;
+        self.entries
+            .retain(|path, _| !path.starts_with(&inner_path));
+        Ok(())
+    }
+
+    /// Removes all artifacts created through the inner backend (see its `cleanup()`).
+    fn cleanup(&mut self) -> bool {
+        let is_cleaned = self.inner.cleanup();
+        is_cleaned && self.reindex().is_ok()
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirFS, MapFS};
+    use tempdir::TempDir;
+
+    const KEY: [u8; 32] = [42; 32];
+
+    fn setup_test_env() -> TempDir {
+        TempDir::new("encryptedfs_test").unwrap()
+    }
+
+    mod contents {
+        use super::*;
+
+        #[test]
+        fn test_roundtrip() -> Result<()> {
+            let mut fs = EncryptedFS::new(MapFS::new(), &KEY)?;
+
+            fs.mkfile("/docs/secret.txt", Some(b"top secret"))?;
+            assert_eq!(fs.read("/docs/secret.txt")?, b"top secret");
+
+            fs.write("/docs/secret.txt", b"new")?;
+            fs.append("/docs/secret.txt", b" content")?;
+            assert_eq!(fs.read("/docs/secret.txt")?, b"new content");
+
+            fs.mkfile("/empty.txt", None)?;
+            assert_eq!(fs.read("/empty.txt")?, b"");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_no_plaintext_in_inner() -> Result<()> {
+            let mut fs = EncryptedFS::new(MapFS::new(), &KEY)?;
+            fs.mkfile("/a.txt", Some(b"plaintext"))?;
+            fs.mkfile("/b.txt", Some(b"plaintext"))?;
+
+            let inner = fs.into_inner();
+            let a = inner.read("/a.txt")?;
+            let b = inner.read("/b.txt")?;
+            assert!(!a.windows(9).any(|w| w == b"plaintext"));
+            assert_ne!(a, b); // fresh nonce for every file
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_wrong_key() -> Result<()> {
+            let mut fs = EncryptedFS::new(MapFS::new(), &KEY)?;
+            fs.mkfile("/a.txt", Some(b"data"))?;
+
+            let fs = EncryptedFS::new(fs.into_inner(), &[0; 32])?;
+            let err = fs.read("/a.txt").unwrap_err().to_string();
+            assert!(err.contains("wrong key, moved or corrupted content"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_tampered_content() -> Result<()> {
+            let mut fs = EncryptedFS::new(MapFS::new(), &KEY)?;
+            fs.mkfile("/a.txt", Some(b"data"))?;
+
+            let mut inner = fs.into_inner();
+            let mut sealed = inner.read("/a.txt")?;
+            *sealed.last_mut().unwrap() ^= 1;
+            inner.write("/a.txt", &sealed)?;
+            inner.mkfile("/short.txt", Some(b"abc"))?;
+
+            let fs = EncryptedFS::new(inner, &KEY)?;
+            assert!(fs.read("/a.txt").is_err());
+            assert!(fs.read("/short.txt").is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_swapped_content() -> Result<()> {
+            let mut fs = EncryptedFS::new(MapFS::new(), &KEY)?;
+            fs.mkfile("/a.txt", Some(b"alpha"))?;
+            fs.mkfile("/b.txt", Some(b"beta"))?;
+
+            let mut inner = fs.into_inner();
+            let a = inner.read("/a.txt")?;
+            let b = inner.read("/b.txt")?;
+            inner.write("/a.txt", &b)?;
+            inner.write("/b.txt", &a)?;
+            inner.rename("/a.txt", "/c.txt")?;
+
+            let fs = EncryptedFS::new(inner, &KEY)?;
+            assert!(fs.read("/b.txt").is_err());
+            assert!(fs.read("/c.txt").is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_hard_link_unsupported() -> Result<()> {
+            let mut fs = EncryptedFS::new(MapFS::new(), &KEY)?;
+            fs.mkfile("/a.txt", Some(b"data"))?;
+
+            assert!(matches!(
+                fs.hard_link("/a.txt", "/b.txt"),
+                Err(VfsError::Other(_))
+            ));
+            assert!(!fs.exists("/b.txt"));
+            assert!(!fs.inner().exists("/b.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_errors() -> Result<()> {
+            let mut fs = EncryptedFS::new(MapFS::new(), &KEY)?;
+            fs.mkdir("/dir")?;
+
+            assert!(fs.read("/missing").is_err());
+            assert!(fs.write("/missing", b"").is_err());
+            assert!(fs.append("/dir", b"").is_err());
+            assert!(fs.mkdir("/dir").is_err());
+            assert!(fs.rm("/").is_err());
+            assert!(fs.rm("").is_err());
+
+            Ok(())
+        }
+    }
+
+    mod names {
+        use super::*;
+
+        #[test]
+        fn test_symlink_targets_are_encrypted() -> Result<()> {
+            let mut fs = EncryptedFS::with_encrypted_names(MapFS::new(), &KEY)?;
+            fs.mkfile("/secrets/token.txt", Some(b"s3cr3t"))?;
+            fs.symlink("../secrets/token.txt", "/links/token")?;
+
+            assert_eq!(fs.read("/links/token")?, b"s3cr3t");
+            assert_eq!(
+                fs.read_link("/links/token")?,
+                Path::new("../secrets/token.txt")
+            );
+
+            let stored = fs.encrypt_path(Path::new("/links/token"))?;
+            let stored_target = fs.inner().read_link(&stored)?;
+            assert!(stored_target.starts_with(".."));
+            assert!(!stored_target.to_string_lossy().contains("token"));
+
+            let fs = EncryptedFS::with_encrypted_names(fs.into_inner(), &KEY)?;
+            assert!(fs.is_symlink("/links/token")?);
+            assert_eq!(fs.read("/links/token")?, b"s3cr3t");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_encrypted_names_on_host() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let root = temp_dir.path().join("vault");
+
+            let mut fs = EncryptedFS::with_encrypted_names(DirFS::new(&root)?, &KEY)?;
+            fs.mkfile("/secrets/token.txt", Some(b"s3cr3t"))?;
+            fs.mkdir("/secrets/keys")?;
+
+            let listed: Vec<_> = fs.ls("/secrets")?.collect();
+            assert_eq!(
+                listed,
+                vec![Path::new("/secrets/keys"), Path::new("/secrets/token.txt")]
+            );
+
+            let host = fs.to_host("/secrets/token.txt")?;
+            assert!(host.exists());
+            assert!(!host.to_string_lossy().contains("token"));
+            assert!(!root.join("secrets").exists());
+            assert_ne!(std::fs::read(&host)?, b"s3cr3t");
+
+            fs.rm("/secrets/token.txt")?;
+            assert!(!host.exists());
+            assert!(!fs.exists("/secrets/token.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_reopen_indexes_inner_tree() -> Result<()> {
+            let mut fs = EncryptedFS::with_encrypted_names(MapFS::new(), &KEY)?;
+            fs.mkfile("/a/b/c.txt", Some(b"c"))?;
+            fs.mkdir("/d")?;
+            let inner = fs.into_inner();
+
+            let mut fs = EncryptedFS::with_encrypted_names(inner, &KEY)?;
+            let tree: Vec<_> = fs.tree("/")?.collect();
+            assert_eq!(
+                tree,
+                vec![
+                    Path::new("/a"),
+                    Path::new("/a/b"),
+                    Path::new("/a/b/c.txt"),
+                    Path::new("/d")
+                ]
+            );
+            fs.cd("/a/b")?;
+            assert_eq!(fs.read("c.txt")?, b"c");
+
+            assert!(EncryptedFS::with_encrypted_names(fs.into_inner(), &[0; 32]).is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_names_are_deterministic() -> Result<()> {
+            let fs = EncryptedFS::with_encrypted_names(MapFS::new(), &KEY)?;
+
+            let first = fs.encrypt_path(Path::new("/a/a"))?;
+            let second = fs.encrypt_path(Path::new("/a/a"))?;
+            assert_eq!(first, second);
+            assert_eq!(fs.decrypt_path(&first)?, Path::new("/a/a"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_open() -> Result<()> {
+            use crate::OpenOptions;
+            use std::io::{Read, Write};
+
+            let mut fs = EncryptedFS::new(MapFS::new(), &KEY)?;
+            let mut file = fs.open("/a.txt", OpenOptions::new().write(true).create(true))?;
+            file.write_all(b"Hello")?;
+            drop(file);
+
+            assert_ne!(fs.inner().read("/a.txt")?, b"Hello");
+            let mut content = String::new();
+            fs.open("/a.txt", OpenOptions::new().read(true))?
+                .read_to_string(&mut content)?;
+            assert_eq!(content, "Hello");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rename() -> Result<()> {
+            let mut fs = EncryptedFS::with_encrypted_names(MapFS::new(), &KEY)?;
+            fs.mkfile("/dir/secret.txt", Some(b"Hello"))?;
+
+            fs.rename("/dir", "/moved/dir")?;
+
+            assert!(!fs.exists("/dir"));
+            assert_eq!(fs.read("/moved/dir/secret.txt")?, b"Hello");
+            assert_eq!(fs.inner().tree("/")?.count(), 3);
+
+            let fs = EncryptedFS::with_encrypted_names(fs.into_inner(), &KEY)?;
+            assert_eq!(fs.read("/moved/dir/secret.txt")?, b"Hello");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_metadata() -> Result<()> {
+            let mut fs = EncryptedFS::with_encrypted_names(MapFS::new(), &KEY)?;
+            fs.mkfile("/dir/secret.txt", Some(b"Hello"))?;
+
+            let meta = fs.metadata("/dir/secret.txt")?;
+            assert!(meta.is_file());
+            assert_eq!(meta.len(), 5);
+            assert!(fs.metadata("/dir")?.is_dir());
+            assert!(fs.metadata("/missing").is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_cleanup() -> Result<()> {
+            let mut fs = EncryptedFS::with_encrypted_names(MapFS::new(), &KEY)?;
+            fs.mkfile("/a.txt", None)?;
+
+            assert!(fs.cleanup());
+            assert!(!fs.exists("/a.txt"));
+            assert_eq!(fs.tree("/")?.count(), 0);
+
+            Ok(())
+        }
+    }
+}
@@ -1,23 +1,73 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EntryType {
     File,
     Directory,
+    Symlink,
+}
+
+impl EntryType {
+    /// Returns the permission bits a new entry of this type gets in memory backends:
+    /// `0o644` for files, `0o755` for directories and `0o777` for symbolic links.
+    pub fn default_mode(&self) -> u32 {
+        match self {
+            EntryType::File => 0o644,
+            EntryType::Directory => 0o755,
+            EntryType::Symlink => 0o777,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Entry {
     entry_type: EntryType,
-    content: Option<Vec<u8>>,
+    inode: Option<InodeId>,
+    link_target: Option<PathBuf>,
+    expires_at: Option<SystemTime>,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    created: SystemTime,
+    modified: SystemTime,
+    accessed: AccessTime,
 }
 
 impl Entry {
     pub fn new(entry_type: EntryType) -> Entry {
+        let now = SystemTime::now();
         Entry {
             entry_type,
-            content: None,
+            inode: None,
+            link_target: None,
+            expires_at: None,
+            mode: entry_type.default_mode(),
+            uid: 0,
+            gid: 0,
+            created: now,
+            modified: now,
+            accessed: AccessTime::new(now),
         }
     }
 
+    /// Creates a file entry whose contents are kept in the inode `inode` (see `Inode`).
+    pub fn new_file(inode: InodeId) -> Entry {
+        let mut entry = Entry::new(EntryType::File);
+        entry.inode = Some(inode);
+        entry
+    }
+
+    /// Creates a symbolic link entry pointing to `target`.
+    pub fn new_symlink<P: AsRef<Path>>(target: P) -> Entry {
+        let mut entry = Entry::new(EntryType::Symlink);
+        entry.link_target = Some(target.as_ref().to_path_buf());
+        entry
+    }
+
     pub fn entry_type(&self) -> EntryType {
         self.entry_type
     }
@@ -30,21 +80,232 @@ impl Entry {
         self.entry_type == EntryType::Directory
     }
 
-    pub fn content(&self) -> Option<&Vec<u8>> {
-        self.content.as_ref()
+    pub fn is_symlink(&self) -> bool {
+        self.entry_type == EntryType::Symlink
+    }
+
+    /// Returns the target of a symbolic link entry (`None` for files and directories).
+    pub fn link_target(&self) -> Option<&Path> {
+        self.link_target.as_deref()
+    }
+
+    /// Returns the inode holding the contents of a file entry (`None` for directories, links
+    /// and files of backends which keep contents elsewhere).
+    pub fn inode(&self) -> Option<InodeId> {
+        self.inode
+    }
+
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires_at
+    }
+
+    pub fn set_expires_at(&mut self, expires_at: Option<SystemTime>) {
+        self.expires_at = expires_at;
+    }
+
+    /// Returns the permission bits of the entry (for files, see `Inode::mode()`).
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: u32) {
+        self.mode = mode & 0o7777;
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    pub fn set_owner(&mut self, uid: u32, gid: u32) {
+        self.uid = uid;
+        self.gid = gid;
+    }
+
+    pub fn created(&self) -> SystemTime {
+        self.created
+    }
+
+    pub fn modified(&self) -> SystemTime {
+        self.modified
+    }
+
+    pub fn accessed(&self) -> SystemTime {
+        self.accessed.get()
+    }
+
+    /// Updates the access time. Takes `&self`, since entries are read through shared references.
+    pub fn mark_accessed(&self) {
+        self.accessed.set(SystemTime::now());
+    }
+
+    /// Sets creation, modification and access times (e.g. when the entry is restored).
+    pub fn set_times(&mut self, created: SystemTime, modified: SystemTime, accessed: SystemTime) {
+        self.created = created;
+        self.modified = modified;
+        self.accessed.set(accessed);
+    }
+}
+
+/// Identifier of an `Inode` in the inode table of a backend.
+pub type InodeId = u64;
+
+/// Contents, permissions and times of a file, shared by all its hard links.
+///
+/// Entries point into an inode table by `InodeId`; the inode counts the entries pointing to it,
+/// so the backend frees it only when the last link is removed.
+///
+/// Clones of an inode share its contents until one of them is modified.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inode {
+    content: Arc<Vec<u8>>,
+    links: usize,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    created: SystemTime,
+    modified: SystemTime,
+    accessed: AccessTime,
+}
+
+impl Inode {
+    /// Creates an inode with `content` and one link.
+    pub fn new(content: &[u8]) -> Inode {
+        let now = SystemTime::now();
+        Inode {
+            content: Arc::new(Vec::from(content)),
+            links: 1,
+            mode: EntryType::File.default_mode(),
+            uid: 0,
+            gid: 0,
+            created: now,
+            modified: now,
+            accessed: AccessTime::new(now),
+        }
+    }
+
+    pub fn content(&self) -> &Vec<u8> {
+        &self.content
     }
 
     pub fn set_content(&mut self, content: &[u8]) {
-        self.content = Some(Vec::from(content));
+        self.content = Arc::new(Vec::from(content));
+        self.touch();
+    }
+
+    /// Returns mutable contents and marks the inode as modified.
+    pub fn content_mut(&mut self) -> &mut Vec<u8> {
+        self.touch();
+        Arc::make_mut(&mut self.content)
     }
 
     pub fn append_content(&mut self, content: &[u8]) {
-        let mut new_content = if self.content.is_some() {
-            self.content.take().unwrap()
-        } else {
-            Vec::new()
-        };
-        new_content.extend_from_slice(content);
-        self.set_content(&new_content);
+        Arc::make_mut(&mut self.content).extend_from_slice(content);
+        self.touch();
+    }
+
+    /// Returns the number of entries pointing to this inode.
+    pub fn links(&self) -> usize {
+        self.links
+    }
+
+    /// Counts one more entry pointing to this inode.
+    pub fn link(&mut self) {
+        self.links += 1;
+    }
+
+    /// Counts one entry less and returns the number of remaining links.
+    pub fn unlink(&mut self) -> usize {
+        self.links = self.links.saturating_sub(1);
+        self.links
+    }
+
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: u32) {
+        self.mode = mode & 0o7777;
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    pub fn set_owner(&mut self, uid: u32, gid: u32) {
+        self.uid = uid;
+        self.gid = gid;
+    }
+
+    pub fn created(&self) -> SystemTime {
+        self.created
+    }
+
+    pub fn modified(&self) -> SystemTime {
+        self.modified
+    }
+
+    pub fn accessed(&self) -> SystemTime {
+        self.accessed.get()
+    }
+
+    /// Updates the access time. Takes `&self`, since inodes are read through shared references.
+    pub fn mark_accessed(&self) {
+        self.accessed.set(SystemTime::now());
+    }
+
+    /// Sets creation, modification and access times (e.g. when the inode is restored).
+    pub fn set_times(&mut self, created: SystemTime, modified: SystemTime, accessed: SystemTime) {
+        self.created = created;
+        self.modified = modified;
+        self.accessed.set(accessed);
+    }
+
+    fn touch(&mut self) {
+        self.modified = SystemTime::now();
+        self.accessed.set(self.modified);
+    }
+}
+
+/// Access time stored as nanoseconds since `UNIX_EPOCH`, so it can be updated through `&self`
+/// without making `Entry` and `Inode` `!Sync`.
+#[derive(Debug)]
+struct AccessTime(AtomicU64);
+
+impl AccessTime {
+    fn new(time: SystemTime) -> Self {
+        let access_time = Self(AtomicU64::new(0));
+        access_time.set(time);
+        access_time
+    }
+
+    fn get(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_nanos(self.0.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, time: SystemTime) {
+        let nanos = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        self.0.store(nanos, Ordering::Relaxed);
+    }
+}
+
+impl Clone for AccessTime {
+    fn clone(&self) -> Self {
+        Self::new(self.get())
+    }
+}
+
+impl PartialEq for AccessTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
     }
 }
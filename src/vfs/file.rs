@@ -0,0 +1,246 @@
+//! This module provides file handles returned by `FsBackend::open()` and the options they are
+//! opened with.
+
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::VfsError;
+use crate::core::{FsBackend, Result, utils};
+
+/// Options and flags used to open a file with `FsBackend::open()`.
+///
+/// Mirrors `std::fs::OpenOptions`: all flags are off by default and the same combinations are
+/// rejected (no access mode, `truncate` without `write` or with `append`, `create` or
+/// `create_new` without `write` or `append`).
+///
+/// ### Example:
+/// ```no_run
+/// use std::io::Write;
+/// use vfs_kit::{FsBackend, MapFS, OpenOptions};
+///
+/// let mut fs = MapFS::new();
+/// let mut file = fs
+///     .open("/log.txt", OpenOptions::new().append(true).create(true))
+///     .unwrap();
+/// file.write_all(b"started\n").unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+impl OpenOptions {
+    /// Creates a blank set of options, all flags are `false`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the option for read access.
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// Sets the option for write access.
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// Sets the option for appending: every write goes to the end of the file.
+    /// Implies write access.
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Sets the option for truncating an existing file to zero length.
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Sets the option to create a new file, if it does not exist.
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Sets the option to create a new file, failing if it already exists.
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    pub(crate) fn is_readable(&self) -> bool {
+        self.read
+    }
+
+    pub(crate) fn is_writable(&self) -> bool {
+        self.write || self.append
+    }
+
+    pub(crate) fn is_append(&self) -> bool {
+        self.append
+    }
+
+    pub(crate) fn is_truncate(&self) -> bool {
+        self.truncate
+    }
+
+    pub(crate) fn is_create(&self) -> bool {
+        self.create
+    }
+
+    pub(crate) fn is_create_new(&self) -> bool {
+        self.create_new
+    }
+
+    /// Checks flag combinations the same way `std::fs::OpenOptions` does.
+    pub(crate) fn check(&self, path: &Path) -> Result<()> {
+        if !self.read && !self.is_writable() {
+            return Err(VfsError::invalid_path(path, "no access mode is set"));
+        }
+        if self.truncate && (!self.write || self.append) {
+            return Err(VfsError::invalid_path(
+                path,
+                "truncate requires write access without append",
+            ));
+        }
+        if (self.create || self.create_new) && !self.is_writable() {
+            return Err(VfsError::invalid_path(
+                path,
+                "create requires write or append access",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A file handle returned by `FsBackend::open()`.
+///
+/// Implemented for everything that is `Read + Write + Seek`; exists as a separate trait so that
+/// handles can be boxed (`Box<dyn VfsFile>`).
+pub trait VfsFile: Read + Write + Seek {}
+
+impl<T: Read + Write + Seek> VfsFile for T {}
+
+/// Applies `options` to `path` of `fs` before opening: validates the flags, rejects directories,
+/// creates or truncates the file if requested. Returns the inner absolute path of the file with
+/// symbolic links resolved.
+pub(crate) fn prepare_open<B, P>(fs: &mut B, path: P, options: &OpenOptions) -> Result<PathBuf>
+where
+    B: FsBackend,
+    P: AsRef<Path>,
+{
+    if path.as_ref().as_os_str().is_empty() {
+        return Err(VfsError::invalid_path("", "empty"));
+    }
+    let inner = utils::normalize(fs.cwd().join(path));
+    options.check(&inner)?;
+
+    if fs.exists(&inner) {
+        if options.is_create_new() {
+            return Err(VfsError::AlreadyExists(inner));
+        }
+        if fs.is_dir(&inner)? {
+            return Err(VfsError::IsADirectory(inner));
+        }
+        if options.is_truncate() {
+            fs.write(&inner, &[])?;
+        }
+    } else if options.is_create() || options.is_create_new() {
+        fs.mkfile(&inner, None)?;
+    } else {
+        return Err(VfsError::NotFound(inner));
+    }
+    fs.canonicalize(&inner)
+}
+
+/// Checks that a handle opened with `is_allowed` access may be used for `operation`.
+pub(crate) fn check_access(is_allowed: bool, operation: &str) -> io::Result<()> {
+    if is_allowed {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("the file is not opened for {operation}"),
+        ))
+    }
+}
+
+/// A file handle working on a copy of the file contents, which is written back with
+/// `FsBackend::write()` on `flush()` (and on drop, ignoring errors).
+///
+/// Used by backends which must see every change (e.g. to journal or encrypt it).
+pub(crate) struct BufferedFile<'a, B: FsBackend> {
+    fs: &'a mut B,
+    path: PathBuf, // inner absolute normalized path
+    cursor: Cursor<Vec<u8>>,
+    options: OpenOptions,
+    is_dirty: bool,
+}
+
+impl<'a, B: FsBackend> BufferedFile<'a, B> {
+    /// Opens `path` of `fs` (see `prepare_open()`) and reads its contents into memory.
+    pub(crate) fn open<P: AsRef<Path>>(
+        fs: &'a mut B,
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<Self> {
+        let path = prepare_open(fs, path, options)?;
+        let content = fs.read(&path)?;
+        Ok(Self {
+            fs,
+            path,
+            cursor: Cursor::new(content),
+            options: options.clone(),
+            is_dirty: false,
+        })
+    }
+}
+
+impl<B: FsBackend> Read for BufferedFile<'_, B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        check_access(self.options.is_readable(), "reading")?;
+        self.cursor.read(buf)
+    }
+}
+
+impl<B: FsBackend> Write for BufferedFile<'_, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        check_access(self.options.is_writable(), "writing")?;
+        if self.options.is_append() {
+            self.cursor.seek(SeekFrom::End(0))?;
+        }
+        self.is_dirty = true;
+        self.cursor.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.is_dirty {
+            self.fs.write(&self.path, self.cursor.get_ref())?;
+            self.is_dirty = false;
+        }
+        Ok(())
+    }
+}
+
+impl<B: FsBackend> Seek for BufferedFile<'_, B> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.cursor.seek(pos)
+    }
+}
+
+impl<B: FsBackend> Drop for BufferedFile<'_, B> {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            eprintln!("Unable to write back {}: {}", self.path.display(), err);
+        }
+    }
+}
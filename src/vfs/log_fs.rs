@@ -0,0 +1,1385 @@
+//! This module provides an append-only, log-structured virtual filesystem (VFS) implementation.
+//! Every modifying operation is persisted on the host as an atomic record in a journal, and the
+//! in-memory state is rebuilt by replaying that journal when the VFS is opened.
+//!
+//! ### Key Features:
+//! - **Append-only journal**: `mkdir()`, `mkfile()`, `write()`, `append()`, `rename()`,
+//!   `symlink()`, `hard_link()`, `set_permissions()` and `rm()` are stored as self-contained, checksummed records.
+//! - **Crash recovery**: a torn (partially written) final record is detected and discarded on replay.
+//! - **Log rotation**: the journal is split into segments of a configurable size.
+//! - **Compaction**: the current state can be folded into a snapshot, after which old segments
+//!   are removed.
+//!
+//! ### Host layout
+//!
+//! All files live directly in the LogFS root directory on the host:
+//! - `snapshot.vfs` — optional snapshot of the state covering journal segments up to some number;
+//! - `journal.NNNNNNNN.log` — journal segments, replayed in ascending order on top of the snapshot.
+//!   The segment with the highest number is the active one (new records are appended to it).
+//!
+//! ### Record format
+//!
+//! Both snapshots and segments start with an 8-byte magic, the snapshot additionally stores the
+//! number of the last segment it covers (`u64`, little-endian). The rest of the file is a sequence
+//! of records:
+//!
+//! ```text
+//! +-------------+-------------+---------------------------------------------------+
+//! | len: u32 LE | crc: u32 LE | payload (len bytes)                               |
+//! +-------------+-------------+---------------------------------------------------+
+//! payload = op: u8 | path_len: u32 LE | path (UTF-8) | [content_len: u64 LE | content]
+//! ```
+//!
+//! `crc` is the CRC-32 (IEEE) checksum of the payload. The content part is present for
+//! `mkfile`, `write`, `append`, `rename`, `symlink`, `hard_link` (these three store the target
+//! path) and `set_permissions` (the mode as `u32` LE) records only.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::VfsError;
+use crate::core::{FsBackend, Result, utils};
+use crate::vfs::file::BufferedFile;
+use crate::{InodeId, MapFS, Metadata, OpenOptions, VfsFile};
+
+const JOURNAL_MAGIC: &[u8; 8] = b"VFSKLOG1";
+const SNAPSHOT_MAGIC: &[u8; 8] = b"VFSKSNP1";
+const SNAPSHOT_FILE: &str = "snapshot.vfs";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.vfs.tmp";
+const RECORD_HEADER_LEN: usize = 8;
+
+/// Default size of a journal segment after which a new one is started (16 MiB).
+pub const DEFAULT_ROTATION_SIZE: u64 = 16 * 1024 * 1024;
+/// Default number of journal segments after which the journal is compacted into a snapshot.
+pub const DEFAULT_MAX_SEGMENTS: usize = 8;
+
+/// An append-only, log-structured virtual file system (VFS).
+///
+/// `LogFS` keeps its state in memory (the same way `MapFS` does) and persists every modifying
+/// operation as a record in a journal stored in the `root` directory on the host. When a `LogFS`
+/// is created over an existing root, the state is rebuilt by loading the snapshot (if any) and
+/// replaying the journal segments.
+///
+/// ### Usage notes:
+/// - Operations that don't change the tree (`cd()`, `read()`, `ls()`, ...) are not journaled;
+///   in particular, CWD is always `/` after reopening.
+/// - A record is written with a single `write` call. If the process crashes in the middle of it,
+///   the incomplete record is discarded (and truncated) on the next open.
+/// - By default, records are not flushed to the disk with `fsync`; use `set_sync(true)` if each
+///   operation must survive a power loss.
+/// - When the active segment grows beyond the rotation size, a new segment is started. When the
+///   number of segments exceeds the configured maximum, the journal is compacted automatically.
+/// - `cleanup()` clears the state and compacts the journal, so the removed data is not kept on disk.
+/// - Not thread‑safe by itself (share it between threads with `SharedFs`).
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{FsBackend, LogFS};
+///
+/// let root = std::env::temp_dir().join("my_log_vfs");
+/// {
+///     let mut fs = LogFS::new(&root).unwrap();
+///     fs.mkfile("/docs/note.txt", Some(b"Hello")).unwrap();
+/// }
+///
+/// // The state is restored from the journal
+/// let fs = LogFS::new(&root).unwrap();
+/// assert_eq!(fs.read("/docs/note.txt").unwrap(), b"Hello");
+/// ```
+pub struct LogFS {
+    state: MapFS,      // in-memory state rebuilt from the journal
+    segment: File,     // active journal segment
+    segment_seq: u64,  // number of the active segment
+    segment_len: u64,  // size of the active segment in bytes
+    snapshot_seq: u64, // number of the last segment covered by the snapshot
+    rotation_size: u64,
+    max_segments: usize,
+    is_sync: bool,
+}
+
+/// Journaled operations.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(u8)]
+enum Op {
+    Mkdir = 1,
+    Mkfile = 2,
+    Write = 3,
+    Append = 4,
+    Rm = 5,
+    Rename = 6,
+    Symlink = 7,
+    HardLink = 8,
+    SetMode = 9,
+}
+
+impl Op {
+    fn from_u8(value: u8) -> Option<Op> {
+        match value {
+            1 => Some(Op::Mkdir),
+            2 => Some(Op::Mkfile),
+            3 => Some(Op::Write),
+            4 => Some(Op::Append),
+            5 => Some(Op::Rm),
+            6 => Some(Op::Rename),
+            7 => Some(Op::Symlink),
+            8 => Some(Op::HardLink),
+            9 => Some(Op::SetMode),
+            _ => None,
+        }
+    }
+
+    fn has_content(&self) -> bool {
+        matches!(
+            self,
+            Op::Mkfile
+                | Op::Write
+                | Op::Append
+                | Op::Rename
+                | Op::Symlink
+                | Op::HardLink
+                | Op::SetMode
+        )
+    }
+}
+
+/// A decoded journal record.
+#[derive(Debug, Clone, PartialEq)]
+struct Record {
+    op: Op,
+    path: PathBuf,
+    content: Vec<u8>,
+}
+
+impl LogFS {
+    /// Opens (or creates) a LogFS instance with the journal stored in the `root` directory.
+    /// * `root` is an absolute host path. If it does not exist, it will be created.
+    ///
+    /// If the directory already contains a journal, the state is rebuilt by replaying it.
+    /// If `root` is not absolute or is not a directory, error returns.
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref();
+
+        if root.as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        if root.is_relative() {
+            return Err(VfsError::invalid_path(
+                root,
+                "the root path must be absolute",
+            ));
+        }
+        if root.exists() && !root.is_dir() {
+            return Err(VfsError::NotADirectory(root.to_path_buf()));
+        }
+
+        let root = utils::normalize(root);
+        std::fs::create_dir_all(&root)?;
+
+        let mut state = MapFS::new();
+        state.set_root(&root)?;
+
+        // Load snapshot
+        let mut snapshot_seq = 0;
+        let snapshot_path = root.join(SNAPSHOT_FILE);
+        if snapshot_path.exists() {
+            let bytes = std::fs::read(&snapshot_path)?;
+            let header_len = SNAPSHOT_MAGIC.len() + 8;
+            if bytes.len() < header_len || &bytes[..SNAPSHOT_MAGIC.len()] != SNAPSHOT_MAGIC {
+                return Err(VfsError::other(format!(
+                    "{} is not a LogFS snapshot",
+                    snapshot_path.display()
+                )));
+            }
+            let seq_bytes = &bytes[SNAPSHOT_MAGIC.len()..header_len];
+            snapshot_seq = u64::from_le_bytes(seq_bytes.try_into().unwrap()); // safe unwrap()
+            let (records, valid_len) = Self::decode_records(&bytes[header_len..]);
+            if valid_len != bytes.len() - header_len {
+                // unlike a journal segment, a snapshot is never appended to, so it cannot be torn
+                return Err(VfsError::other(format!(
+                    "{} is damaged at offset {}",
+                    snapshot_path.display(),
+                    header_len + valid_len
+                )));
+            }
+            for record in records {
+                Self::apply(&mut state, record)?;
+            }
+        }
+
+        // Replay journal segments which are not covered by the snapshot
+        let mut segments = Vec::new();
+        for (seq, path) in Self::list_segments(&root)? {
+            if seq <= snapshot_seq {
+                // leftover of an interrupted compaction
+                std::fs::remove_file(path)?;
+            } else {
+                segments.push((seq, path));
+            }
+        }
+        for (i, (_, path)) in segments.iter().enumerate() {
+            Self::replay_segment(&mut state, path, i + 1 == segments.len())?;
+        }
+
+        let segment_seq = match segments.last() {
+            Some((seq, _)) => *seq,
+            None => {
+                let seq = snapshot_seq + 1;
+                Self::create_segment(&root, seq)?;
+                seq
+            }
+        };
+        let segment_path = Self::segment_path(&root, segment_seq);
+        let segment = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&segment_path)?;
+        let segment_len = segment.metadata()?.len();
+
+        Ok(Self {
+            state,
+            segment,
+            segment_seq,
+            segment_len,
+            snapshot_seq,
+            rotation_size: DEFAULT_ROTATION_SIZE,
+            max_segments: DEFAULT_MAX_SEGMENTS,
+            is_sync: false,
+        })
+    }
+
+    /// Changes the size (in bytes) of a journal segment after which a new segment is started.
+    /// The value `0` disables rotation.
+    pub fn set_rotation_size(&mut self, size: u64) {
+        self.rotation_size = size;
+    }
+
+    /// Changes the number of journal segments after which the journal is compacted automatically.
+    /// The value `0` disables automatic compaction.
+    pub fn set_max_segments(&mut self, count: usize) {
+        self.max_segments = count;
+    }
+
+    /// Changes sync flag.
+    /// If sync flag is true, every record is flushed to the disk (`fsync`) before the
+    /// operation returns.
+    pub fn set_sync(&mut self, sync: bool) {
+        self.is_sync = sync;
+    }
+
+    /// Returns the number of journal segments which are replayed on open.
+    pub fn segments_count(&self) -> usize {
+        (self.segment_seq - self.snapshot_seq) as usize
+    }
+
+    /// Finishes the active journal segment and starts a new one.
+    pub fn rotate(&mut self) -> Result<()> {
+        self.start_segment()?;
+        if self.max_segments > 0 && self.segments_count() > self.max_segments {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Compacts the journal: the current state is written into a new snapshot, after which
+    /// all journal segments covered by it are removed.
+    ///
+    /// The snapshot is written into a temporary file first and then atomically renamed,
+    /// so an interrupted compaction never loses data.
+    pub fn compact(&mut self) -> Result<()> {
+        let covered_seq = self.segment_seq;
+        self.start_segment()?;
+
+        let root = self.state.root().to_path_buf();
+        let tmp_path = root.join(SNAPSHOT_TMP_FILE);
+        {
+            let mut tmp = File::create(&tmp_path)?;
+            let mut buf = Vec::new();
+            buf.extend_from_slice(SNAPSHOT_MAGIC);
+            buf.extend_from_slice(&covered_seq.to_le_bytes());
+            // the snapshot must cover entries the current user has no access to as well
+            self.state.set_enforce_permissions(false);
+            let records = self.encode_state();
+            self.state.set_enforce_permissions(true);
+            buf.extend(records?);
+            tmp.write_all(&buf)?;
+            tmp.sync_all()?;
+        }
+        std::fs::rename(&tmp_path, root.join(SNAPSHOT_FILE))?;
+        self.snapshot_seq = covered_seq;
+
+        for (seq, path) in Self::list_segments(&root)? {
+            if seq <= covered_seq {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the current state as a sequence of records which rebuild it. Hard links are
+    /// kept: the first path of a file is stored with its contents, the others as links to it.
+    /// Permissions are set last, children first, so no record is refused on replay.
+    fn encode_state(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let mut linked: BTreeMap<InodeId, &Path> = BTreeMap::new(); // first path of a file
+        let mut modes = Vec::new();
+        for path in self.state.tree("/")? {
+            if self.state.is_symlink(path)? {
+                let target = self.state.read_link(path)?;
+                let target = Self::path_to_str(&target)?;
+                buf.extend(Self::encode_record(Op::Symlink, path, target.as_bytes())?);
+                continue;
+            }
+            if self.state.is_dir(path)? {
+                buf.extend(Self::encode_record(Op::Mkdir, path, &[])?);
+            } else if let Some(existing) = self.state.inode_id(path).and_then(|id| linked.get(&id))
+            {
+                let existing = Self::path_to_str(existing)?;
+                buf.extend(Self::encode_record(
+                    Op::HardLink,
+                    path,
+                    existing.as_bytes(),
+                )?);
+                continue;
+            } else {
+                if let Some(id) = self.state.inode_id(path) {
+                    linked.insert(id, path);
+                }
+                buf.extend(Self::encode_record(
+                    Op::Mkfile,
+                    path,
+                    &self.state.read(path)?,
+                )?);
+            }
+            let meta = self.state.metadata(path)?;
+            if let Some(mode) = meta.mode()
+                && mode != meta.entry_type().default_mode()
+            {
+                modes.push((path, mode));
+            }
+        }
+        for (path, mode) in modes.into_iter().rev() {
+            buf.extend(Self::encode_record(Op::SetMode, path, &mode.to_le_bytes())?);
+        }
+        Ok(buf)
+    }
+
+    fn to_inner<P: AsRef<Path>>(&self, inner_path: P) -> PathBuf {
+        utils::normalize(self.state.cwd().join(inner_path))
+    }
+
+    /// Applies `change` to the state, appends the record of it to the active segment and rotates
+    /// the journal if needed.
+    ///
+    /// The record is encoded before the state is touched, and the change is reverted if the
+    /// record cannot be appended, so the state never gets ahead of the journal.
+    fn log<F>(&mut self, op: Op, path: &Path, content: &[u8], change: F) -> Result<()>
+    where
+        F: FnOnce(&mut MapFS) -> Result<()>,
+    {
+        let record = Self::encode_record(op, path, content)?;
+        let segment = &mut self.segment;
+        let (segment_len, is_sync) = (self.segment_len, self.is_sync);
+        self.state.transaction(|state| {
+            change(state)?;
+            Self::append_record(segment, segment_len, &record, is_sync)
+        })?;
+        self.segment_len += record.len() as u64;
+
+        if self.rotation_size > 0 && self.segment_len >= self.rotation_size {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    /// Writes `record` at the end of `segment`, which is `len` bytes long. If that fails,
+    /// the torn tail is truncated, so the following records are not appended after it.
+    fn append_record(segment: &mut File, len: u64, record: &[u8], is_sync: bool) -> Result<()> {
+        let result = segment.write_all(record).and_then(|_| match is_sync {
+            true => segment.sync_data(),
+            false => Ok(()),
+        });
+        if let Err(err) = result {
+            if let Err(trunc_err) = segment
+                .set_len(len)
+                .and_then(|_| segment.seek(SeekFrom::Start(len)))
+            {
+                eprintln!("Unable to truncate journal segment: {}", trunc_err);
+            }
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    /// Creates a new empty segment and makes it active.
+    fn start_segment(&mut self) -> Result<()> {
+        let root = self.state.root().to_path_buf();
+        let seq = self.segment_seq + 1;
+        self.segment = Self::create_segment(&root, seq)?;
+        self.segment_seq = seq;
+        self.segment_len = JOURNAL_MAGIC.len() as u64;
+        Ok(())
+    }
+
+    fn segment_path(root: &Path, seq: u64) -> PathBuf {
+        root.join(format!("journal.{:08}.log", seq))
+    }
+
+    fn create_segment(root: &Path, seq: u64) -> Result<File> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(Self::segment_path(root, seq))?;
+        file.write_all(JOURNAL_MAGIC)?;
+        file.sync_all()?;
+        Ok(file)
+    }
+
+    /// Returns journal segments stored in `root`, sorted by their numbers.
+    fn list_segments(root: &Path) -> Result<Vec<(u64, PathBuf)>> {
+        let mut segments = BTreeMap::new();
+        for entry in std::fs::read_dir(root)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let seq = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("journal."))
+                .and_then(|name| name.strip_suffix(".log"))
+                .and_then(|seq| seq.parse::<u64>().ok());
+            if let Some(seq) = seq {
+                segments.insert(seq, entry.path());
+            }
+        }
+        Ok(segments.into_iter().collect())
+    }
+
+    /// Replays all records of a segment. A torn tail of the last (active) segment is truncated;
+    /// an invalid record anywhere else is an error, and the segment is left as is.
+    fn replay_segment(state: &mut MapFS, path: &Path, is_last: bool) -> Result<()> {
+        let bytes = std::fs::read(path)?;
+        if bytes.len() < JOURNAL_MAGIC.len() && is_last {
+            // the segment was being created when the process crashed
+            let mut file = File::create(path)?;
+            file.write_all(JOURNAL_MAGIC)?;
+            return Ok(());
+        }
+        if &bytes[..JOURNAL_MAGIC.len()] != JOURNAL_MAGIC {
+            return Err(VfsError::other(format!(
+                "{} is not a LogFS journal segment",
+                path.display()
+            )));
+        }
+
+        let data = &bytes[JOURNAL_MAGIC.len()..];
+        let (records, valid_len) = Self::decode_records(data);
+        if valid_len < data.len() && !(is_last && Self::is_torn_tail(&data[valid_len..])) {
+            return Err(VfsError::other(format!(
+                "{} is damaged at offset {}",
+                path.display(),
+                JOURNAL_MAGIC.len() + valid_len
+            )));
+        }
+        for record in records {
+            Self::apply(state, record)?;
+        }
+
+        let valid_len = (JOURNAL_MAGIC.len() + valid_len) as u64;
+        if valid_len < bytes.len() as u64 {
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(path)?
+                .set_len(valid_len)?;
+        }
+        Ok(())
+    }
+
+    /// Checks if the invalid record at the start of `tail` reaches the end of the segment,
+    /// i.e. it is the last record, which was being written when the process crashed.
+    fn is_torn_tail(tail: &[u8]) -> bool {
+        if tail.len() < RECORD_HEADER_LEN {
+            return true;
+        }
+        let len = u32::from_le_bytes(tail[..4].try_into().unwrap()) as usize; // safe unwrap()
+        RECORD_HEADER_LEN + len >= tail.len()
+    }
+
+    /// Applies a record to the state.
+    fn apply(state: &mut MapFS, record: Record) -> Result<()> {
+        let Record { op, path, content } = record;
+        let result = match op {
+            Op::Mkdir => state.mkdir(&path),
+            Op::Mkfile => state.mkfile(&path, Some(&content)),
+            Op::Write => state.write(&path, &content),
+            Op::Append => state.append(&path, &content),
+            Op::Rm => state.rm(&path),
+            Op::Rename => match std::str::from_utf8(&content) {
+                Ok(target) => state.rename(&path, target),
+                Err(err) => Err(VfsError::other(err)),
+            },
+            Op::Symlink => match std::str::from_utf8(&content) {
+                Ok(target) => state.symlink(target, &path),
+                Err(err) => Err(VfsError::other(err)),
+            },
+            Op::HardLink => match std::str::from_utf8(&content) {
+                Ok(existing) => state.hard_link(existing, &path),
+                Err(err) => Err(VfsError::other(err)),
+            },
+            Op::SetMode => match <[u8; 4]>::try_from(content.as_slice()) {
+                Ok(mode) => state.set_permissions(&path, u32::from_le_bytes(mode)),
+                Err(err) => Err(VfsError::other(err)),
+            },
+        };
+        result.map_err(|err| {
+            VfsError::other(format!(
+                "corrupted journal: {:?} {}: {}",
+                op,
+                path.display(),
+                err
+            ))
+        })
+    }
+
+    /// Returns `path` as UTF-8, which is required to journal it.
+    fn path_to_str(path: &Path) -> Result<&str> {
+        path.to_str()
+            .ok_or_else(|| VfsError::invalid_path(path, "not a valid UTF-8 path"))
+    }
+
+    /// Encodes a record. An error is returned if the record is too large for the format.
+    fn encode_record(op: Op, path: &Path, content: &[u8]) -> Result<Vec<u8>> {
+        let path_str = Self::path_to_str(path)?;
+        let path_len = u32::try_from(path_str.len())
+            .map_err(|_| VfsError::invalid_path(path, "too long to be journaled"))?;
+
+        let mut payload = Vec::with_capacity(1 + 4 + path_str.len() + 8 + content.len());
+        payload.push(op as u8);
+        payload.extend_from_slice(&path_len.to_le_bytes());
+        payload.extend_from_slice(path_str.as_bytes());
+        if op.has_content() {
+            payload.extend_from_slice(&(content.len() as u64).to_le_bytes());
+            payload.extend_from_slice(content);
+        }
+
+        let payload_len = u32::try_from(payload.len()).map_err(|_| {
+            VfsError::other(format!(
+                "{} bytes are too many to be journaled for {}",
+                content.len(),
+                path.display()
+            ))
+        })?;
+
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+        record.extend_from_slice(&payload_len.to_le_bytes());
+        record.extend_from_slice(&crc32(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+        Ok(record)
+    }
+
+    /// Decodes records until the end of `bytes` or the first incomplete (or damaged) record.
+    /// Returns decoded records and the length of the valid part of `bytes`.
+    fn decode_records(bytes: &[u8]) -> (Vec<Record>, usize) {
+        let mut records = Vec::new();
+        let mut offset = 0;
+        while bytes.len() - offset >= RECORD_HEADER_LEN {
+            let len = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+            let crc = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap());
+            let start = offset + RECORD_HEADER_LEN;
+            if bytes.len() - start < len {
+                break;
+            }
+            let payload = &bytes[start..start + len];
+            if crc32(payload) != crc {
+                break;
+            }
+            match Self::decode_payload(payload) {
+                Some(record) => records.push(record),
+                None => break,
+            }
+            offset = start + len;
+        }
+        (records, offset)
+    }
+
+    fn decode_payload(payload: &[u8]) -> Option<Record> {
+        let op = Op::from_u8(*payload.first()?)?;
+        let path_len = u32::from_le_bytes(payload.get(1..5)?.try_into().ok()?) as usize;
+        let path_end = 5usize.checked_add(path_len)?;
+        let path = std::str::from_utf8(payload.get(5..path_end)?).ok()?;
+        let rest = &payload[path_end..];
+        let content = if op.has_content() {
+            let content_len =
+                usize::try_from(u64::from_le_bytes(rest.get(..8)?.try_into().ok()?)).ok()?;
+            rest.get(8..8usize.checked_add(content_len)?)?.to_vec()
+        } else {
+            Vec::new()
+        };
+        Some(Record {
+            op,
+            path: PathBuf::from(path),
+            content,
+        })
+    }
+}
+
+impl FsBackend for LogFS {
+    /// Returns root path related to the host file system (the directory holding the journal).
+    fn root(&self) -> &Path {
+        self.state.root()
+    }
+
+    /// Returns current working directory related to the vfs root.
+    fn cwd(&self) -> &Path {
+        self.state.cwd()
+    }
+
+    /// Returns a hypothetical "host-path" joining `root` and `inner_path`.
+    /// * `inner_path` must exist in VFS
+    fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+        self.state.to_host(inner_path)
+    }
+
+    /// Changes the current working directory.
+    /// * `path` can be in relative or absolute form, but in both cases it must exist in VFS.
+    ///
+    /// An error is returned if the specified `path` does not exist.
+    fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.state.cd(path)
+    }
+
+    /// Checks if a `path` exists in the VFS.
+    /// The `path` can be in relative or absolute form.
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.state.exists(path)
+    }
+
+    /// Checks if `path` is a directory.
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.state.is_dir(path)
+    }
+
+    /// Checks if `path` is a regular file.
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.state.is_file(path)
+    }
+
+    /// Checks if `path` is a symbolic link.
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.state.is_symlink(path)
+    }
+
+    /// Returns metadata of `path`. See `MapFS::metadata()` for details.
+    /// Times are those of the in-memory state and are not recorded in the journal.
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        self.state.metadata(path)
+    }
+
+    /// Sets the permission bits of a file or directory and journals the operation.
+    /// See `MapFS::set_permissions()` for details.
+    fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        let inner = self.to_inner(&path);
+        self.log(
+            Op::SetMode,
+            &inner,
+            &(mode & 0o7777).to_le_bytes(),
+            |state| state.set_permissions(&inner, mode),
+        )
+    }
+
+    /// Returns an iterator over directory entries at a specific depth (shallow listing).
+    /// See `MapFS::ls()` for details.
+    fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        self.state.ls(path)
+    }
+
+    /// Returns a recursive iterator over the directory tree starting from a given path.
+    /// See `MapFS::tree()` for details.
+    fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        self.state.tree(path)
+    }
+
+    /// Creates directory and all it parents (if needed) and journals the operation.
+    /// * `path` - inner vfs path.
+    fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let inner = self.to_inner(&path);
+        self.log(Op::Mkdir, &inner, &[], |state| state.mkdir(path))
+    }
+
+    /// Creates new file in VFS and journals the operation.
+    /// * `file_path` must be inner VFS path. It must contain the name of the file,
+    ///   optionally preceded by parent directory.
+    ///
+    /// If the parent directory does not exist, it will be created.
+    fn mkfile<P: AsRef<Path>>(&mut self, file_path: P, content: Option<&[u8]>) -> Result<()> {
+        let inner = self.to_inner(&file_path);
+        self.log(Op::Mkfile, &inner, content.unwrap_or_default(), |state| {
+            state.mkfile(&inner, content)
+        })
+    }
+
+    /// Reads the entire contents of a file into a byte vector.
+    /// * `path` is the inner VFS path.
+    fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        self.state.read(self.to_inner(path))
+    }
+
+    /// Writes bytes to an existing file, replacing its entire contents,
+    /// and journals the operation.
+    fn write<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let inner = self.to_inner(&path);
+        self.log(Op::Write, &inner, content, |state| {
+            state.write(&inner, content)
+        })
+    }
+
+    /// Appends bytes to the end of an existing file, preserving its old contents,
+    /// and journals the operation.
+    fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let inner = self.to_inner(&path);
+        self.log(Op::Append, &inner, content, |state| {
+            state.append(&inner, content)
+        })
+    }
+
+    /// Opens a file with the given `options`. The handle works on a copy of the contents,
+    /// which is written back (and journaled as a single `write` record) on `flush()` or drop.
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<impl VfsFile + '_> {
+        BufferedFile::open(self, path, options)
+    }
+
+    /// Moves a file or a whole directory subtree from `from` to `to` and journals the operation.
+    /// See `MapFS::rename()` for details.
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        let inner_from = self.to_inner(&from);
+        let inner_to = self.to_inner(&to);
+        let target = Self::path_to_str(&inner_to)?.to_string();
+        self.log(Op::Rename, &inner_from, target.as_bytes(), |state| {
+            state.rename(from, to)
+        })
+    }
+
+    /// Creates a symbolic link at `link` pointing to `target` and journals the operation.
+    /// See `MapFS::symlink()` for details.
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, target: P, link: Q) -> Result<()> {
+        let inner = self.to_inner(&link);
+        let target_str = Self::path_to_str(target.as_ref())?.to_string();
+        self.log(Op::Symlink, &inner, target_str.as_bytes(), |state| {
+            state.symlink(target, &inner)
+        })
+    }
+
+    /// Returns the target of the symbolic link at `path`.
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        self.state.read_link(path)
+    }
+
+    /// Creates a hard link `new` to the file `existing` and journals the operation.
+    /// See `MapFS::hard_link()` for details.
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, existing: P, new: Q) -> Result<()> {
+        let inner_existing = self.to_inner(&existing);
+        let inner_new = self.to_inner(&new);
+        let existing_str = Self::path_to_str(&inner_existing)?.to_string();
+        self.log(Op::HardLink, &inner_new, existing_str.as_bytes(), |state| {
+            state.hard_link(&inner_existing, &inner_new)
+        })
+    }
+
+    /// Removes a file or directory at the specified path and journals the operation.
+    ///
+    /// - `path`: can be absolute (starting with '/') or relative to the current working
+    ///   directory (cwd). If the path is a directory, all its contents are removed recursively.
+    fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let inner = self.to_inner(&path);
+        if utils::is_virtual_root(&inner) {
+            return Err(VfsError::RootProtected(PathBuf::from("/")));
+        }
+        self.log(Op::Rm, &inner, &[], |state| state.rm(path))
+    }
+
+    /// Removes all artifacts (dirs and files) in vfs and compacts the journal,
+    /// so the removed data is not kept on the host.
+    fn cleanup(&mut self) -> bool {
+        self.state.cleanup();
+        if let Err(err) = self.compact() {
+            eprintln!("Unable to compact journal: {}", err);
+            return false;
+        }
+        true
+    }
+}
+
+/// Calculates CRC-32 (IEEE 802.3) checksum.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn setup_test_env() -> TempDir {
+        TempDir::new("logfs_test").unwrap()
+    }
+
+    mod creations {
+        use super::*;
+
+        #[test]
+        fn test_new_creates_root_and_segment() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let root = temp_dir.path().join("log");
+
+            let fs = LogFS::new(&root)?;
+
+            assert_eq!(fs.root(), root);
+            assert_eq!(fs.cwd(), Path::new("/"));
+            assert!(LogFS::segment_path(&root, 1).exists());
+            assert_eq!(fs.segments_count(), 1);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_new_relative_root() {
+            let result = LogFS::new("relative/root");
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn test_new_root_is_file() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let file = temp_dir.path().join("file");
+            std::fs::write(&file, b"data")?;
+
+            assert!(LogFS::new(&file).is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_new_foreign_segment() -> Result<()> {
+            let temp_dir = setup_test_env();
+            std::fs::write(
+                temp_dir.path().join("journal.00000001.log"),
+                b"not a journal",
+            )?;
+
+            let error = LogFS::new(temp_dir.path()).err().unwrap();
+            assert!(error.to_string().contains("not a LogFS journal"));
+
+            Ok(())
+        }
+    }
+
+    mod replay {
+        use super::*;
+        use crate::OpenOptions;
+
+        #[test]
+        fn test_state_restored_after_reopen() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkdir("/docs/drafts")?;
+                fs.mkfile("/docs/readme.txt", Some(b"Hello"))?;
+                fs.append("/docs/readme.txt", b", world")?;
+                fs.mkfile("/tmp.txt", None)?;
+                fs.write("/tmp.txt", b"temporary")?;
+                fs.rm("/tmp.txt")?;
+            }
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert!(fs.is_dir("/docs/drafts")?);
+            assert_eq!(fs.read("/docs/readme.txt")?, b"Hello, world");
+            assert!(!fs.exists("/tmp.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rename_restored_after_reopen() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/src/a.txt", Some(b"A"))?;
+                fs.cd("/src")?;
+                fs.rename("a.txt", "/dst/b.txt")?;
+                fs.rename("/src", "/old")?;
+            }
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.read("/dst/b.txt")?, b"A");
+            assert!(fs.is_dir("/old")?);
+            assert!(!fs.exists("/src"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_symlink_restored_after_reopen_and_compaction() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/data/v1.txt", Some(b"v1"))?;
+                fs.symlink("data/v1.txt", "/current")?;
+                fs.write("/current", b"v2")?;
+            }
+
+            let mut fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.read_link("/current")?, Path::new("data/v1.txt"));
+            assert_eq!(fs.read("/data/v1.txt")?, b"v2");
+
+            fs.compact()?;
+            drop(fs);
+            let fs = LogFS::new(temp_dir.path())?;
+            assert!(fs.is_symlink("/current")?);
+            assert_eq!(fs.read("/current")?, b"v2");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_hard_link_restored_after_reopen_and_compaction() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/a.txt", Some(b"v1"))?;
+                fs.hard_link("/a.txt", "/links/b.txt")?;
+            }
+
+            let mut fs = LogFS::new(temp_dir.path())?;
+            fs.write("/links/b.txt", b"v2")?;
+            assert_eq!(fs.read("/a.txt")?, b"v2");
+
+            fs.compact()?;
+            drop(fs);
+            let mut fs = LogFS::new(temp_dir.path())?;
+            fs.append("/a.txt", b"!")?;
+            assert_eq!(fs.read("/links/b.txt")?, b"v2!");
+            assert_eq!(
+                fs.state.inode_id("/a.txt"),
+                fs.state.inode_id("/links/b.txt")
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_permissions_restored_after_reopen_and_compaction() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/private/key.pem", Some(b"key"))?;
+                fs.set_permissions("/private/key.pem", 0o400)?;
+                fs.set_permissions("/private", 0o000)?;
+            }
+
+            let mut fs = LogFS::new(temp_dir.path())?;
+            assert!(matches!(
+                fs.read("/private/key.pem"),
+                Err(VfsError::PermissionDenied(_))
+            ));
+
+            fs.compact()?;
+            drop(fs);
+            let mut fs = LogFS::new(temp_dir.path())?;
+            fs.set_permissions("/private", 0o700)?;
+            assert_eq!(fs.metadata("/private/key.pem")?.mode(), Some(0o400));
+            assert_eq!(fs.read("/private/key.pem")?, b"key");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_open_restored_after_reopen() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                let mut file = fs.open("/a.txt", OpenOptions::new().append(true).create(true))?;
+                file.write_all(b"Hello")?;
+                file.flush()?;
+                file.write_all(b", world")?;
+            }
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.read("/a.txt")?, b"Hello, world");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_relative_paths_are_journaled_as_absolute() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkdir("/home/user")?;
+                fs.cd("/home/user")?;
+                fs.mkfile("notes.txt", Some(b"note"))?;
+            }
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.cwd(), Path::new("/"));
+            assert_eq!(fs.read("/home/user/notes.txt")?, b"note");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_failed_operations_are_not_journaled() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkdir("/docs")?;
+                assert!(fs.mkdir("/docs").is_err());
+                assert!(fs.write("/missing.txt", b"data").is_err());
+                assert!(fs.rm("/").is_err());
+            }
+
+            let fs = LogFS::new(temp_dir.path())?;
+            let entries: Vec<_> = fs.tree("/")?.collect();
+            assert_eq!(entries, vec![Path::new("/docs")]);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_unjournaled_operations_are_reverted() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = LogFS::new(temp_dir.path())?;
+            fs.mkfile("/a.txt", Some(b"first"))?;
+
+            // The segment can no longer be written to
+            let segment_path = LogFS::segment_path(temp_dir.path(), 1);
+            fs.segment = File::open(&segment_path)?;
+            assert!(matches!(
+                fs.write("/a.txt", b"second"),
+                Err(VfsError::Io(_))
+            ));
+            assert!(fs.mkdir("/docs").is_err());
+            assert_eq!(fs.read("/a.txt")?, b"first");
+            assert!(!fs.exists("/docs"));
+
+            fs.segment = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&segment_path)?;
+            fs.write("/a.txt", b"third")?;
+            drop(fs);
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.read("/a.txt")?, b"third");
+            assert!(!fs.exists("/docs"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_torn_record_is_discarded() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/a.txt", Some(b"first"))?;
+                fs.mkfile("/b.txt", Some(b"second"))?;
+            }
+
+            // Simulate a crash in the middle of writing the last record
+            let segment = LogFS::segment_path(temp_dir.path(), 1);
+            let len = std::fs::metadata(&segment)?.len();
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(&segment)?
+                .set_len(len - 3)?;
+
+            let mut fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.read("/a.txt")?, b"first");
+            assert!(!fs.exists("/b.txt"));
+
+            // The journal is usable after recovery
+            fs.mkfile("/c.txt", Some(b"third"))?;
+            drop(fs);
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.read("/c.txt")?, b"third");
+            assert!(!fs.exists("/b.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_damaged_record_is_discarded() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/a.txt", Some(b"first"))?;
+                fs.mkfile("/b.txt", Some(b"second"))?;
+            }
+
+            // Corrupt the content of the last record
+            let segment = LogFS::segment_path(temp_dir.path(), 1);
+            let mut bytes = std::fs::read(&segment)?;
+            let last = bytes.len() - 1;
+            bytes[last] ^= 0xFF;
+            std::fs::write(&segment, &bytes)?;
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert!(fs.exists("/a.txt"));
+            assert!(!fs.exists("/b.txt"));
+            assert!(std::fs::metadata(&segment)?.len() < bytes.len() as u64);
+
+            Ok(())
+        }
+        #[test]
+        fn test_damaged_sealed_segment() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/a.txt", Some(b"first"))?;
+                fs.mkfile("/b.txt", Some(b"second"))?;
+                fs.rotate()?;
+                fs.mkfile("/c.txt", Some(b"third"))?;
+            }
+
+            // Corrupt a byte of the first record of the sealed segment
+            let segment = LogFS::segment_path(temp_dir.path(), 1);
+            let mut bytes = std::fs::read(&segment)?;
+            let pos = JOURNAL_MAGIC.len() + RECORD_HEADER_LEN + 1;
+            bytes[pos] ^= 0xFF;
+            std::fs::write(&segment, &bytes)?;
+
+            assert!(matches!(
+                LogFS::new(temp_dir.path()),
+                Err(VfsError::Other(_))
+            ));
+            assert_eq!(std::fs::read(&segment)?, bytes);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_damaged_record_in_the_middle() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/a.txt", Some(b"first"))?;
+                fs.mkfile("/b.txt", Some(b"second"))?;
+            }
+
+            // Corrupt the first record of the active segment, which is followed by another one
+            let segment = LogFS::segment_path(temp_dir.path(), 1);
+            let mut bytes = std::fs::read(&segment)?;
+            let pos = JOURNAL_MAGIC.len() + RECORD_HEADER_LEN + 1;
+            bytes[pos] ^= 0xFF;
+            std::fs::write(&segment, &bytes)?;
+
+            assert!(matches!(
+                LogFS::new(temp_dir.path()),
+                Err(VfsError::Other(_))
+            ));
+            assert_eq!(std::fs::read(&segment)?, bytes);
+
+            Ok(())
+        }
+    }
+
+    mod rotation_compaction {
+        use super::*;
+
+        #[test]
+        fn test_rotation_by_size() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.set_max_segments(0);
+                fs.set_rotation_size(64);
+                for i in 0..10 {
+                    fs.mkfile(format!("/file{}.txt", i), Some(&[b'x'; 40]))?;
+                }
+                assert!(fs.segments_count() > 1);
+                assert_eq!(
+                    LogFS::list_segments(temp_dir.path())?.len(),
+                    fs.segments_count()
+                );
+            }
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.tree("/")?.count(), 10);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_compact() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkdir("/docs")?;
+                fs.mkfile("/docs/a.txt", Some(b"A"))?;
+                fs.mkfile("/docs/b.txt", Some(b"B"))?;
+                fs.rm("/docs/b.txt")?;
+                fs.rotate()?;
+                fs.append("/docs/a.txt", b"A")?;
+
+                fs.compact()?;
+                assert_eq!(fs.segments_count(), 1);
+                assert!(temp_dir.path().join(SNAPSHOT_FILE).exists());
+
+                // journal continues after compaction
+                fs.mkfile("/after.txt", Some(b"after"))?;
+            }
+
+            let segments = LogFS::list_segments(temp_dir.path())?;
+            assert_eq!(segments.len(), 1);
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.read("/docs/a.txt")?, b"AA");
+            assert!(!fs.exists("/docs/b.txt"));
+            assert_eq!(fs.read("/after.txt")?, b"after");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_auto_compaction() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let mut fs = LogFS::new(temp_dir.path())?;
+            fs.set_rotation_size(32);
+            fs.set_max_segments(3);
+
+            for i in 0..20 {
+                fs.mkfile(format!("/file{}.txt", i), Some(b"some content"))?;
+                assert!(fs.segments_count() <= 3);
+            }
+            assert!(temp_dir.path().join(SNAPSHOT_FILE).exists());
+            drop(fs);
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.tree("/")?.count(), 20);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_stale_segments_removed_on_open() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/a.txt", Some(b"A"))?;
+                fs.compact()?;
+            }
+
+            // Simulate a crash right after the snapshot was renamed
+            let stale = LogFS::segment_path(temp_dir.path(), 1);
+            std::fs::write(&stale, JOURNAL_MAGIC)?;
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert!(!stale.exists());
+            assert_eq!(fs.read("/a.txt")?, b"A");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_damaged_snapshot() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/a.txt", Some(b"A"))?;
+                fs.mkfile("/b.txt", Some(b"B"))?;
+                fs.compact()?;
+                fs.write("/b.txt", b"BB")?;
+            }
+
+            let snapshot = temp_dir.path().join(SNAPSHOT_FILE);
+            let mut bytes = std::fs::read(&snapshot)?;
+            bytes.truncate(bytes.len() - 1);
+            std::fs::write(&snapshot, &bytes)?;
+
+            let error = LogFS::new(temp_dir.path()).err().unwrap();
+            assert!(error.to_string().contains("is damaged"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_cleanup() -> Result<()> {
+            let temp_dir = setup_test_env();
+            {
+                let mut fs = LogFS::new(temp_dir.path())?;
+                fs.mkfile("/docs/secret.txt", Some(b"secret"))?;
+                assert!(fs.cleanup());
+                assert!(!fs.exists("/docs"));
+            }
+
+            let fs = LogFS::new(temp_dir.path())?;
+            assert_eq!(fs.tree("/")?.count(), 0);
+            for (_, segment) in LogFS::list_segments(temp_dir.path())? {
+                let bytes = std::fs::read(segment)?;
+                assert!(!bytes.windows(6).any(|w| w == b"secret"));
+            }
+
+            Ok(())
+        }
+    }
+
+    mod records {
+        use super::*;
+
+        #[test]
+        fn test_crc32() {
+            assert_eq!(crc32(b""), 0);
+            assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        }
+
+        #[test]
+        fn test_encode_decode_roundtrip() -> Result<()> {
+            let mut bytes = Vec::new();
+            bytes.extend(LogFS::encode_record(Op::Mkdir, Path::new("/docs"), &[])?);
+            bytes.extend(LogFS::encode_record(
+                Op::Mkfile,
+                Path::new("/docs/файл.txt"),
+                b"content",
+            )?);
+            bytes.extend(LogFS::encode_record(
+                Op::Rm,
+                Path::new("/docs"),
+                b"ignored",
+            )?);
+
+            let (records, valid_len) = LogFS::decode_records(&bytes);
+            assert_eq!(valid_len, bytes.len());
+            assert_eq!(records.len(), 3);
+            assert_eq!(records[1].op, Op::Mkfile);
+            assert_eq!(records[1].path, Path::new("/docs/файл.txt"));
+            assert_eq!(records[1].content, b"content");
+            assert!(records[2].content.is_empty());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_decode_oversized_lengths() {
+            let mut payload = vec![Op::Write as u8];
+            payload.extend_from_slice(&1u32.to_le_bytes());
+            payload.push(b'/');
+            payload.extend_from_slice(&u64::MAX.to_le_bytes());
+            assert_eq!(LogFS::decode_payload(&payload), None);
+
+            let mut payload = vec![Op::Mkdir as u8];
+            payload.extend_from_slice(&u32::MAX.to_le_bytes());
+            assert_eq!(LogFS::decode_payload(&payload), None);
+        }
+    }
+}
@@ -1,12 +1,24 @@
 //! This module provides a virtual filesystem (VFS) implementation that maps to a memory storage.
 
 use std::collections::BTreeMap;
+use std::io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
-use anyhow::anyhow;
+use imbl::OrdMap;
 
+use crate::VfsError;
 use crate::core::{FsBackend, Result, utils};
-use crate::{Entry, EntryType};
+use crate::glob::Pattern;
+use crate::vfs::file::{check_access, prepare_open};
+use crate::{DirFS, Entry, EntryType, Inode, InodeId, Metadata, OpenOptions, VfsFile};
+
+mod image;
+
+// Permission bits of one class (owner, group or others).
+const READ: u32 = 0o4;
+const WRITE: u32 = 0o2;
+const EXECUTE: u32 = 0o1;
 
 /// A virtual file system (VFS) implementation that stores file and directory entries in memory
 /// using a hierarchical map structure.
@@ -18,9 +30,9 @@ use crate::{Entry, EntryType};
 /// ### Internal state
 ///
 /// * `root` — An absolute, normalized path associated with the host that serves as the physical
-/// anchor of the virtual file system (VFS). It has no effect on VFS operation under typical usage
-/// scenarios. This path determines how virtual paths are mapped to host paths
-/// (e.g., for synchronization or persistent storage layers).
+///   anchor of the virtual file system (VFS). It has no effect on VFS operation under typical usage
+///   scenarios. This path determines how virtual paths are mapped to host paths
+///   (e.g., for synchronization or persistent storage layers).
 ///   - Must be absolute and normalized (no `..`, no redundant separators).
 ///   - Example: `/tmp/my_vfs_root` on Unix, `C:\\vfs\\root` on Windows.
 ///
@@ -53,10 +65,34 @@ use crate::{Entry, EntryType};
 /// - Path resolution (e.g., in `is_file()`, `ls()`) combines `cwd` with input paths to produce
 ///   inner absolute paths before querying `entries`.
 ///
+/// ### Hard links
+///
+/// File contents are kept in an inode table, which entries point into; a file may have several
+/// hard links (see `hard_link()`) and its contents are freed when the last of them is removed.
+///
+/// ### Permissions
+///
+/// Unix permissions are emulated: every entry has permission bits and an owner (see
+/// `set_permissions()` and `set_owner()`), which are checked against the user set with
+/// `set_user()`, the same way the host does it (there is no superuser, though):
+/// - reading a file requires `r`, writing it `w`;
+/// - listing a directory requires `r` and `x`, going through it (in any path) `x`;
+/// - creating, removing or renaming an entry requires `w` and `x` on its parent directory.
+///
+/// Queries of existence and type are not checked, and the root is always accessible.
+/// Enforcement can be switched off with `set_enforce_permissions(false)`.
+///
+/// ### Expiry
+///
+/// Any file or directory may be given a time-to-live (see `mkfile_with_ttl()` and `set_ttl()`).
+/// Once expired, the entry (and, for a directory, all its contents) behaves as if it was removed:
+/// it is hidden from `exists()`, `ls()`, `tree()`, etc. and is physically evicted on the next
+/// modifying operation touching it, or by an explicit `purge_expired()` call.
+///
 /// ### Thread Safety
 ///
-/// This struct is **not thread‑safe by default**. If concurrent access is required, wrap it in
-/// a synchronization primitive (e.g., `Arc<Mutex<MapFS>>` or `RwLock<MapFS>`) at the application level.
+/// This struct is **not thread‑safe by default**. If concurrent access is required, share it with
+/// `SharedFs`, which gives every thread its own handle (and its own `cwd`) over one `RwLock`.
 ///
 /// ### Example
 ///
@@ -71,9 +107,114 @@ use crate::{Entry, EntryType};
 /// fs.rm("/docs/note.txt").unwrap();
 /// ```
 pub struct MapFS {
-    root: PathBuf,                     // host-related absolute normalized path
-    cwd: PathBuf,                      // inner absolute normalized path
-    entries: BTreeMap<PathBuf, Entry>, // inner absolute normalized paths
+    root: PathBuf,                          // host-related absolute normalized path
+    cwd: PathBuf,                           // inner absolute normalized path
+    entries: OrdMap<PathBuf, Entry>, // inner absolute normalized paths; shared with snapshots
+    inodes: OrdMap<InodeId, Inode>,  // contents of files, shared by their hard links
+    next_inode: InodeId,
+    uid: u32, // user the operations are performed as
+    gid: u32,
+    is_enforcing: bool,
+    snapshots: BTreeMap<SnapshotId, Snapshot>,
+    next_snapshot: u64,
+}
+
+/// Identifier of a snapshot taken with `MapFS::snapshot()`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnapshotId(u64);
+
+/// The tree of a `MapFS` at the moment of `snapshot()`, sharing its maps with the live tree.
+struct Snapshot {
+    cwd: PathBuf,
+    entries: OrdMap<PathBuf, Entry>,
+    inodes: OrdMap<InodeId, Inode>,
+    next_inode: InodeId,
+}
+
+/// A running `MapFS::transaction()`; reverts the tree to `saved` when dropped uncommitted
+/// (on error or panic).
+struct Transaction<'a> {
+    fs: &'a mut MapFS,
+    saved: Option<Snapshot>,
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if let Some(saved) = self.saved.take() {
+            self.fs.revert(&saved);
+        }
+    }
+}
+
+/// Options of `MapFS::from_host_dir()`.
+///
+/// Patterns have the syntax of `FsBackend::glob()` and are matched against paths relative to
+/// the imported directory (e.g. `**/*.json` or `target/**`).
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{ImportOptions, MapFS};
+///
+/// let fs = MapFS::from_host_dir(
+///     "tests/fixtures",
+///     ImportOptions::new()
+///         .include("**/*.{json,toml}")
+///         .exclude("**/.git")
+///         .max_total_size(16 * 1024 * 1024),
+/// )
+/// .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    max_total_size: Option<u64>,
+}
+
+impl ImportOptions {
+    /// Creates options importing everything, without a size limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Imports only files and links matching `pattern` (or any other included one), with the
+    /// directories leading to them. Directories matching it are imported even if empty.
+    pub fn include(&mut self, pattern: &str) -> &mut Self {
+        self.include.push(pattern.to_string());
+        self
+    }
+
+    /// Skips entries matching `pattern`; an excluded directory is not walked at all.
+    /// Exclusion takes precedence over inclusion.
+    pub fn exclude(&mut self, pattern: &str) -> &mut Self {
+        self.exclude.push(pattern.to_string());
+        self
+    }
+
+    /// Fails the import once the total size of imported files would exceed `bytes`.
+    pub fn max_total_size(&mut self, bytes: u64) -> &mut Self {
+        self.max_total_size = Some(bytes);
+        self
+    }
+}
+
+/// Compiled `ImportOptions` and the state of a running import.
+struct Import {
+    host_root: PathBuf,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    max_total_size: Option<u64>,
+    total_size: u64,
+}
+
+impl Import {
+    fn is_included(&self, relative: &Path) -> bool {
+        self.include.is_empty() || self.include.iter().any(|p| p.matches(relative))
+    }
+
+    fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude.iter().any(|p| p.matches(relative))
+    }
 }
 
 impl MapFS {
@@ -83,25 +224,619 @@ impl MapFS {
         Self {
             root: PathBuf::from("/"),
             cwd: PathBuf::from("/"),
-            entries: BTreeMap::new(),
+            entries: OrdMap::new(),
+            inodes: OrdMap::new(),
+            next_inode: 0,
+            uid: 0,
+            gid: 0,
+            is_enforcing: true,
+            snapshots: BTreeMap::new(),
+            next_snapshot: 0,
         }
     }
 
     /// Changes root path.
     /// * `path` must be an absolute
+    ///
     /// If `path` isn't an absolute error returns.
     pub fn set_root<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         let path = path.as_ref();
         if !path.is_absolute() {
-            return Err(anyhow!("root path must be an absolute"));
+            return Err(VfsError::invalid_path(
+                path,
+                "root path must be an absolute",
+            ));
         }
         self.root = path.to_path_buf();
         Ok(())
     }
 
+    /// Creates a `MapFS` with the contents of the host directory at `path`, loaded recursively
+    /// (see `ImportOptions` to filter entries and to limit the size). The root of the new
+    /// `MapFS` is set to the canonical absolute form of `path`.
+    ///
+    /// Host symbolic links are imported as links (never followed): an absolute target inside
+    /// `path` is converted into an inner path, a target outside `path` is refused with an error.
+    /// Special files (sockets, pipes, ...) are skipped. Permissions and times are not imported.
+    ///
+    /// An error is returned if `path` is not an existing directory, if a pattern is invalid or
+    /// if the size limit is exceeded.
+    pub fn from_host_dir<P: AsRef<Path>>(path: P, options: &ImportOptions) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(VfsError::NotFound(path.to_path_buf()));
+        }
+        if !path.is_dir() {
+            return Err(VfsError::NotADirectory(path.to_path_buf()));
+        }
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| Pattern::new(pattern))
+                .collect::<Result<Vec<_>>>()
+        };
+        let mut import = Import {
+            host_root: std::fs::canonicalize(path)?,
+            include: compile(&options.include)?,
+            exclude: compile(&options.exclude)?,
+            max_total_size: options.max_total_size,
+            total_size: 0,
+        };
+
+        let mut fs = MapFS::new();
+        fs.set_root(&import.host_root)?;
+        let host_root = import.host_root.clone();
+        fs.import_dir(&mut import, &host_root, Path::new("/"))?;
+        Ok(fs)
+    }
+
+    /// Writes the whole tree to the host directory `host_dir` (an absolute path, created if
+    /// missing, which must be empty otherwise) and returns a `DirFS` rooted there, tracking
+    /// everything written.
+    ///
+    /// The returned `DirFS` has auto-clean switched off, so the files stay on the host after it
+    /// is dropped (e.g. to inspect the state of a failed test); switch it on with
+    /// `set_auto_clean(true)` to remove them on drop instead.
+    ///
+    /// Files are written with their contents and permission bits, hard links and symbolic
+    /// links are recreated as such. Permission checks of `MapFS` do not apply, expired entries
+    /// are skipped, owners and times are not written. If writing fails, everything written so
+    /// far is removed.
+    pub fn materialize<P: AsRef<Path>>(&self, host_dir: P) -> Result<DirFS> {
+        let host_dir = host_dir.as_ref();
+        if host_dir.is_dir() && std::fs::read_dir(host_dir)?.next().is_some() {
+            return Err(VfsError::AlreadyExists(host_dir.to_path_buf()));
+        }
+        let mut dir = DirFS::new(host_dir)?; // auto-clean removes a partial tree on error
+
+        let now = SystemTime::now();
+        let mut written: BTreeMap<InodeId, &Path> = BTreeMap::new();
+        let mut modes = Vec::new();
+        for (path, entry) in self.entries.iter() {
+            if self.is_expired(path, now) {
+                continue;
+            }
+            if let Some(target) = entry.link_target() {
+                dir.symlink(target, path)?;
+            } else if let Some(id) = entry.inode() {
+                if let Some(existing) = written.get(&id) {
+                    dir.hard_link(existing, path)?;
+                    continue;
+                }
+                let inode = &self.inodes[&id];
+                dir.mkfile(path, Some(inode.content()))?;
+                written.insert(id, path);
+                modes.push((path, inode.mode()));
+            } else {
+                dir.mkdir(path)?;
+                modes.push((path, entry.mode()));
+            }
+        }
+        // children first, so restricted directories do not block setting the rest
+        for (path, mode) in modes.into_iter().rev() {
+            dir.set_permissions(path, mode)?;
+        }
+
+        dir.set_auto_clean(false);
+        Ok(dir)
+    }
+
+    /// Sets the user and group the operations are performed as (`0` and `0` by default).
+    /// New entries are owned by them, and permission bits are checked against them.
+    pub fn set_user(&mut self, uid: u32, gid: u32) {
+        self.uid = uid;
+        self.gid = gid;
+    }
+
+    /// Returns the user and group the operations are performed as.
+    pub fn user(&self) -> (u32, u32) {
+        (self.uid, self.gid)
+    }
+
+    /// Changes the owner of an existing file or directory (symbolic links are followed).
+    /// Unlike the host, any user may change the owner, so fixtures can be set up freely.
+    ///
+    /// An error is returned if `path` does not exist or is the root.
+    pub fn set_owner<P: AsRef<Path>>(&mut self, path: P, uid: u32, gid: u32) -> Result<()> {
+        let inner = self.resolve(path, true)?;
+        if utils::is_virtual_root(&inner) {
+            return Err(VfsError::RootProtected(inner));
+        }
+        self.evict_expired(&inner);
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        match self.inode_mut(&inner) {
+            Some(inode) => inode.set_owner(uid, gid),
+            None => self.entries
+                .get_mut(&inner)
+                .unwrap()
+                .set_owner(uid, gid), // safe unwrap()
+        }
+        Ok(())
+    }
+
+    /// Switches checking of permission bits on (default) or off.
+    pub fn set_enforce_permissions(&mut self, enforce: bool) {
+        self.is_enforcing = enforce;
+    }
+
+    /// Creates new file in VFS which expires after `ttl`.
+    /// Apart from the expiry, works the same way as `mkfile()`.
+    pub fn mkfile_with_ttl<P: AsRef<Path>>(
+        &mut self,
+        file_path: P,
+        content: Option<&[u8]>,
+        ttl: Duration,
+    ) -> Result<()> {
+        let expires_at = Self::expiry(ttl);
+        let inner = self.resolve(file_path, false)?;
+        self.mkfile(&inner, content)?;
+        self.entries
+            .get_mut(&inner)
+            .unwrap() // safe unwrap(): just created
+            .set_expires_at(expires_at);
+        Ok(())
+    }
+
+    /// Sets time-to-live of an existing file or directory, counting from now.
+    /// An expired directory expires with all its contents.
+    /// * `ttl` - time-to-live, or `None` to make the entry permanent again. A `ttl` too long
+    ///   to be represented (e.g. `Duration::MAX`) never expires.
+    ///
+    /// An error is returned if `path` does not exist or is the root.
+    pub fn set_ttl<P: AsRef<Path>>(&mut self, path: P, ttl: Option<Duration>) -> Result<()> {
+        let inner = self.resolve(path, false)?;
+        if utils::is_virtual_root(&inner) {
+            return Err(VfsError::RootProtected(inner));
+        }
+        self.evict_expired(&inner);
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        let expires_at = ttl.and_then(Self::expiry);
+        self.entries
+            .get_mut(&inner)
+            .unwrap() // safe unwrap()
+            .set_expires_at(expires_at);
+        Ok(())
+    }
+
+    /// Returns the moment when `path` expires, or `None` if it has no time-to-live.
+    /// Note that an entry also expires together with any of its parent directories.
+    pub fn expires_at<P: AsRef<Path>>(&self, path: P) -> Result<Option<SystemTime>> {
+        let inner = self.resolve(path, false)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(self
+            .entries
+            .get(&inner)
+            .and_then(|entry| entry.expires_at()))
+    }
+
+    /// Removes all expired entries (with their contents) from VFS.
+    /// Returns the number of removed entries.
+    pub fn purge_expired(&mut self) -> usize {
+        let now = SystemTime::now();
+        let expired: Vec<PathBuf> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expires_at().is_some_and(|t| t <= now))
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        let count = self.entries.len();
+        for path in &expired {
+            self.remove_subtree(path);
+        }
+        count - self.entries.len()
+    }
+
+    /// Returns the id of the inode holding the contents of the file at `path` (symbolic links
+    /// are followed), or `None` if `path` is not a file. Hard links of one file share the id.
+    pub fn inode_id<P: AsRef<Path>>(&self, path: P) -> Option<InodeId> {
+        let inner = self.resolve(path, true).ok()?;
+        if !self.is_live(&inner) {
+            return None;
+        }
+        self.entries.get(&inner).and_then(Entry::inode)
+    }
+
+    /// Captures the tree (entries, file contents and the working directory) and returns the id
+    /// `restore()` reverts to.
+    ///
+    /// Taking a snapshot is cheap: it shares the tree with the live `MapFS`. A change afterwards
+    /// copies only the part of the index on the path to the changed entry, and file contents
+    /// stay shared until they are modified. Access times are not captured: entries and inodes
+    /// shared with a snapshot share their access time too, so reading them updates it in both.
+    /// Snapshots are kept until `discard_snapshot()` and are not saved into images.
+    ///
+    /// ### Example:
+    /// ```no_run
+    /// use vfs_kit::{FsBackend, MapFS};
+    ///
+    /// let mut fs = MapFS::new();
+    /// fs.mkfile("/data.txt", Some(b"initial")).unwrap();
+    /// let initial = fs.snapshot();
+    ///
+    /// fs.write("/data.txt", b"changed").unwrap();
+    /// fs.restore(initial).unwrap();
+    /// assert_eq!(fs.read("/data.txt").unwrap(), b"initial");
+    /// ```
+    pub fn snapshot(&mut self) -> SnapshotId {
+        let id = SnapshotId(self.next_snapshot);
+        self.next_snapshot += 1;
+        let snapshot = self.capture();
+        self.snapshots.insert(id, snapshot);
+        id
+    }
+
+    /// Reverts the tree to the snapshot `id`. The snapshot is kept, so the same state may be
+    /// restored any number of times. The user, permission enforcement and other snapshots
+    /// are not affected.
+    ///
+    /// An error is returned if there is no snapshot `id`.
+    pub fn restore(&mut self, id: SnapshotId) -> Result<()> {
+        let Some(snapshot) = self.snapshots.remove(&id) else {
+            return Err(VfsError::other(format!("no snapshot with id {}", id.0)));
+        };
+        self.revert(&snapshot);
+        self.snapshots.insert(id, snapshot);
+        Ok(())
+    }
+
+    /// Drops the snapshot `id`, freeing the entries and contents only it refers to.
+    ///
+    /// An error is returned if there is no snapshot `id`.
+    pub fn discard_snapshot(&mut self, id: SnapshotId) -> Result<()> {
+        match self.snapshots.remove(&id) {
+            Some(_) => Ok(()),
+            None => Err(VfsError::other(format!("no snapshot with id {}", id.0))),
+        }
+    }
+
+    /// Runs `f` as a transaction: the changes it makes are applied all-or-nothing.
+    /// If `f` returns an error or panics, the tree (with the working directory) is reverted to
+    /// the state before the call; otherwise the changes are kept.
+    ///
+    /// The state is saved the same cheap way `snapshot()` does it. Transactions may be nested.
+    ///
+    /// ### Example:
+    /// ```no_run
+    /// use vfs_kit::{FsBackend, MapFS};
+    ///
+    /// let mut fs = MapFS::new();
+    /// let result = fs.transaction(|fs| {
+    ///     fs.mkfile("/out/a.rs", Some(b"mod b;"))?;
+    ///     fs.write("/out/missing.rs", b"")
+    /// });
+    /// assert!(result.is_err());
+    /// assert!(!fs.exists("/out"));
+    /// ```
+    pub fn transaction<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let mut tx = Transaction {
+            saved: Some(self.capture()),
+            fs: self,
+        };
+        let result = f(tx.fs);
+        if result.is_ok() {
+            tx.saved = None; // commit
+        }
+        result
+    }
+
+    /// Writes the whole state to `writer` as a portable image, which `load()` restores: the
+    /// tree with contents, hard links, permissions, owners, times and time-to-live of entries,
+    /// `root`, `cwd`, the user and the enforcement flag. Paths must be valid UTF-8.
+    ///
+    /// With the `serde` feature, `MapFS` also implements `Serialize` and `Deserialize` with the
+    /// same contents, so any serde format (JSON, CBOR, ...) can be used instead.
+    ///
+    /// ### Image format (version 1)
+    ///
+    /// All integers are little-endian, times are `u64` nanoseconds since `UNIX_EPOCH`.
+    ///
+    /// ```text
+    /// image   = magic "VFSKIMAG" | version: u32 | root: str | cwd: str | uid: u32 | gid: u32
+    ///           | enforce: u8 | inode_count: u64 | inode* | entry_count: u64 | entry*
+    /// inode   = id: u64 | attrs | content_len: u64 | content
+    /// entry   = path: str | type: u8 (1 file, 2 directory, 3 link) | attrs
+    ///           | has_ttl: u8 | [expires_at: u64] | inode_id: u64 (files) | target: str (links)
+    /// attrs   = mode: u32 | uid: u32 | gid: u32 | created | modified | accessed
+    /// str     = len: u32 | UTF-8 bytes
+    /// ```
+    ///
+    /// Files point to inodes by id, so hard links share one inode. The root is not stored.
+    pub fn save<W: Write>(&self, writer: W) -> Result<()> {
+        image::Image::from_fs(self).write(&mut BufWriter::new(writer))
+    }
+
+    /// Restores a `MapFS` from an image written by `save()`.
+    ///
+    /// An error is returned if the data is not an image, has an unsupported version, is
+    /// truncated or inconsistent (e.g. an entry without a parent directory).
+    pub fn load<R: Read>(reader: R) -> Result<Self> {
+        image::Image::read(&mut BufReader::new(reader))?.into_fs()
+    }
+
+    /// Imports the contents of `host_dir` into the existing directory `inner_dir`.
+    fn import_dir(&mut self, import: &mut Import, host_dir: &Path, inner_dir: &Path) -> Result<()> {
+        let mut children = std::fs::read_dir(host_dir)?.collect::<io::Result<Vec<_>>>()?;
+        children.sort_by_key(|child| child.file_name()); // deterministic with a size limit
+        for child in children {
+            let host_path = child.path();
+            let inner = inner_dir.join(child.file_name());
+            let relative = inner.strip_prefix("/")?;
+            if import.is_excluded(relative) {
+                continue;
+            }
+            let file_type = child.file_type()?; // links are not followed
+            if file_type.is_dir() {
+                if import.is_included(relative) {
+                    self.mkdir(&inner)?;
+                }
+                self.import_dir(import, &host_path, &inner)?;
+            } else if !import.is_included(relative) {
+                continue;
+            } else if file_type.is_symlink() {
+                let target = std::fs::read_link(&host_path)?;
+                let target = if target.is_absolute() {
+                    let Ok(rest) = target.strip_prefix(&import.host_root) else {
+                        return Err(VfsError::invalid_path(
+                            &host_path,
+                            "the link points outside the imported directory",
+                        ));
+                    };
+                    Path::new("/").join(rest)
+                } else {
+                    target
+                };
+                self.symlink(target, &inner)?;
+            } else if file_type.is_file() {
+                import.total_size += child.metadata()?.len();
+                if let Some(limit) = import.max_total_size
+                    && import.total_size > limit
+                {
+                    return Err(VfsError::other(format!(
+                        "{} exceeds the size limit of {limit} bytes",
+                        import.host_root.display()
+                    )));
+                }
+                self.mkfile(&inner, Some(&std::fs::read(&host_path)?))?;
+            }
+        }
+        Ok(())
+    }
+
     fn to_inner<P: AsRef<Path>>(&self, inner_path: P) -> PathBuf {
         utils::normalize(self.cwd.join(inner_path))
     }
+
+    /// Returns the moment `ttl` from now, or `None` if it cannot be represented.
+    fn expiry(ttl: Duration) -> Option<SystemTime> {
+        SystemTime::now().checked_add(ttl)
+    }
+
+    /// Converts `path` into an inner absolute path with symbolic links resolved
+    /// (see `utils::resolve()`).
+    fn resolve<P: AsRef<Path>>(&self, path: P, follow_last: bool) -> Result<PathBuf> {
+        let now = SystemTime::now();
+        utils::resolve(&self.to_inner(path), follow_last, |inner| {
+            Ok(self
+                .entries
+                .get(inner)
+                .filter(|_| !self.is_expired(inner, now))
+                .and_then(Entry::link_target)
+                .map(Path::to_path_buf))
+        })
+    }
+
+    /// Checks if there is a live entry at the resolved `inner_path` (a link is not followed).
+    fn is_live(&self, inner_path: &Path) -> bool {
+        utils::is_virtual_root(inner_path)
+            || (self.entries.contains_key(inner_path)
+                && !self.is_expired(inner_path, SystemTime::now()))
+    }
+
+    /// Checks if `inner_path` or any of its parents has expired.
+    fn is_expired(&self, inner_path: &Path, now: SystemTime) -> bool {
+        inner_path.ancestors().any(|path| {
+            self.entries
+                .get(path)
+                .and_then(|entry| entry.expires_at())
+                .is_some_and(|t| t <= now)
+        })
+    }
+
+    /// Physically removes the expired part of the tree on the way to `inner_path`.
+    fn evict_expired(&mut self, inner_path: &Path) {
+        let now = SystemTime::now();
+        let expired = inner_path
+            .ancestors()
+            .filter(|path| {
+                self.entries
+                    .get(*path)
+                    .and_then(|entry| entry.expires_at())
+                    .is_some_and(|t| t <= now)
+            })
+            .last()
+            .map(Path::to_path_buf);
+        if let Some(expired) = expired {
+            self.remove_subtree(&expired);
+        }
+    }
+
+    /// Removes `inner_path` and all its contents from `entries`.
+    /// Inodes of removed files are freed once their last link is gone.
+    fn remove_subtree(&mut self, inner_path: &Path) {
+        let removed: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|&pb| pb.starts_with(inner_path)) // Match prefix (includes subpaths)
+            .cloned()
+            .collect();
+
+        for p in &removed {
+            if let Some(id) = self.entries
+                .remove(p)
+                .and_then(|entry| entry.inode())
+            {
+                self.unlink_inode(id);
+            }
+        }
+    }
+
+    /// Stores `content` in a new inode with one link and returns its id.
+    fn add_inode(&mut self, content: &[u8]) -> InodeId {
+        let id = self.next_inode;
+        self.next_inode += 1;
+        let mut inode = Inode::new(content);
+        inode.set_owner(self.uid, self.gid);
+        self.inodes.insert(id, inode);
+        id
+    }
+
+    /// Drops one link of the inode `id`, freeing the inode if it was the last one.
+    fn unlink_inode(&mut self, id: InodeId) {
+        let inodes = &mut self.inodes;
+        if let Some(inode) = inodes.get_mut(&id)
+            && inode.unlink() == 0
+        {
+            inodes.remove(&id);
+        }
+    }
+
+    /// Returns the inode of the file entry at the resolved `inner_path`.
+    fn inode(&self, inner_path: &Path) -> Option<&Inode> {
+        self.entries
+            .get(inner_path)
+            .and_then(Entry::inode)
+            .and_then(|id| self.inodes.get(&id))
+    }
+
+    /// Returns the inode of the file entry at the resolved `inner_path` for modification.
+    fn inode_mut(&mut self, inner_path: &Path) -> Option<&mut Inode> {
+        self.entries
+            .get(inner_path)
+            .and_then(Entry::inode)
+            .and_then(|id| self.inodes.get_mut(&id))
+    }
+
+    /// Checks that no existing parent of the resolved `inner_path` is a file (or a link).
+    fn check_dir_parents(&self, inner_path: &Path) -> Result<()> {
+        let now = SystemTime::now();
+        for parent in inner_path.ancestors().skip(1) {
+            if let Some(entry) = self.entries.get(parent)
+                && !entry.is_dir()
+                && !self.is_expired(parent, now)
+            {
+                return Err(VfsError::NotADirectory(parent.to_path_buf()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the parents of the resolved `inner_path` are directories and that the
+    /// current user may create or remove entries in its parent directory (a missing parent
+    /// is checked by `mkdir()` on creation).
+    fn check_parent(&self, inner_path: &Path) -> Result<()> {
+        self.check_dir_parents(inner_path)?;
+        match inner_path.parent() {
+            Some(parent) if self.exists(parent) => self.check_access(parent, WRITE | EXECUTE),
+            _ => Ok(()),
+        }
+    }
+
+    /// Inserts `entry` at `inner_path` owned by the current user.
+    fn insert_entry(&mut self, inner_path: PathBuf, mut entry: Entry) {
+        entry.set_owner(self.uid, self.gid);
+        self.entries.insert(inner_path, entry);
+    }
+
+    /// Captures the tree, sharing it with the live state.
+    fn capture(&self) -> Snapshot {
+        Snapshot {
+            cwd: self.cwd.clone(),
+            entries: self.entries.clone(),
+            inodes: self.inodes.clone(),
+            next_inode: self.next_inode,
+        }
+    }
+
+    /// Reverts the tree to `snapshot`.
+    fn revert(&mut self, snapshot: &Snapshot) {
+        self.cwd = snapshot.cwd.clone();
+        self.entries = snapshot.entries.clone();
+        self.inodes = snapshot.inodes.clone();
+        self.next_inode = snapshot.next_inode;
+    }
+
+    /// Returns permission bits, user and group of the entry at the resolved `inner_path`
+    /// (of its inode for files).
+    fn attributes(&self, inner_path: &Path) -> Option<(u32, u32, u32)> {
+        match self.inode(inner_path) {
+            Some(inode) => Some((inode.mode(), inode.uid(), inode.gid())),
+            None => self
+                .entries
+                .get(inner_path)
+                .map(|entry| (entry.mode(), entry.uid(), entry.gid())),
+        }
+    }
+
+    /// Checks that the current user may go through all parents of the resolved `inner_path`
+    /// and has `access` (a combination of `READ`, `WRITE` and `EXECUTE`) to it.
+    fn check_access(&self, inner_path: &Path, access: u32) -> Result<()> {
+        if !self.is_enforcing {
+            return Ok(());
+        }
+        let parents = inner_path.ancestors().skip(1).map(|path| (path, EXECUTE));
+        for (path, access) in std::iter::once((inner_path, access)).chain(parents) {
+            let Some((mode, uid, gid)) = self.attributes(path) else {
+                continue; // the root or a missing entry
+            };
+            let class = if uid == self.uid {
+                mode >> 6
+            } else if gid == self.gid {
+                mode >> 3
+            } else {
+                mode
+            };
+            if class & access != access {
+                return Err(VfsError::PermissionDenied(path.to_path_buf()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for MapFS {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FsBackend for MapFS {
@@ -124,12 +859,14 @@ impl FsBackend for MapFS {
 
     /// Changes the current working directory.
     /// * `path` can be in relative or absolute form, but in both cases it must exist in VFS.
+    ///
     /// An error is returned if the specified `path` does not exist.
     fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        let target = self.to_inner(path);
+        let target = self.resolve(path, true)?;
         if !self.is_dir(&target)? {
-            return Err(anyhow!("{} not a directory", target.display()));
+            return Err(VfsError::NotADirectory(target));
         }
+        self.check_access(&target, EXECUTE)?;
         self.cwd = target;
         Ok(())
     }
@@ -137,30 +874,110 @@ impl FsBackend for MapFS {
     /// Checks if a `path` exists in the VFS.
     /// The `path` can be in relative or absolute form.
     fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
-        let inner = self.to_inner(path);
-        utils::is_virtual_root(&inner) || self.entries.contains_key(&inner)
+        self.resolve(path, true)
+            .is_ok_and(|inner| self.is_live(&inner))
     }
 
     /// Checks if `path` is a directory.
     fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
-        let path = path.as_ref();
-        let inner = self.to_inner(path);
-        if !self.exists(&inner) {
-            return Err(anyhow!("{} does not exist", path.display()));
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
         }
         Ok(utils::is_virtual_root(&inner) || self.entries[&inner].is_dir())
     }
 
     /// Checks if `path` is a regular file.
     fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
-        let path = path.as_ref();
-        let inner = self.to_inner(path);
-        if !self.exists(&inner) {
-            return Err(anyhow!("{} does not exist", path.display()));
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
         }
         Ok(!utils::is_virtual_root(&inner) && self.entries[&inner].is_file())
     }
 
+    /// Checks if `path` is a symbolic link.
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve(path, false)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(!utils::is_virtual_root(&inner) && self.entries[&inner].is_symlink())
+    }
+
+    /// Returns metadata of `path`. Times are tracked in memory: creation, last change of contents
+    /// and last read. The expiry is the earliest one of the entry and its parent directories.
+    /// The virtual root has no timestamps and is reported as `0o755` directory of the current
+    /// user.
+    /// * `path` must exist in VFS
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        self.check_access(&inner, 0)?;
+        let Some(entry) = self.entries.get(&inner) else {
+            // virtual root
+            let mut meta = Metadata::new(EntryType::Directory, 0);
+            meta.set_mode(Some(EntryType::Directory.default_mode()));
+            meta.set_owner(Some(self.uid), Some(self.gid));
+            return Ok(meta);
+        };
+        let mut meta = match self.inode(&inner) {
+            Some(inode) => {
+                let mut meta = Metadata::new(entry.entry_type(), inode.content().len() as u64);
+                meta.set_created(Some(inode.created()));
+                meta.set_modified(Some(inode.modified()));
+                meta.set_accessed(Some(inode.accessed()));
+                meta
+            }
+            None => {
+                let mut meta = Metadata::new(entry.entry_type(), 0);
+                meta.set_created(Some(entry.created()));
+                meta.set_modified(Some(entry.modified()));
+                meta.set_accessed(Some(entry.accessed()));
+                meta
+            }
+        };
+        let (mode, uid, gid) = self.attributes(&inner).unwrap(); // safe unwrap()
+        meta.set_mode(Some(mode));
+        meta.set_owner(Some(uid), Some(gid));
+        meta.set_expires_at(
+            inner
+                .ancestors()
+                .filter_map(|path| self.entries.get(path).and_then(Entry::expires_at))
+                .min(),
+        );
+        Ok(meta)
+    }
+
+    /// Sets the permission bits of a file (shared by its hard links) or directory.
+    /// Only the owner may change them (`VfsError::PermissionDenied` for other users),
+    /// the root cannot be changed.
+    fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        let inner = self.resolve(path, true)?;
+        if utils::is_virtual_root(&inner) {
+            return Err(VfsError::RootProtected(inner));
+        }
+        self.evict_expired(&inner);
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        self.check_access(&inner, 0)?;
+        let (_, uid, _) = self.attributes(&inner).unwrap(); // safe unwrap()
+        if self.is_enforcing && uid != self.uid {
+            return Err(VfsError::PermissionDenied(inner));
+        }
+        match self.inode_mut(&inner) {
+            Some(inode) => inode.set_mode(mode),
+            None => self.entries
+                .get_mut(&inner)
+                .unwrap()
+                .set_mode(mode), // safe unwrap()
+        }
+        Ok(())
+    }
+
     /// Returns an iterator over directory entries at a specific depth (shallow listing).
     ///
     /// This method lists only the **immediate children** of the given directory,
@@ -174,7 +991,7 @@ impl FsBackend for MapFS {
     /// * `Ok(impl Iterator<Item = &Path>)` - Iterator over entries of immediate children
     ///   (relative to VFS root). The yielded paths are *inside* the target directory
     ///   but do not include deeper nesting.
-    /// * `Err(anyhow::Error)` - If the specified path does not exist in VFS.
+    /// * `Err(VfsError)` - If the specified path does not exist in VFS.
     ///
     /// # Example:
     ///```no_run
@@ -205,24 +1022,27 @@ impl FsBackend for MapFS {
     /// - **Performance:** The filtering is done in‑memory; no additional filesystem I/O occurs
     ///   during iteration.
     fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
-        let inner_path = self.to_inner(path);
+        let inner_path = self.resolve(path, true)?;
         if !self.exists(&inner_path) {
-            return Err(anyhow!("{} does not exist", inner_path.display()));
+            return Err(VfsError::NotFound(inner_path));
         }
         let is_file = self.is_file(&inner_path)?;
+        self.check_access(&inner_path, if is_file { 0 } else { READ | EXECUTE })?;
         let component_count = if is_file {
             inner_path.components().count()
         } else {
             inner_path.components().count() + 1
         };
+        let now = SystemTime::now();
         Ok(self
             .entries
-            .iter()
-            .map(|(pb, _)| pb.as_path())
+            .keys()
+            .map(|pb| pb.as_path())
             .filter(move |&path| {
                 path.starts_with(&inner_path)
                     && (path != inner_path || is_file)
                     && path.components().count() == component_count
+                    && !self.is_expired(path, now)
             }))
     }
 
@@ -237,7 +1057,7 @@ impl FsBackend for MapFS {
     /// # Returns
     /// * `Ok(impl Iterator<Item = &Path>)` - Iterator over all entries *within* the tree
     ///   (relative to VFS root), excluding the root of the traversal.
-    /// * `Err(anyhow::Error)` - If:
+    /// * `Err(VfsError)` - If:
     ///   - The specified path does not exist in VFS.
     ///   - The path is not a directory (implicitly checked via `exists` and tree structure).
     ///
@@ -273,30 +1093,39 @@ impl FsBackend for MapFS {
     /// - Symbolic links are treated as regular entries (no follow/resolve).
     /// - Use `MapFS` methods (e.g., `is_file()`, `is_dir()`) for yielded items for type checks.
     fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
-        let inner_path = self.to_inner(path);
+        let inner_path = self.resolve(path, true)?;
         if !self.exists(&inner_path) {
-            return Err(anyhow!("{} does not exist", inner_path.display()));
+            return Err(VfsError::NotFound(inner_path));
         }
         let is_file = self.is_file(&inner_path)?;
+        // only the starting directory is checked, the whole subtree is listed from memory
+        self.check_access(&inner_path, if is_file { 0 } else { READ | EXECUTE })?;
+        let now = SystemTime::now();
         Ok(self
             .entries
-            .iter()
-            .map(|(pb, _)| pb.as_path())
-            .filter(move |&path| path.starts_with(&inner_path) && (path != inner_path || is_file)))
+            .keys()
+            .map(|pb| pb.as_path())
+            .filter(move |&path| {
+                path.starts_with(&inner_path)
+                    && (path != inner_path || is_file)
+                    && !self.is_expired(path, now)
+            }))
     }
 
     /// Creates directory and all it parents (if needed).
     /// * `path` - inner vfs path.
     fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         if path.as_ref().as_os_str().is_empty() {
-            return Err(anyhow!("invalid path: empty"));
+            return Err(VfsError::invalid_path("", "empty"));
         }
 
-        let inner_path = self.to_inner(path);
+        let inner_path = self.resolve(path, false)?;
+        self.evict_expired(&inner_path);
 
-        if self.exists(&inner_path) {
-            return Err(anyhow!("path already exists: {}", inner_path.display()));
+        if self.is_live(&inner_path) {
+            return Err(VfsError::AlreadyExists(inner_path));
         }
+        self.check_dir_parents(&inner_path)?;
 
         // Looking for the first existing parent
         let mut existed_parent = inner_path.clone();
@@ -309,6 +1138,8 @@ impl FsBackend for MapFS {
             existed_parent = parent_buf;
         }
 
+        self.check_access(&existed_parent, WRITE | EXECUTE)?;
+
         // Create from the closest existing parent to the target path
         let need_to_create: Vec<_> = inner_path
             .strip_prefix(&existed_parent)?
@@ -319,8 +1150,7 @@ impl FsBackend for MapFS {
         for component in need_to_create {
             built.push(component);
             if !self.exists(&built) {
-                self.entries
-                    .insert(built.clone(), Entry::new(EntryType::Directory));
+                self.insert_entry(built.clone(), Entry::new(EntryType::Directory));
             }
         }
 
@@ -329,24 +1159,24 @@ impl FsBackend for MapFS {
 
     /// Creates new file in VFS.
     /// * `file_path` must be inner VFS path. It must contain the name of the file,
-    /// optionally preceded by parent directory.
+    ///   optionally preceded by parent directory.
+    ///
     /// If the parent directory does not exist, it will be created.
     fn mkfile<P: AsRef<Path>>(&mut self, file_path: P, content: Option<&[u8]>) -> Result<()> {
-        let file_path = self.to_inner(file_path);
-        if self.exists(&file_path) {
-            return Err(anyhow!("{} already exist", file_path.display()));
+        let file_path = self.resolve(file_path, false)?;
+        self.evict_expired(&file_path);
+        if self.is_live(&file_path) {
+            return Err(VfsError::AlreadyExists(file_path));
         }
-        if let Some(parent) = file_path.parent() {
-            if !self.exists(parent) {
-                self.mkdir(parent)?;
-            }
+        self.check_parent(&file_path)?;
+        if let Some(parent) = file_path.parent()
+            && !self.exists(parent)
+        {
+            self.mkdir(parent)?;
         }
 
-        let mut entry = Entry::new(EntryType::File);
-        if let Some(content) = content {
-            entry.set_content(content);
-        }
-        self.entries.insert(file_path.clone(), entry);
+        let inode = self.add_inode(content.unwrap_or_default());
+        self.insert_entry(file_path.clone(), Entry::new_file(inode));
 
         Ok(())
     }
@@ -356,20 +1186,24 @@ impl FsBackend for MapFS {
     ///
     /// # Returns
     /// * `Ok(Vec<u8>)` - File content as a byte vector if successful.
-    /// * `Err(anyhow::Error)` - If any of the following occurs:
-    ///   - File does not exist in VFS (`file does not exist: ...`)
-    ///   - Path points to a directory (`... is a directory`)
+    /// * `Err(VfsError)` - If any of the following occurs:
+    ///   - File does not exist in VFS (`VfsError::NotFound`)
+    ///   - Path points to a directory (`VfsError::IsADirectory`)
+    ///   - The current user has no access to the file (`VfsError::PermissionDenied`)
     ///
     /// # Notes
-    /// - Does **not** follow symbolic links on the host filesystem (reads the link itself).
+    /// - Follows symbolic links (reads the target).
     /// - Returns an empty vector for empty files.
     fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
-        let path = path.as_ref();
-        if self.is_dir(path)? {
+        let inner = self.resolve(path, true)?;
+        if self.is_dir(&inner)? {
             // checks for existent too
-            return Err(anyhow!("{} is a directory", path.display()));
+            return Err(VfsError::IsADirectory(inner));
         }
-        Ok(self.entries[path].content().cloned().unwrap_or(Vec::new()))
+        self.check_access(&inner, READ)?;
+        let inode = self.inode(&inner).unwrap(); // safe unwrap(): a file always has an inode
+        inode.mark_accessed();
+        Ok(inode.content().clone())
     }
 
     /// Writes bytes to an existing file, replacing its entire contents.
@@ -378,20 +1212,23 @@ impl FsBackend for MapFS {
     ///
     /// # Returns
     /// * `Ok(())` - If the write operation succeeded.
-    /// * `Err(anyhow::Error)` - If any of the following occurs:
-    ///   - File does not exist in VFS (`file does not exist: ...`)
-    ///   - Path points to a directory (`... is a directory`)
+    /// * `Err(VfsError)` - If any of the following occurs:
+    ///   - File does not exist in VFS (`VfsError::NotFound`)
+    ///   - Path points to a directory (`VfsError::IsADirectory`)
+    ///   - The current user has no access to the file (`VfsError::PermissionDenied`)
     ///
     /// # Behavior
     /// - **Overwrites completely**: The entire existing content is replaced.
     /// - **No file creation**: File must exist (use `mkfile()` first).
     fn write<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
-        let path = path.as_ref();
-        if self.is_dir(path)? {
+        let inner = self.resolve(path, true)?;
+        self.evict_expired(&inner);
+        if self.is_dir(&inner)? {
             // checks for existent too
-            return Err(anyhow!("{} is a directory", path.display()));
+            return Err(VfsError::IsADirectory(inner));
         }
-        self.entries.get_mut(path).unwrap().set_content(content); // safe unwrap()
+        self.check_access(&inner, WRITE)?;
+        self.inode_mut(&inner).unwrap().set_content(content); // safe unwrap()
         Ok(())
     }
 
@@ -403,70 +1240,234 @@ impl FsBackend for MapFS {
     ///
     /// # Returns
     /// * `Ok(())` - If the append operation succeeded.
-    /// * `Err(anyhow::Error)` - If any of the following occurs:
-    ///   - File does not exist in VFS (`file does not exist: ...`)
-    ///   - Path points to a directory (`... is a directory`)
+    /// * `Err(VfsError)` - If any of the following occurs:
+    ///   - File does not exist in VFS (`VfsError::NotFound`)
+    ///   - Path points to a directory (`VfsError::IsADirectory`)
+    ///   - The current user has no access to the file (`VfsError::PermissionDenied`)
     ///
     /// # Behavior
     /// - **Appends only**: Existing content is preserved; new bytes are added at the end.
     /// - **File creation**: Does NOT create the file if it doesn't exist (returns error).
     fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
-        let path = path.as_ref();
-        if self.is_dir(path)? {
+        let inner = self.resolve(path, true)?;
+        self.evict_expired(&inner);
+        if self.is_dir(&inner)? {
+            // checks for existent too
+            return Err(VfsError::IsADirectory(inner));
+        }
+        self.check_access(&inner, WRITE)?;
+        self.inode_mut(&inner).unwrap().append_content(content); // safe unwrap()
+        Ok(())
+    }
+
+    /// Opens a file with the given `options` and returns a handle working directly on the
+    /// contents of its inode (no copy is made).
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<impl VfsFile + '_> {
+        let inner = prepare_open(self, path, options)?;
+        if options.is_readable() {
+            self.check_access(&inner, READ)?;
+        }
+        if options.is_writable() {
+            self.check_access(&inner, WRITE)?;
+        }
+        let inode = self.inode_mut(&inner).unwrap(); // safe unwrap()
+        Ok(MapFile {
+            inode,
+            pos: 0,
+            options: options.clone(),
+        })
+    }
+
+    /// Moves a file or a whole directory subtree from `from` to `to` by rekeying the entries.
+    /// Entries keep their contents (and hard links), times and expiry. Missing parents of `to` are created.
+    ///
+    /// An error is returned if `from` does not exist or is the root, if `to` already exists,
+    /// or if `to` is inside `from`.
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        let (from, to) = utils::check_transfer(self, from, to)?;
+        self.evict_expired(&to);
+        self.check_parent(&from)?;
+        self.check_parent(&to)?;
+        if let Some(parent) = to.parent()
+            && !self.exists(parent)
+        {
+            self.mkdir(parent)?;
+        }
+
+        let moved: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|&pb| pb.starts_with(&from))
+            .cloned()
+            .collect();
+        let entries = &mut self.entries;
+        for path in moved {
+            let entry = entries.remove(&path).unwrap(); // safe unwrap()
+            entries.insert(utils::rebase(&path, &from, &to), entry);
+        }
+        self.cwd = utils::rebase(&self.cwd, &from, &to);
+
+        Ok(())
+    }
+
+    /// Creates a symbolic link entry at `link` pointing to `target` (see `FsBackend::symlink()`).
+    /// Missing parents of `link` are created.
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, target: P, link: Q) -> Result<()> {
+        if link.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let target = target.as_ref();
+        let link = self.resolve(link, false)?;
+        self.evict_expired(&link);
+        if self.is_live(&link) {
+            return Err(VfsError::AlreadyExists(link));
+        }
+        let parent = link.parent().unwrap(); // safe unwrap(): the root always exists
+        utils::link_target(parent, target)?;
+        self.check_parent(&link)?;
+        if !self.exists(parent) {
+            self.mkdir(parent)?;
+        }
+        self.insert_entry(link, Entry::new_symlink(target));
+        Ok(())
+    }
+
+    /// Returns the target of the symbolic link at `path`.
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let inner = self.resolve(path, false)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        self.entries
+            .get(&inner)
+            .and_then(Entry::link_target)
+            .map(Path::to_path_buf)
+            .ok_or_else(|| VfsError::invalid_path(inner, "not a symbolic link"))
+    }
+
+    /// Adds the entry `new` pointing to the inode of the file `existing`
+    /// (see `FsBackend::hard_link()`). Missing parents of `new` are created.
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, existing: P, new: Q) -> Result<()> {
+        if new.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let existing = self.resolve(existing, true)?;
+        self.evict_expired(&existing);
+        if self.is_dir(&existing)? {
             // checks for existent too
-            return Err(anyhow!("{} is a directory", path.display()));
+            return Err(VfsError::IsADirectory(existing));
+        }
+        let new = self.resolve(new, false)?;
+        self.evict_expired(&new);
+        if self.is_live(&new) {
+            return Err(VfsError::AlreadyExists(new));
         }
-        self.entries.get_mut(path).unwrap().append_content(content); // safe unwrap()
+        self.check_access(&existing, 0)?;
+        self.check_parent(&new)?;
+        if let Some(parent) = new.parent()
+            && !self.exists(parent)
+        {
+            self.mkdir(parent)?;
+        }
+
+        let id = self.entries[&existing].inode().unwrap(); // safe unwrap(): a file has an inode
+        self.inodes.get_mut(&id).unwrap().link(); // safe unwrap()
+        self.insert_entry(new, Entry::new_file(id));
         Ok(())
     }
 
     /// Removes a file or directory at the specified path.
     ///
     /// - `path`: can be absolute (starting with '/') or relative to the current working
-    /// directory (cwd). If the path is a directory, all its contents are removed recursively.
+    ///   directory (cwd). If the path is a directory, all its contents are removed recursively.
+    ///   A symbolic link is removed itself, its target is kept. The contents of a file are
+    ///   kept while other hard links to it remain.
     ///
     /// Returns:
     /// - `Ok(())` on successful removal.
     /// - `Err(_)` if the path does not exist in the VFS;
     fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         if path.as_ref().as_os_str().is_empty() {
-            return Err(anyhow!("invalid path: empty"));
+            return Err(VfsError::invalid_path("", "empty"));
         }
         if utils::is_virtual_root(&path) {
-            return Err(anyhow!("invalid path: the root cannot be removed"));
+            return Err(VfsError::RootProtected(PathBuf::from("/")));
         }
 
-        let inner_path = self.to_inner(path); // Convert to VFS-internal normalized path
+        let inner_path = self.resolve(path, false)?; // a link is removed, not its target
+        self.evict_expired(&inner_path);
 
         // Check if the path exists in the virtual filesystem
-        if !self.exists(&inner_path) {
-            return Err(anyhow!("{} does not exist", inner_path.display()));
+        if !self.is_live(&inner_path) {
+            return Err(VfsError::NotFound(inner_path));
         }
+        self.check_parent(&inner_path)?;
 
-        // Update internal state: collect all entries that start with `inner_path`
-        let removed: Vec<PathBuf> = self
-            .entries
-            .iter()
-            .map(|(pb, _)| pb)
-            .filter(|&pb| pb.starts_with(&inner_path)) // Match prefix (includes subpaths)
-            .cloned()
-            .collect();
-
-        // Remove all matched entries from the set
-        for p in &removed {
-            self.entries.remove(p);
-        }
+        // Update internal state: remove all entries that start with `inner_path`
+        self.remove_subtree(&inner_path);
 
         Ok(())
     }
 
     /// Removes all artifacts (dirs and files) in vfs.
     fn cleanup(&mut self) -> bool {
-        self.entries.clear();
+        self.entries = OrdMap::new();
+        self.inodes = OrdMap::new();
         true
     }
 }
 
+/// A file handle of `MapFS` working directly on the contents of an `Inode`.
+struct MapFile<'a> {
+    inode: &'a mut Inode,
+    pos: u64,
+    options: OpenOptions,
+}
+
+impl Read for MapFile<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        check_access(self.options.is_readable(), "reading")?;
+        let mut cursor = Cursor::new(self.inode.content().as_slice());
+        cursor.set_position(self.pos);
+        let count = cursor.read(buf)?;
+        self.pos = cursor.position();
+        self.inode.mark_accessed();
+        Ok(count)
+    }
+}
+
+impl Write for MapFile<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        check_access(self.options.is_writable(), "writing")?;
+        let mut cursor = Cursor::new(self.inode.content_mut());
+        if self.options.is_append() {
+            cursor.seek(SeekFrom::End(0))?;
+        } else {
+            cursor.set_position(self.pos);
+        }
+        let count = cursor.write(buf)?;
+        self.pos = cursor.position();
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MapFile<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let mut cursor = Cursor::new(self.inode.content().as_slice());
+        cursor.set_position(self.pos);
+        self.pos = cursor.seek(pos)?;
+        Ok(self.pos)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1433,7 +2434,7 @@ mod tests {
 
             assert!(vfs.exists("/empty.txt"));
             assert!(vfs.is_file("/empty.txt")?);
-            assert_eq!(vfs.read("/empty.txt")?, &[]);
+            assert_eq!(vfs.read("/empty.txt")?, b"");
 
             Ok(())
         }
@@ -2012,4 +3013,1357 @@ mod tests {
             assert!(vfs2.cleanup()); // Empty VFS
         }
     }
+
+    mod ttl {
+        use super::*;
+
+        const HOUR: Duration = Duration::from_secs(3600);
+
+        /// Helper to create a pre‑populated MapFS instance for testing
+        fn setup_test_vfs() -> MapFS {
+            let mut vfs = MapFS::new();
+            vfs.mkdir("/cache/session").unwrap();
+            vfs.mkfile("/cache/session/token", Some(b"secret")).unwrap();
+            vfs.mkfile("/readme.md", Some(b"Docs")).unwrap();
+            vfs
+        }
+
+        #[test]
+        fn test_mkfile_with_ttl_not_expired() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+            vfs.mkfile_with_ttl("/cache/item", Some(b"value"), HOUR)?;
+
+            assert!(vfs.exists("/cache/item"));
+            assert_eq!(vfs.read("/cache/item")?, b"value");
+            let expires_at = vfs.expires_at("/cache/item")?.unwrap();
+            assert!(expires_at > SystemTime::now());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_expired_file_is_hidden() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+            vfs.mkfile_with_ttl("/cache/item", Some(b"value"), Duration::ZERO)?;
+
+            assert!(!vfs.exists("/cache/item"));
+            assert!(vfs.read("/cache/item").is_err());
+            assert!(vfs.is_file("/cache/item").is_err());
+            assert!(!vfs.ls("/cache")?.any(|p| p == Path::new("/cache/item")));
+            assert!(!vfs.tree("/")?.any(|p| p == Path::new("/cache/item")));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_expired_directory_hides_contents() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+            vfs.set_ttl("/cache", Some(Duration::ZERO))?;
+
+            assert!(!vfs.exists("/cache"));
+            assert!(!vfs.exists("/cache/session/token"));
+            let entries: Vec<_> = vfs.tree("/")?.collect();
+            assert_eq!(entries, vec![Path::new("/readme.md")]);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_expired_path_can_be_recreated() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+            vfs.set_ttl("/cache", Some(Duration::ZERO))?;
+
+            vfs.mkfile("/cache/new", Some(b"fresh"))?;
+
+            assert!(vfs.exists("/cache/new"));
+            assert!(!vfs.exists("/cache/session")); // old contents are gone
+            assert_eq!(vfs.expires_at("/cache")?, None);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_write_to_expired_file() {
+            let mut vfs = setup_test_vfs();
+            vfs.set_ttl("/readme.md", Some(Duration::ZERO)).unwrap();
+
+            let result = vfs.write("/readme.md", b"new");
+            assert!(result.is_err());
+            assert!(result.unwrap_err().to_string().contains("does not exist"));
+            assert!(!vfs.entries.contains_key(Path::new("/readme.md"))); // evicted lazily
+        }
+
+        #[test]
+        fn test_set_ttl_none_makes_permanent() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+            vfs.set_ttl("/readme.md", Some(HOUR))?;
+            vfs.set_ttl("/readme.md", None)?;
+
+            assert_eq!(vfs.expires_at("/readme.md")?, None);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_expiry_in_metadata() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+            assert_eq!(vfs.metadata("/cache/session/token")?.expires_at(), None);
+
+            vfs.set_ttl("/cache", Some(HOUR))?;
+            vfs.set_ttl("/cache/session/token", Some(2 * HOUR))?;
+            let cache_expiry = vfs.expires_at("/cache")?;
+            assert_eq!(vfs.metadata("/cache")?.expires_at(), cache_expiry);
+            assert_eq!(vfs.metadata("/cache/session")?.expires_at(), cache_expiry);
+            assert_eq!(
+                vfs.metadata("/cache/session/token")?.expires_at(),
+                cache_expiry
+            );
+
+            vfs.set_ttl("/cache/session/token", Some(Duration::from_secs(60)))?;
+            assert_eq!(
+                vfs.metadata("/cache/session/token")?.expires_at(),
+                vfs.expires_at("/cache/session/token")?
+            );
+            assert_eq!(vfs.metadata("/")?.expires_at(), None);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_ttl_overflow_never_expires() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+            vfs.mkfile_with_ttl("/t", None, Duration::MAX)?;
+            vfs.set_ttl("/readme.md", Some(Duration::MAX))?;
+
+            assert!(vfs.exists("/t"));
+            assert_eq!(vfs.expires_at("/t")?, None);
+            assert_eq!(vfs.expires_at("/readme.md")?, None);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_mkfile_with_ttl_errors() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+
+            assert!(vfs.mkfile_with_ttl("/readme.md", None, HOUR).is_err());
+            assert_eq!(vfs.expires_at("/readme.md")?, None);
+            assert!(vfs.mkfile_with_ttl("/readme.md/x", None, HOUR).is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_set_ttl_errors() {
+            let mut vfs = setup_test_vfs();
+
+            assert!(vfs.set_ttl("/", Some(HOUR)).is_err());
+            assert!(vfs.set_ttl("/nonexistent", Some(HOUR)).is_err());
+        }
+
+        #[test]
+        fn test_set_ttl_relative_path() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+            vfs.cd("/cache/session")?;
+            vfs.set_ttl("token", Some(Duration::ZERO))?;
+
+            assert!(!vfs.exists("/cache/session/token"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_purge_expired() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+            vfs.mkfile_with_ttl("/tmp/a", None, Duration::ZERO)?;
+            vfs.mkfile_with_ttl("/tmp/b", None, HOUR)?;
+            vfs.set_ttl("/cache", Some(Duration::ZERO))?;
+
+            let removed = vfs.purge_expired();
+
+            assert_eq!(removed, 4); // /tmp/a, /cache, /cache/session, /cache/session/token
+            assert!(vfs.entries.contains_key(Path::new("/tmp/b")));
+            assert!(!vfs.entries.contains_key(Path::new("/cache/session/token")));
+            assert_eq!(vfs.purge_expired(), 0);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_ttl_survives_write() -> Result<()> {
+            let mut vfs = setup_test_vfs();
+            vfs.mkfile_with_ttl("/item", Some(b"old"), HOUR)?;
+            let expires_at = vfs.expires_at("/item")?;
+
+            vfs.write("/item", b"new")?;
+            vfs.append("/item", b"er")?;
+
+            assert_eq!(vfs.expires_at("/item")?, expires_at);
+            assert_eq!(vfs.read("/item")?, b"newer");
+
+            Ok(())
+        }
+    }
+    mod open {
+        use super::*;
+
+        #[test]
+        fn test_open_read_write_seek() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/file.txt", Some(b"Hello, world"))?;
+
+            {
+                let mut file = fs.open("/file.txt", OpenOptions::new().read(true).write(true))?;
+                let mut hello = [0u8; 5];
+                file.read_exact(&mut hello)?;
+                assert_eq!(&hello, b"Hello");
+
+                file.seek(SeekFrom::End(-5))?;
+                file.write_all(b"there!")?;
+                file.seek(SeekFrom::Start(0))?;
+                let mut content = String::new();
+                file.read_to_string(&mut content)?;
+                assert_eq!(content, "Hello, there!");
+            }
+            assert_eq!(fs.read("/file.txt")?, b"Hello, there!");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_open_create_truncate_append() -> Result<()> {
+            let mut fs = MapFS::new();
+
+            let mut file = fs.open("/logs/a.log", OpenOptions::new().append(true).create(true))?;
+            file.write_all(b"one ")?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(b"two")?;
+            drop(file);
+            assert_eq!(fs.read("/logs/a.log")?, b"one two");
+
+            let mut file = fs.open("/logs/a.log", OpenOptions::new().write(true).truncate(true))?;
+            file.write_all(b"new")?;
+            drop(file);
+            assert_eq!(fs.read("/logs/a.log")?, b"new");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_open_errors() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/dir/file.txt", Some(b"content"))?;
+
+            assert!(matches!(
+                fs.open("/missing", OpenOptions::new().read(true)),
+                Err(VfsError::NotFound(_))
+            ));
+            assert!(matches!(
+                fs.open("/dir", OpenOptions::new().read(true)),
+                Err(VfsError::IsADirectory(_))
+            ));
+            assert!(matches!(
+                fs.open(
+                    "/dir/file.txt",
+                    OpenOptions::new().write(true).create_new(true)
+                ),
+                Err(VfsError::AlreadyExists(_))
+            ));
+            assert!(matches!(
+                fs.open("/dir/file.txt", &OpenOptions::new()),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            assert!(matches!(
+                fs.open("/new.txt", OpenOptions::new().read(true).create(true)),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            assert!(!fs.exists("/new.txt"));
+
+            let mut file = fs.open("/dir/file.txt", OpenOptions::new().read(true))?;
+            assert!(file.write_all(b"denied").is_err());
+            drop(file);
+            let mut file = fs.open("/dir/file.txt", OpenOptions::new().write(true))?;
+            assert!(file.read(&mut [0u8; 4]).is_err());
+            drop(file);
+            assert_eq!(fs.read("/dir/file.txt")?, b"content");
+
+            Ok(())
+        }
+    }
+
+    mod rename {
+        use super::*;
+
+        #[test]
+        fn test_rename_file() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/a.txt", Some(b"A"))?;
+
+            fs.rename("/a.txt", "/docs/b.txt")?;
+
+            assert!(!fs.exists("/a.txt"));
+            assert!(fs.is_dir("/docs")?);
+            assert_eq!(fs.read("/docs/b.txt")?, b"A");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rename_dir_subtree() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/src/lib.rs", Some(b"lib"))?;
+            fs.mkfile("/src/vfs/mod.rs", Some(b"mod"))?;
+            fs.mkfile("/srcfile", None)?;
+            fs.cd("/src/vfs")?;
+
+            fs.rename("/src", "/dst")?;
+
+            let tree: Vec<_> = fs.tree("/")?.collect();
+            assert_eq!(
+                tree,
+                vec![
+                    Path::new("/dst"),
+                    Path::new("/dst/lib.rs"),
+                    Path::new("/dst/vfs"),
+                    Path::new("/dst/vfs/mod.rs"),
+                    Path::new("/srcfile"),
+                ]
+            );
+            assert_eq!(fs.read("/dst/vfs/mod.rs")?, b"mod");
+            assert_eq!(fs.cwd(), Path::new("/dst/vfs"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rename_relative_paths() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/home/a.txt", Some(b"A"))?;
+            fs.cd("/home")?;
+
+            fs.rename("a.txt", "../b.txt")?;
+
+            assert_eq!(fs.read("/b.txt")?, b"A");
+            assert!(!fs.exists("/home/a.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rename_errors() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/dir/a.txt", None)?;
+            fs.mkfile("/b.txt", None)?;
+
+            assert!(matches!(
+                fs.rename("/missing", "/c"),
+                Err(VfsError::NotFound(_))
+            ));
+            assert!(matches!(
+                fs.rename("/dir/a.txt", "/b.txt"),
+                Err(VfsError::AlreadyExists(path)) if path == Path::new("/b.txt")
+            ));
+            assert!(matches!(
+                fs.rename("/dir", "/dir/sub"),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            assert!(matches!(
+                fs.rename("/dir", "/b.txt/sub"),
+                Err(VfsError::NotADirectory(path)) if path == Path::new("/b.txt")
+            ));
+            assert!(matches!(
+                fs.rename("/", "/x"),
+                Err(VfsError::RootProtected(_))
+            ));
+            assert!(matches!(
+                fs.rename("", "/x"),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            assert!(fs.exists("/dir/a.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rename_keeps_ttl() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile_with_ttl("/a.txt", None, Duration::from_secs(60))?;
+            let expires_at = fs.expires_at("/a.txt")?;
+
+            fs.rename("/a.txt", "/b.txt")?;
+
+            assert_eq!(fs.expires_at("/b.txt")?, expires_at);
+
+            Ok(())
+        }
+    }
+
+    mod copy {
+        use super::*;
+
+        #[test]
+        fn test_copy_file() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/a.txt", Some(b"A"))?;
+
+            fs.copy("/a.txt", "/backup/a.txt")?;
+            fs.write("/a.txt", b"changed")?;
+
+            assert_eq!(fs.read("/backup/a.txt")?, b"A");
+            assert_eq!(fs.read("/a.txt")?, b"changed");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_copy_dir_tree() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/src/lib.rs", Some(b"lib"))?;
+            fs.mkfile("/src/vfs/mod.rs", Some(b"mod"))?;
+            fs.mkdir("/src/empty")?;
+
+            fs.copy("/src", "/dst")?;
+
+            let copied: Vec<_> = fs.tree("/dst")?.collect();
+            assert_eq!(
+                copied,
+                vec![
+                    Path::new("/dst/empty"),
+                    Path::new("/dst/lib.rs"),
+                    Path::new("/dst/vfs"),
+                    Path::new("/dst/vfs/mod.rs"),
+                ]
+            );
+            assert_eq!(fs.read("/dst/vfs/mod.rs")?, b"mod");
+            assert!(fs.exists("/src/vfs/mod.rs"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_copy_errors() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/dir/a.txt", None)?;
+
+            assert!(matches!(
+                fs.copy("/missing", "/x"),
+                Err(VfsError::NotFound(_))
+            ));
+            assert!(matches!(
+                fs.copy("/dir", "/dir/a.txt"),
+                Err(VfsError::AlreadyExists(_))
+            ));
+            assert!(matches!(
+                fs.copy("/dir", "/dir/copy"),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            assert_eq!(fs.tree("/")?.count(), 2);
+
+            Ok(())
+        }
+    }
+
+    mod metadata {
+        use super::*;
+
+        #[test]
+        fn test_metadata_len_and_type() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/dir/file.txt", Some(b"Hello"))?;
+            fs.mkfile("/empty.txt", None)?;
+
+            let meta = fs.metadata("/dir/file.txt")?;
+            assert!(meta.is_file());
+            assert_eq!(meta.len(), 5);
+            assert!(!meta.is_readonly());
+            assert!(fs.metadata("/empty.txt")?.is_empty());
+            assert!(fs.metadata("/dir")?.is_dir());
+            assert!(fs.metadata("/")?.is_dir());
+            assert!(matches!(
+                fs.metadata("/missing"),
+                Err(VfsError::NotFound(_))
+            ));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_metadata_times() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/file.txt", Some(b"old"))?;
+            let created = fs.metadata("/file.txt")?;
+            assert!(created.created().is_some());
+            assert!(created.modified() >= created.created());
+
+            std::thread::sleep(Duration::from_millis(10));
+            fs.read("/file.txt")?;
+            let read = fs.metadata("/file.txt")?;
+            assert_eq!(read.modified(), created.modified());
+            assert!(read.accessed() > created.accessed());
+
+            std::thread::sleep(Duration::from_millis(10));
+            fs.append("/file.txt", b"er")?;
+            let appended = fs.metadata("/file.txt")?;
+            assert_eq!(appended.created(), created.created());
+            assert!(appended.modified() > read.modified());
+            assert_eq!(appended.len(), 5);
+
+            Ok(())
+        }
+    }
+
+    mod symlinks {
+        use super::*;
+
+        #[test]
+        fn test_links_are_followed() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/data/v1/config.toml", Some(b"v1"))?;
+            fs.symlink("v1", "/data/current")?;
+            fs.symlink("/data/current/config.toml", "/config.toml")?;
+
+            assert!(fs.is_symlink("/data/current")?);
+            assert!(fs.is_dir("/data/current")?);
+            assert!(!fs.is_symlink("/data/v1")?);
+            assert_eq!(fs.read_link("/data/current")?, Path::new("v1"));
+            assert_eq!(fs.read("/config.toml")?, b"v1");
+            assert_eq!(
+                fs.canonicalize("/config.toml")?,
+                Path::new("/data/v1/config.toml")
+            );
+
+            fs.write("/data/current/config.toml", b"v2")?;
+            assert_eq!(fs.read("/data/v1/config.toml")?, b"v2");
+            assert_eq!(
+                fs.ls("/data/current")?.collect::<Vec<_>>(),
+                vec![Path::new("/data/v1/config.toml")]
+            );
+
+            fs.cd("/data/current")?;
+            assert_eq!(fs.cwd(), Path::new("/data/v1"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_dangling_link() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.symlink("missing.txt", "/link")?;
+
+            assert!(!fs.exists("/link"));
+            assert!(fs.is_symlink("/link")?);
+            assert!(matches!(fs.read("/link"), Err(VfsError::NotFound(_))));
+            assert!(matches!(
+                fs.canonicalize("/link"),
+                Err(VfsError::NotFound(_))
+            ));
+            assert!(matches!(
+                fs.symlink("other", "/link"),
+                Err(VfsError::AlreadyExists(_))
+            ));
+
+            fs.mkfile("/missing.txt", Some(b"found"))?;
+            assert_eq!(fs.read("/link")?, b"found");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_loops_and_escapes_are_refused() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.symlink("b", "/a")?;
+            fs.symlink("a", "/b")?;
+            fs.symlink("self", "/self")?;
+
+            assert!(!fs.exists("/a"));
+            assert!(matches!(fs.read("/a"), Err(VfsError::InvalidPath { .. })));
+            assert!(matches!(fs.cd("/self"), Err(VfsError::InvalidPath { .. })));
+
+            assert!(fs.symlink("../../etc/passwd", "/dir/link").is_err());
+            assert!(fs.symlink("", "/empty").is_err());
+            assert!(!fs.exists("/dir"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rm_rename_copy_work_on_links() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/dir/file.txt", Some(b"content"))?;
+            fs.symlink("dir", "/link")?;
+
+            fs.copy("/link", "/copy")?;
+            assert!(fs.is_symlink("/copy")?);
+            assert_eq!(fs.read("/copy/file.txt")?, b"content");
+
+            fs.rename("/copy", "/moved")?;
+            assert_eq!(fs.read_link("/moved")?, Path::new("dir"));
+
+            fs.rm("/link")?;
+            fs.rm("/moved")?;
+            assert!(fs.exists("/dir/file.txt"));
+            assert_eq!(fs.tree("/")?.count(), 2);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_read_link_of_regular_entry() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/file.txt", None)?;
+
+            assert!(matches!(
+                fs.read_link("/file.txt"),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            assert!(matches!(
+                fs.read_link("/missing"),
+                Err(VfsError::NotFound(_))
+            ));
+
+            Ok(())
+        }
+    }
+
+    mod hard_links {
+        use super::*;
+
+        #[test]
+        fn test_links_share_content() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/data/report.csv", Some(b"a,b"))?;
+            fs.hard_link("/data/report.csv", "/backup/report.csv")?;
+
+            assert!(fs.is_file("/backup/report.csv")?);
+            assert!(!fs.is_symlink("/backup/report.csv")?);
+            assert_eq!(
+                fs.inode_id("/data/report.csv"),
+                fs.inode_id("/backup/report.csv")
+            );
+
+            fs.append("/backup/report.csv", b"\n1,2")?;
+            assert_eq!(fs.read("/data/report.csv")?, b"a,b\n1,2");
+
+            {
+                let mut file = fs.open("/data/report.csv", OpenOptions::new().write(true))?;
+                file.write_all(b"x")?;
+            }
+            assert_eq!(fs.read("/backup/report.csv")?, b"x,b\n1,2");
+            assert_eq!(
+                fs.metadata("/backup/report.csv")?.modified(),
+                fs.metadata("/data/report.csv")?.modified()
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rm_keeps_content_of_other_links() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/a.txt", Some(b"shared"))?;
+            fs.hard_link("/a.txt", "/b.txt")?;
+            fs.hard_link("/b.txt", "/dir/c.txt")?;
+            assert_eq!(fs.inodes.len(), 1);
+            assert_eq!(fs.inodes.values().next().unwrap().links(), 3);
+
+            fs.rm("/a.txt")?;
+            fs.rm("/dir")?;
+            assert_eq!(fs.read("/b.txt")?, b"shared");
+            assert_eq!(fs.inodes.values().next().unwrap().links(), 1);
+
+            fs.rm("/b.txt")?;
+            assert!(fs.inodes.is_empty());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rename_keeps_link() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/a.txt", Some(b"1"))?;
+            fs.hard_link("/a.txt", "/b.txt")?;
+            fs.rename("/b.txt", "/moved/b.txt")?;
+
+            fs.write("/moved/b.txt", b"2")?;
+            assert_eq!(fs.read("/a.txt")?, b"2");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_expired_and_cleaned_links_free_inodes() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/a.txt", Some(b"1"))?;
+            fs.hard_link("/a.txt", "/tmp/b.txt")?;
+            fs.set_ttl("/tmp", Some(Duration::ZERO))?;
+
+            assert_eq!(fs.purge_expired(), 2);
+            assert_eq!(fs.inodes.values().next().unwrap().links(), 1);
+            assert_eq!(fs.read("/a.txt")?, b"1");
+
+            fs.cleanup();
+            assert!(fs.inodes.is_empty());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_link_follows_symlink() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/real.txt", Some(b"1"))?;
+            fs.symlink("real.txt", "/alias")?;
+            fs.hard_link("/alias", "/copy.txt")?;
+
+            assert!(!fs.is_symlink("/copy.txt")?);
+            assert_eq!(fs.inode_id("/copy.txt"), fs.inode_id("/real.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_link_errors() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/a.txt", None)?;
+            fs.mkdir("/dir")?;
+
+            assert!(matches!(
+                fs.hard_link("/missing", "/b.txt"),
+                Err(VfsError::NotFound(_))
+            ));
+            assert!(matches!(
+                fs.hard_link("/dir", "/b"),
+                Err(VfsError::IsADirectory(_))
+            ));
+            assert!(matches!(
+                fs.hard_link("/a.txt", "/dir"),
+                Err(VfsError::AlreadyExists(_))
+            ));
+            assert!(matches!(
+                fs.hard_link("/a.txt", ""),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            assert_eq!(fs.inodes.values().next().unwrap().links(), 1);
+
+            Ok(())
+        }
+    }
+
+    mod from_host_dir {
+        use super::*;
+        use tempdir::TempDir;
+
+        fn create_host_fixtures() -> Result<TempDir> {
+            let temp_dir = TempDir::new("fixtures")?;
+            let root = temp_dir.path();
+            std::fs::create_dir_all(root.join("data/nested"))?;
+            std::fs::create_dir_all(root.join("empty"))?;
+            std::fs::create_dir_all(root.join(".git/objects"))?;
+            std::fs::write(root.join("data/a.json"), b"{\"a\": 1}")?;
+            std::fs::write(root.join("data/nested/b.json"), b"[]")?;
+            std::fs::write(root.join("data/notes.txt"), b"notes")?;
+            std::fs::write(root.join(".git/objects/blob"), b"git")?;
+            Ok(temp_dir)
+        }
+
+        #[test]
+        fn test_import_everything() -> Result<()> {
+            let temp_dir = create_host_fixtures()?;
+            let fs = MapFS::from_host_dir(temp_dir.path(), &ImportOptions::new())?;
+
+            assert_eq!(fs.root(), std::fs::canonicalize(temp_dir.path())?);
+            assert_eq!(fs.read("/data/a.json")?, b"{\"a\": 1}");
+            assert_eq!(fs.read("/data/nested/b.json")?, b"[]");
+            assert!(fs.is_dir("/empty")?);
+            assert!(fs.exists("/.git/objects/blob"));
+            assert_eq!(fs.tree("/")?.count(), 9);
+
+            // the host is not touched afterwards
+            drop(fs);
+            assert!(temp_dir.path().join("data/a.json").exists());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_include_and_exclude() -> Result<()> {
+            let temp_dir = create_host_fixtures()?;
+            let fs = MapFS::from_host_dir(
+                temp_dir.path(),
+                ImportOptions::new()
+                    .include("**/*.json")
+                    .include("empty")
+                    .exclude("data/nested"),
+            )?;
+
+            let paths: Vec<_> = fs.tree("/")?.collect();
+            assert_eq!(
+                paths,
+                [
+                    Path::new("/data"),
+                    Path::new("/data/a.json"),
+                    Path::new("/empty")
+                ]
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_max_total_size() -> Result<()> {
+            let temp_dir = create_host_fixtures()?;
+            let options = ImportOptions::new().exclude(".git").clone();
+            let total = 8 + 2 + 5; // a.json, b.json, notes.txt
+
+            let fs = MapFS::from_host_dir(temp_dir.path(), options.clone().max_total_size(total))?;
+            assert_eq!(fs.read("/data/notes.txt")?, b"notes");
+            assert!(matches!(
+                MapFS::from_host_dir(temp_dir.path(), options.clone().max_total_size(total - 1)),
+                Err(VfsError::Other(_))
+            ));
+
+            Ok(())
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn test_links_are_imported_as_links() -> Result<()> {
+            let temp_dir = create_host_fixtures()?;
+            let root = std::fs::canonicalize(temp_dir.path())?;
+            std::os::unix::fs::symlink("data/a.json", root.join("relative"))?;
+            std::os::unix::fs::symlink(root.join("data"), root.join("absolute"))?;
+
+            let fs = MapFS::from_host_dir(&root, &ImportOptions::new())?;
+            assert_eq!(fs.read_link("/relative")?, Path::new("data/a.json"));
+            assert_eq!(fs.read_link("/absolute")?, Path::new("/data"));
+            assert_eq!(fs.read("/absolute/notes.txt")?, b"notes");
+
+            std::os::unix::fs::symlink("/etc/passwd", root.join("outside"))?;
+            assert!(matches!(
+                MapFS::from_host_dir(&root, &ImportOptions::new()),
+                Err(VfsError::InvalidPath { .. })
+            ));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_invalid_arguments() -> Result<()> {
+            let temp_dir = create_host_fixtures()?;
+            assert!(matches!(
+                MapFS::from_host_dir(temp_dir.path().join("missing"), &ImportOptions::new()),
+                Err(VfsError::NotFound(_))
+            ));
+            assert!(matches!(
+                MapFS::from_host_dir(temp_dir.path().join("data/a.json"), &ImportOptions::new()),
+                Err(VfsError::NotADirectory(_))
+            ));
+            assert!(matches!(
+                MapFS::from_host_dir(temp_dir.path(), ImportOptions::new().include("[a-")),
+                Err(VfsError::InvalidPath { .. })
+            ));
+
+            Ok(())
+        }
+    }
+
+    mod snapshots {
+        use super::*;
+
+        #[test]
+        fn test_restore_reverts_changes() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/docs/readme.md", Some(b"# Docs"))?;
+            fs.mkfile("/docs/todo.txt", Some(b"nothing"))?;
+            let id = fs.snapshot();
+
+            fs.write("/docs/readme.md", b"# Changed")?;
+            fs.rm("/docs/todo.txt")?;
+            fs.mkfile("/new.txt", None)?;
+            fs.rename("/docs", "/moved")?;
+            fs.cd("/moved")?;
+
+            fs.restore(id)?;
+            assert_eq!(fs.read("/docs/readme.md")?, b"# Docs");
+            assert_eq!(fs.read("/docs/todo.txt")?, b"nothing");
+            assert!(!fs.exists("/new.txt"));
+            assert!(!fs.exists("/moved"));
+            assert_eq!(fs.cwd(), Path::new("/"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_restore_repeatedly() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/counter", Some(b"0"))?;
+            let id = fs.snapshot();
+
+            for i in 1..=3 {
+                fs.append("/counter", i.to_string().as_bytes())?;
+                fs.mkfile(format!("/run{i}"), None)?;
+                assert_eq!(fs.read("/counter")?, format!("0{i}").as_bytes());
+                fs.restore(id)?;
+                assert_eq!(fs.read("/counter")?, b"0");
+                assert_eq!(fs.tree("/")?.count(), 1);
+            }
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_snapshot_shares_contents() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/big.bin", Some(&[7; 4096]))?;
+            fs.mkfile("/small.txt", Some(b"small"))?;
+            let id = fs.snapshot();
+            assert!(fs.entries.ptr_eq(&fs.snapshots[&id].entries));
+
+            fs.write("/small.txt", b"changed")?;
+            assert!(fs.entries.ptr_eq(&fs.snapshots[&id].entries)); // only an inode changed
+            assert!(!fs.inodes.ptr_eq(&fs.snapshots[&id].inodes));
+            let big = fs.inode_id("/big.bin").unwrap();
+            assert_eq!(
+                fs.inodes[&big].content().as_ptr(),
+                fs.snapshots[&id].inodes[&big].content().as_ptr()
+            );
+            let small = fs.inode_id("/small.txt").unwrap();
+            assert_eq!(fs.snapshots[&id].inodes[&small].content(), b"small");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_restore_hard_links() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/a.txt", Some(b"shared"))?;
+            let id = fs.snapshot();
+
+            fs.hard_link("/a.txt", "/b.txt")?;
+            fs.rm("/a.txt")?;
+            fs.mkfile("/c.txt", Some(b"c"))?;
+
+            fs.restore(id)?;
+            assert!(!fs.exists("/b.txt"));
+            let id = fs.inode_id("/a.txt").unwrap();
+            assert_eq!(fs.inodes[&id].links(), 1);
+            fs.mkfile("/c.txt", Some(b"c"))?;
+            assert_ne!(fs.inode_id("/a.txt"), fs.inode_id("/c.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_unknown_snapshot() -> Result<()> {
+            let mut fs = MapFS::new();
+            let id = fs.snapshot();
+            let other = fs.snapshot();
+            assert_ne!(id, other);
+
+            fs.discard_snapshot(id)?;
+            assert!(matches!(fs.restore(id), Err(VfsError::Other(_))));
+            assert!(matches!(fs.discard_snapshot(id), Err(VfsError::Other(_))));
+            fs.restore(other)?;
+
+            Ok(())
+        }
+    }
+
+    mod transactions {
+        use super::*;
+
+        #[test]
+        fn test_commit() -> Result<()> {
+            let mut fs = MapFS::new();
+            let count = fs.transaction(|fs| {
+                fs.mkfile("/out/a.rs", Some(b"mod b;"))?;
+                fs.mkfile("/out/b.rs", None)?;
+                Ok(fs.tree("/out")?.count())
+            })?;
+            assert_eq!(count, 2);
+            assert_eq!(fs.read("/out/a.rs")?, b"mod b;");
+            Ok(())
+        }
+
+        #[test]
+        fn test_rollback_on_error() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/docs/readme.md", Some(b"# Docs"))?;
+
+            let result = fs.transaction(|fs| {
+                fs.write("/docs/readme.md", b"# Changed")?;
+                fs.mkfile("/out/a.rs", None)?;
+                fs.cd("/out")?;
+                fs.rm("/missing.rs")
+            });
+
+            assert!(matches!(result, Err(VfsError::NotFound(_))));
+            assert_eq!(fs.read("/docs/readme.md")?, b"# Docs");
+            assert!(!fs.exists("/out"));
+            assert_eq!(fs.cwd(), Path::new("/"));
+            Ok(())
+        }
+
+        #[test]
+        fn test_rollback_on_panic() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/a.txt", Some(b"a"))?;
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                fs.transaction(|fs| {
+                    fs.rm("/a.txt")?;
+                    panic!("generator failed");
+                    #[allow(unreachable_code)]
+                    Ok(())
+                })
+            }));
+
+            assert!(result.is_err());
+            assert_eq!(fs.read("/a.txt")?, b"a");
+            Ok(())
+        }
+
+        #[test]
+        fn test_nested_rollback() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.transaction(|fs| {
+                fs.mkfile("/outer.txt", None)?;
+                let inner = fs.transaction(|fs| {
+                    fs.mkfile("/inner.txt", None)?;
+                    fs.rm("/missing.txt")
+                });
+                assert!(inner.is_err());
+                assert!(!fs.exists("/inner.txt"));
+                Ok(())
+            })?;
+            assert!(fs.exists("/outer.txt"));
+            Ok(())
+        }
+    }
+
+    mod materialize {
+        use super::*;
+        use tempdir::TempDir;
+
+        #[test]
+        fn test_materialize_tree() -> Result<()> {
+            let temp_dir = TempDir::new("materialize")?;
+            let host = temp_dir.path().join("dump");
+            let mut fs = MapFS::new();
+            fs.mkfile("/docs/readme.md", Some(b"# Dump"))?;
+            fs.mkdir("/empty")?;
+            fs.hard_link("/docs/readme.md", "/readme.md")?;
+            fs.symlink("docs", "/link")?;
+            fs.mkfile_with_ttl("/expired", None, Duration::ZERO)?;
+
+            let dir = fs.materialize(&host)?;
+            assert_eq!(std::fs::read(host.join("docs/readme.md"))?, b"# Dump");
+            assert!(host.join("empty").is_dir());
+            assert!(host.join("link").is_symlink());
+            assert_eq!(std::fs::read(host.join("link/readme.md"))?, b"# Dump");
+            assert!(!host.join("expired").exists());
+            assert!(dir.exists("/docs/readme.md"));
+            assert_eq!(dir.tree("/")?.count(), fs.tree("/")?.count());
+
+            // the dump stays on the host
+            drop(dir);
+            assert!(host.join("docs/readme.md").exists());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_materialized_tree_can_be_cleaned() -> Result<()> {
+            let temp_dir = TempDir::new("materialize")?;
+            let host = temp_dir.path().join("dump");
+            let mut fs = MapFS::new();
+            fs.mkfile("/a/b.txt", Some(b"b"))?;
+
+            let mut dir = fs.materialize(&host)?;
+            dir.set_auto_clean(true);
+            drop(dir);
+            assert!(!host.exists());
+
+            Ok(())
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn test_materialize_permissions_and_hard_links() -> Result<()> {
+            use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+            let temp_dir = TempDir::new("materialize")?;
+            let mut fs = MapFS::new();
+            fs.mkfile("/bin/run.sh", Some(b"#!/bin/sh"))?;
+            fs.hard_link("/bin/run.sh", "/run.sh")?;
+            fs.set_permissions("/bin/run.sh", 0o750)?;
+            fs.set_permissions("/bin", 0o500)?;
+
+            fs.materialize(temp_dir.path())?;
+            let script = std::fs::metadata(temp_dir.path().join("bin/run.sh"))?;
+            assert_eq!(script.permissions().mode() & 0o7777, 0o750);
+            assert_eq!(script.nlink(), 2);
+            let bin = std::fs::metadata(temp_dir.path().join("bin"))?;
+            assert_eq!(bin.permissions().mode() & 0o7777, 0o500);
+
+            std::fs::set_permissions(
+                temp_dir.path().join("bin"),
+                std::fs::Permissions::from_mode(0o755),
+            )?;
+            Ok(())
+        }
+
+        #[test]
+        fn test_materialize_into_non_empty_dir() -> Result<()> {
+            let temp_dir = TempDir::new("materialize")?;
+            std::fs::write(temp_dir.path().join("other.txt"), b"")?;
+            let fs = MapFS::new();
+            assert!(matches!(
+                fs.materialize(temp_dir.path()),
+                Err(VfsError::AlreadyExists(_))
+            ));
+            assert!(matches!(
+                fs.materialize("relative/dump"),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            Ok(())
+        }
+    }
+
+    mod permissions {
+        use super::*;
+
+        fn is_denied<T>(result: Result<T>) -> bool {
+            matches!(result, Err(VfsError::PermissionDenied(_)))
+        }
+
+        #[test]
+        fn test_readonly_file() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/config.toml", Some(b"debug = true"))?;
+            fs.set_permissions("/config.toml", 0o444)?;
+
+            let meta = fs.metadata("/config.toml")?;
+            assert_eq!(meta.mode(), Some(0o444));
+            assert!(meta.is_readonly());
+
+            assert_eq!(fs.read("/config.toml")?, b"debug = true");
+            assert!(is_denied(fs.write("/config.toml", b"")));
+            assert!(is_denied(fs.append("/config.toml", b"!")));
+            assert!(is_denied(
+                fs.open("/config.toml", OpenOptions::new().write(true))
+            ));
+            assert!(
+                fs.open("/config.toml", OpenOptions::new().read(true))
+                    .is_ok()
+            );
+
+            fs.set_permissions("/config.toml", 0o644)?;
+            fs.write("/config.toml", b"debug = false")?;
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_unreadable_file() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/secret", Some(b"s3cr3t"))?;
+            fs.set_permissions("/secret", 0o200)?;
+
+            assert!(is_denied(fs.read("/secret")));
+            assert!(is_denied(fs.open("/secret", OpenOptions::new().read(true))));
+            fs.write("/secret", b"new")?;
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_non_executable_dir() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/dir/file.txt", Some(b"content"))?;
+            fs.set_permissions("/dir", 0o644)?;
+
+            assert!(is_denied(fs.ls("/dir")));
+            assert!(is_denied(fs.tree("/dir")));
+            assert!(is_denied(fs.read("/dir/file.txt")));
+            assert!(is_denied(fs.metadata("/dir/file.txt")));
+            assert!(is_denied(fs.cd("/dir")));
+            assert!(fs.exists("/dir/file.txt")); // not checked
+            assert_eq!(fs.ls("/")?.count(), 1);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_readonly_dir() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/dir/file.txt", None)?;
+            fs.mkdir("/other")?;
+            fs.set_permissions("/dir", 0o555)?;
+
+            assert!(is_denied(fs.mkfile("/dir/new.txt", None)));
+            assert!(is_denied(fs.mkdir("/dir/sub/deeper")));
+            assert!(is_denied(fs.symlink("file.txt", "/dir/link")));
+            assert!(is_denied(fs.hard_link("/dir/file.txt", "/dir/link")));
+            assert!(is_denied(fs.rm("/dir/file.txt")));
+            assert!(is_denied(fs.rename("/dir/file.txt", "/other/file.txt")));
+            assert!(is_denied(fs.rename("/other", "/dir/other")));
+            assert_eq!(fs.ls("/dir")?.count(), 1);
+
+            // the contents of existing files may still be changed
+            fs.write("/dir/file.txt", b"changed")?;
+            // and the directory itself may be removed from a writable parent
+            fs.rm("/dir")?;
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_file_as_parent() -> Result<()> {
+            for is_enforcing in [true, false] {
+                let mut fs = MapFS::new();
+                fs.set_enforce_permissions(is_enforcing);
+                fs.mkfile("/f.txt", None)?;
+                fs.mkfile("/g.txt", None)?;
+
+                assert!(matches!(
+                    fs.mkfile("/f.txt/x", None),
+                    Err(VfsError::NotADirectory(path)) if path == Path::new("/f.txt")
+                ));
+                assert!(matches!(
+                    fs.mkdir("/g.txt/d"),
+                    Err(VfsError::NotADirectory(path)) if path == Path::new("/g.txt")
+                ));
+                assert!(matches!(
+                    fs.symlink("/g.txt", "/f.txt/link"),
+                    Err(VfsError::NotADirectory(_))
+                ));
+                assert!(matches!(
+                    fs.hard_link("/g.txt", "/f.txt/a/b"),
+                    Err(VfsError::NotADirectory(_))
+                ));
+                assert_eq!(
+                    fs.tree("/")?.collect::<Vec<_>>(),
+                    vec![Path::new("/f.txt"), Path::new("/g.txt")]
+                );
+            }
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_owner_group_and_others() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/report.csv", Some(b"a,b"))?;
+            fs.set_permissions("/report.csv", 0o640)?;
+            fs.set_user(1000, 100);
+
+            fs.set_owner("/report.csv", 0, 100)?; // group
+            assert_eq!(fs.read("/report.csv")?, b"a,b");
+            assert!(is_denied(fs.write("/report.csv", b"")));
+
+            fs.set_owner("/report.csv", 0, 0)?; // others
+            assert!(is_denied(fs.read("/report.csv")));
+            assert!(is_denied(fs.set_permissions("/report.csv", 0o666)));
+
+            fs.set_owner("/report.csv", 1000, 0)?; // owner
+            fs.write("/report.csv", b"c,d")?;
+            fs.set_permissions("/report.csv", 0o400)?;
+            assert_eq!(fs.metadata("/report.csv")?.uid(), Some(1000));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_new_entries_are_owned_by_user() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.set_user(1000, 100);
+            fs.mkfile("/home/user/.profile", None)?;
+            fs.symlink(".profile", "/home/user/.bashrc")?;
+
+            for path in ["/home", "/home/user/.profile"] {
+                let meta = fs.metadata(path)?;
+                assert_eq!((meta.uid(), meta.gid()), (Some(1000), Some(100)));
+            }
+            assert_eq!(fs.metadata("/home")?.mode(), Some(0o755));
+            assert_eq!(fs.metadata("/home/user/.bashrc")?.mode(), Some(0o644));
+            assert_eq!(fs.user(), (1000, 100));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_hard_links_share_permissions() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/a.txt", None)?;
+            fs.hard_link("/a.txt", "/b.txt")?;
+            fs.set_permissions("/b.txt", 0o400)?;
+
+            assert_eq!(fs.metadata("/a.txt")?.mode(), Some(0o400));
+            assert!(is_denied(fs.write("/a.txt", b"")));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_enforcement_switched_off() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/dir/file.txt", None)?;
+            fs.set_permissions("/dir/file.txt", 0o000)?;
+            fs.set_permissions("/dir", 0o000)?;
+            assert!(is_denied(fs.read("/dir/file.txt")));
+
+            fs.set_enforce_permissions(false);
+            fs.write("/dir/file.txt", b"content")?;
+            assert_eq!(fs.read("/dir/file.txt")?, b"content");
+            assert_eq!(fs.metadata("/dir")?.mode(), Some(0o000));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_root_is_always_accessible() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.set_user(1000, 1000);
+            fs.mkfile("/file.txt", None)?;
+
+            assert!(matches!(
+                fs.set_permissions("/", 0o000),
+                Err(VfsError::RootProtected(_))
+            ));
+            assert_eq!(fs.metadata("/")?.mode(), Some(0o755));
+
+            Ok(())
+        }
+    }
+
+    mod errors {
+        use super::*;
+        use std::io::ErrorKind;
+
+        #[test]
+        fn test_error_variants() -> Result<()> {
+            let mut fs = MapFS::new();
+            fs.mkfile("/dir/file.txt", None)?;
+
+            let err = fs.read("/missing").unwrap_err();
+            assert!(matches!(&err, VfsError::NotFound(path) if path == Path::new("/missing")));
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+
+            fs.cd("/dir")?;
+            let err = fs.read(".").unwrap_err();
+            assert!(matches!(&err, VfsError::IsADirectory(path) if path == Path::new("/dir")));
+
+            let err = fs.mkfile("file.txt", None).unwrap_err();
+            assert!(
+                matches!(&err, VfsError::AlreadyExists(path) if path == Path::new("/dir/file.txt"))
+            );
+            assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+            assert!(matches!(fs.cd("file.txt"), Err(VfsError::NotADirectory(_))));
+            assert!(matches!(fs.rm("/"), Err(VfsError::RootProtected(_))));
+            assert!(matches!(fs.mkdir(""), Err(VfsError::InvalidPath { .. })));
+
+            Ok(())
+        }
+    }
 }
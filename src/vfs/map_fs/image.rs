@@ -0,0 +1,611 @@
+//! This module provides the portable image of a `MapFS`, written by `MapFS::save()` and read by
+//! `MapFS::load()` (and used as the serde representation with the `serde` feature).
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use super::MapFS;
+use crate::VfsError;
+use crate::core::{Result, utils};
+use crate::{Entry, EntryType, Inode, InodeId};
+
+const MAGIC: &[u8; 8] = b"VFSKIMAG";
+/// Version of the image format written by this version of the crate.
+const VERSION: u32 = 1;
+
+const FILE: u8 = 1;
+const DIRECTORY: u8 = 2;
+const SYMLINK: u8 = 3;
+
+/// The whole state of a `MapFS`. Contents are borrowed while saving.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct Image<'a> {
+    version: u32,
+    root: PathBuf,
+    cwd: PathBuf,
+    uid: u32,
+    gid: u32,
+    is_enforcing: bool,
+    inodes: Vec<InodeImage<'a>>,
+    entries: Vec<EntryImage>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct InodeImage<'a> {
+    id: InodeId,
+    attributes: Attributes,
+    content: Cow<'a, [u8]>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct EntryImage {
+    path: PathBuf,
+    entry_type: EntryType,
+    attributes: Attributes,
+    expires_at: Option<u64>,
+    inode: Option<InodeId>,       // files only
+    link_target: Option<PathBuf>, // symbolic links only
+}
+
+/// Permission bits, owner and times (nanoseconds since `UNIX_EPOCH`) of an entry or inode.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Attributes {
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    created: u64,
+    modified: u64,
+    accessed: u64,
+}
+
+impl Attributes {
+    fn of_entry(entry: &Entry) -> Self {
+        Self {
+            mode: entry.mode(),
+            uid: entry.uid(),
+            gid: entry.gid(),
+            created: to_nanos(entry.created()),
+            modified: to_nanos(entry.modified()),
+            accessed: to_nanos(entry.accessed()),
+        }
+    }
+
+    fn of_inode(inode: &Inode) -> Self {
+        Self {
+            mode: inode.mode(),
+            uid: inode.uid(),
+            gid: inode.gid(),
+            created: to_nanos(inode.created()),
+            modified: to_nanos(inode.modified()),
+            accessed: to_nanos(inode.accessed()),
+        }
+    }
+
+    fn apply_to_entry(&self, entry: &mut Entry) {
+        entry.set_mode(self.mode);
+        entry.set_owner(self.uid, self.gid);
+        entry.set_times(
+            from_nanos(self.created),
+            from_nanos(self.modified),
+            from_nanos(self.accessed),
+        );
+    }
+
+    fn apply_to_inode(&self, inode: &mut Inode) {
+        inode.set_mode(self.mode);
+        inode.set_owner(self.uid, self.gid);
+        inode.set_times(
+            from_nanos(self.created),
+            from_nanos(self.modified),
+            from_nanos(self.accessed),
+        );
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        for value in [self.mode, self.uid, self.gid] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for value in [self.created, self.modified, self.accessed] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(Self {
+            mode: read_u32(reader)?,
+            uid: read_u32(reader)?,
+            gid: read_u32(reader)?,
+            created: read_u64(reader)?,
+            modified: read_u64(reader)?,
+            accessed: read_u64(reader)?,
+        })
+    }
+}
+
+impl<'a> Image<'a> {
+    /// Captures the state of `fs`, borrowing file contents.
+    pub(super) fn from_fs(fs: &'a MapFS) -> Self {
+        let inodes = fs
+            .inodes
+            .iter()
+            .map(|(&id, inode)| InodeImage {
+                id,
+                attributes: Attributes::of_inode(inode),
+                content: Cow::Borrowed(inode.content().as_slice()),
+            })
+            .collect();
+        let entries = fs
+            .entries
+            .iter()
+            .map(|(path, entry)| EntryImage {
+                path: path.clone(),
+                entry_type: entry.entry_type(),
+                attributes: Attributes::of_entry(entry),
+                expires_at: entry.expires_at().map(to_nanos),
+                inode: entry.inode(),
+                link_target: entry.link_target().map(PathBuf::from),
+            })
+            .collect();
+        Self {
+            version: VERSION,
+            root: fs.root.clone(),
+            cwd: fs.cwd.clone(),
+            uid: fs.uid,
+            gid: fs.gid,
+            is_enforcing: fs.is_enforcing,
+            inodes,
+            entries,
+        }
+    }
+
+    /// Rebuilds a `MapFS`, checking that the image is consistent.
+    pub(super) fn into_fs(self) -> Result<MapFS> {
+        if self.version != VERSION {
+            return Err(VfsError::other(format!(
+                "unsupported MapFS image version {}",
+                self.version
+            )));
+        }
+        let mut fs = MapFS::new();
+        fs.set_root(&self.root)?;
+        fs.set_user(self.uid, self.gid);
+        fs.set_enforce_permissions(self.is_enforcing);
+
+        let mut contents: BTreeMap<InodeId, InodeImage> = BTreeMap::new();
+        for inode in self.inodes {
+            if contents.insert(inode.id, inode).is_some() {
+                return Err(corrupted("duplicate inode"));
+            }
+        }
+
+        let mut entries = self.entries;
+        entries.sort_by(|a, b| a.path.cmp(&b.path)); // parents first
+        for image in entries {
+            let path = image.path;
+            if !path.is_absolute()
+                || utils::normalize(&path) != path
+                || utils::is_virtual_root(&path)
+            {
+                return Err(VfsError::invalid_path(
+                    path,
+                    "not a valid entry of an image",
+                ));
+            }
+            let parent = path.parent().unwrap(); // safe unwrap(): not the root
+            if !utils::is_virtual_root(parent) && !fs.entries.get(parent).is_some_and(Entry::is_dir)
+            {
+                return Err(VfsError::NotADirectory(parent.to_path_buf()));
+            }
+            if fs.entries.contains_key(&path) {
+                return Err(VfsError::AlreadyExists(path));
+            }
+
+            let mut entry = match (image.entry_type, image.inode, image.link_target) {
+                (EntryType::Directory, None, None) => Entry::new(EntryType::Directory),
+                (EntryType::Symlink, None, Some(target)) => Entry::new_symlink(target),
+                (EntryType::File, Some(id), None) => {
+                    match fs.inodes.get_mut(&id) {
+                        Some(inode) => inode.link(),
+                        None => {
+                            let Some(content) = contents.remove(&id) else {
+                                return Err(corrupted("a file points to a missing inode"));
+                            };
+                            let mut inode = Inode::new(&content.content);
+                            content.attributes.apply_to_inode(&mut inode);
+                            fs.inodes.insert(id, inode);
+                        }
+                    }
+                    let next = id
+                        .checked_add(1)
+                        .ok_or_else(|| corrupted("inode id out of range"))?;
+                    fs.next_inode = fs.next_inode.max(next);
+                    Entry::new_file(id)
+                }
+                _ => return Err(corrupted("an entry does not match its type")),
+            };
+            image.attributes.apply_to_entry(&mut entry);
+            entry.set_expires_at(image.expires_at.map(from_nanos));
+            fs.entries.insert(path, entry);
+        }
+        // inodes no entry points to are dropped
+
+        let is_dir = utils::is_virtual_root(&self.cwd)
+            || fs.entries.get(&self.cwd).is_some_and(Entry::is_dir);
+        if !is_dir {
+            return Err(VfsError::NotADirectory(self.cwd));
+        }
+        fs.cwd = self.cwd;
+        Ok(fs)
+    }
+
+    /// Writes the image in the binary format (see `MapFS::save()`).
+    pub(super) fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&self.version.to_le_bytes())?;
+        write_path(writer, &self.root)?;
+        write_path(writer, &self.cwd)?;
+        writer.write_all(&self.uid.to_le_bytes())?;
+        writer.write_all(&self.gid.to_le_bytes())?;
+        writer.write_all(&[u8::from(self.is_enforcing)])?;
+
+        writer.write_all(&(self.inodes.len() as u64).to_le_bytes())?;
+        for inode in &self.inodes {
+            writer.write_all(&inode.id.to_le_bytes())?;
+            inode.attributes.write(writer)?;
+            writer.write_all(&(inode.content.len() as u64).to_le_bytes())?;
+            writer.write_all(&inode.content)?;
+        }
+
+        writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for entry in &self.entries {
+            write_path(writer, &entry.path)?;
+            let entry_type = match entry.entry_type {
+                EntryType::File => FILE,
+                EntryType::Directory => DIRECTORY,
+                EntryType::Symlink => SYMLINK,
+            };
+            writer.write_all(&[entry_type])?;
+            entry.attributes.write(writer)?;
+            match entry.expires_at {
+                Some(expires_at) => {
+                    writer.write_all(&[1])?;
+                    writer.write_all(&expires_at.to_le_bytes())?;
+                }
+                None => writer.write_all(&[0])?,
+            }
+            match (&entry.inode, &entry.link_target) {
+                (Some(id), _) => writer.write_all(&id.to_le_bytes())?,
+                (None, Some(target)) => write_path(writer, target)?,
+                (None, None) => {}
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads an image in the binary format (see `MapFS::save()`).
+    pub(super) fn read<R: Read>(reader: &mut R) -> Result<Image<'static>> {
+        let mut magic = [0; 8];
+        read_exact(reader, &mut magic)?;
+        if &magic != MAGIC {
+            return Err(VfsError::other("not a MapFS image"));
+        }
+        let version = read_u32(reader)?;
+        if version != VERSION {
+            return Err(VfsError::other(format!(
+                "unsupported MapFS image version {version}"
+            )));
+        }
+        let root = read_path(reader)?;
+        let cwd = read_path(reader)?;
+        let uid = read_u32(reader)?;
+        let gid = read_u32(reader)?;
+        let is_enforcing = read_u8(reader)? != 0;
+
+        let mut inodes = Vec::new();
+        for _ in 0..read_u64(reader)? {
+            let id = read_u64(reader)?;
+            let attributes = Attributes::read(reader)?;
+            let len = read_u64(reader)?;
+            let content = read_bytes(reader, len)?;
+            inodes.push(InodeImage {
+                id,
+                attributes,
+                content: Cow::Owned(content),
+            });
+        }
+
+        let mut entries = Vec::new();
+        for _ in 0..read_u64(reader)? {
+            let path = read_path(reader)?;
+            let entry_type = match read_u8(reader)? {
+                FILE => EntryType::File,
+                DIRECTORY => EntryType::Directory,
+                SYMLINK => EntryType::Symlink,
+                _ => return Err(corrupted("unknown entry type")),
+            };
+            let attributes = Attributes::read(reader)?;
+            let expires_at = match read_u8(reader)? {
+                0 => None,
+                _ => Some(read_u64(reader)?),
+            };
+            let (inode, link_target) = match entry_type {
+                EntryType::File => (Some(read_u64(reader)?), None),
+                EntryType::Symlink => (None, Some(read_path(reader)?)),
+                EntryType::Directory => (None, None),
+            };
+            entries.push(EntryImage {
+                path,
+                entry_type,
+                attributes,
+                expires_at,
+                inode,
+                link_target,
+            });
+        }
+
+        Ok(Image {
+            version,
+            root,
+            cwd,
+            uid,
+            gid,
+            is_enforcing,
+            inodes,
+            entries,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for MapFS {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        Image::from_fs(self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MapFS {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        Image::deserialize(deserializer)?
+            .into_fs()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn corrupted(reason: &str) -> VfsError {
+    VfsError::other(format!("corrupted MapFS image: {reason}"))
+}
+
+fn to_nanos(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64)
+}
+
+fn from_nanos(nanos: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos)
+}
+
+fn write_path<W: Write>(writer: &mut W, path: &std::path::Path) -> Result<()> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| VfsError::invalid_path(path, "not a valid UTF-8 string"))?;
+    writer.write_all(&(path.len() as u32).to_le_bytes())?;
+    writer.write_all(path.as_bytes())?;
+    Ok(())
+}
+
+fn read_path<R: Read>(reader: &mut R) -> Result<PathBuf> {
+    let len = read_u32(reader)?;
+    let bytes = read_bytes(reader, u64::from(len))?;
+    let path = String::from_utf8(bytes).map_err(|_| corrupted("a path is not UTF-8"))?;
+    Ok(PathBuf::from(path))
+}
+
+/// Reads exactly `len` bytes, without trusting `len` for the allocation.
+fn read_bytes<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(corrupted("unexpected end of data"));
+    }
+    Ok(bytes)
+}
+
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<()> {
+    reader.read_exact(buf).map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => corrupted("unexpected end of data"),
+        _ => VfsError::Io(err),
+    })
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8> {
+    let mut buf = [0; 1];
+    read_exact(reader, &mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut buf = [0; 4];
+    read_exact(reader, &mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut buf = [0; 8];
+    read_exact(reader, &mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FsBackend, MapFS};
+    use std::path::Path;
+
+    fn create_fixture() -> Result<MapFS> {
+        let mut fs = MapFS::new();
+        fs.set_root("/srv/fixtures")?;
+        fs.mkfile("/docs/readme.md", Some(b"# Fixtures"))?;
+        fs.mkfile("/data/blob.bin", Some(&[0, 159, 146, 150]))?;
+        fs.mkdir("/empty")?;
+        fs.hard_link("/docs/readme.md", "/data/readme.md")?;
+        fs.symlink("../docs", "/data/docs")?;
+        fs.set_permissions("/data/blob.bin", 0o600)?;
+        fs.set_owner("/docs", 1000, 100)?;
+        fs.set_ttl("/empty", Some(Duration::from_secs(3600)))?;
+        fs.cd("/docs")?;
+        Ok(fs)
+    }
+
+    fn assert_same(restored: &MapFS, fs: &MapFS) -> Result<()> {
+        assert_eq!(restored.root(), fs.root());
+        assert_eq!(restored.cwd(), fs.cwd());
+        assert_eq!(restored.entries, fs.entries);
+        assert_eq!(restored.inodes, fs.inodes);
+        assert_eq!(restored.user(), fs.user());
+        Ok(())
+    }
+
+    mod binary {
+        use super::*;
+
+        #[test]
+        fn test_save_load_roundtrip() -> Result<()> {
+            let fs = create_fixture()?;
+            let mut image = Vec::new();
+            fs.save(&mut image)?;
+
+            let mut restored = MapFS::load(image.as_slice())?;
+            assert_same(&restored, &fs)?;
+            assert_eq!(restored.read("readme.md")?, b"# Fixtures");
+            assert_eq!(restored.read("/data/docs/readme.md")?, b"# Fixtures");
+            assert_eq!(restored.metadata("/data/blob.bin")?.mode(), Some(0o600));
+            assert!(restored.expires_at("/empty")?.is_some());
+
+            // hard links still share the contents, new inodes do not clash
+            restored.write("/data/readme.md", b"changed")?;
+            assert_eq!(restored.read("/docs/readme.md")?, b"changed");
+            restored.mkfile("/new.txt", Some(b"new"))?;
+            assert_ne!(
+                restored.inode_id("/new.txt"),
+                restored.inode_id("/docs/readme.md")
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_empty_fs() -> Result<()> {
+            let mut image = Vec::new();
+            MapFS::new().save(&mut image)?;
+            let fs = MapFS::load(image.as_slice())?;
+            assert_eq!(fs.tree("/")?.count(), 0);
+            assert_eq!(fs.cwd(), Path::new("/"));
+            Ok(())
+        }
+
+        #[test]
+        fn test_header() -> Result<()> {
+            let mut image = Vec::new();
+            MapFS::new().save(&mut image)?;
+            assert_eq!(&image[..8], MAGIC);
+            assert_eq!(image[8..12], VERSION.to_le_bytes());
+            Ok(())
+        }
+
+        #[test]
+        fn test_invalid_images() -> Result<()> {
+            let mut image = Vec::new();
+            create_fixture()?.save(&mut image)?;
+
+            assert!(matches!(
+                MapFS::load(&b"not an image"[..]),
+                Err(VfsError::Other(_))
+            ));
+            let mut newer = image.clone();
+            newer[8] = 2;
+            let err = MapFS::load(newer.as_slice()).err().unwrap();
+            assert!(err.to_string().contains("version 2"));
+            for len in [4, 20, image.len() - 1] {
+                assert!(matches!(
+                    MapFS::load(&image[..len]),
+                    Err(VfsError::Other(_))
+                ));
+            }
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_inconsistent_image() -> Result<()> {
+            let fs = create_fixture()?;
+            let mut image = Image::from_fs(&fs);
+            image
+                .entries
+                .retain(|entry| entry.path != Path::new("/docs"));
+            assert!(matches!(
+                image.into_fs(),
+                Err(VfsError::NotADirectory(path)) if path == Path::new("/docs")
+            ));
+
+            let mut image = Image::from_fs(&fs);
+            image.inodes.clear();
+            assert!(matches!(image.into_fs(), Err(VfsError::Other(_))));
+
+            let mut single = MapFS::new();
+            single.mkfile("/file.txt", Some(b"content"))?;
+            let mut image = Image::from_fs(&single);
+            for inode in &mut image.inodes {
+                inode.id = u64::MAX;
+            }
+            for entry in &mut image.entries {
+                if entry.inode.is_some() {
+                    entry.inode = Some(u64::MAX);
+                }
+            }
+            let err = image.into_fs().err().unwrap();
+            assert!(err.to_string().contains("inode id out of range"));
+
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    mod serde_repr {
+        use super::*;
+
+        #[test]
+        fn test_json_roundtrip() -> Result<()> {
+            let fs = create_fixture()?;
+            let json = serde_json::to_string(&fs).map_err(VfsError::other)?;
+            let restored: MapFS = serde_json::from_str(&json).map_err(VfsError::other)?;
+            assert_same(&restored, &fs)?;
+            assert_eq!(restored.read("/data/readme.md")?, b"# Fixtures");
+            Ok(())
+        }
+
+        #[test]
+        fn test_inconsistent_json() -> Result<()> {
+            let fs = create_fixture()?;
+            let mut json = serde_json::to_value(&fs).map_err(VfsError::other)?;
+            json["version"] = 7.into();
+            let err = serde_json::from_value::<MapFS>(json).err().unwrap();
+            assert!(err.to_string().contains("version 7"));
+            Ok(())
+        }
+    }
+}
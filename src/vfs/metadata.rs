@@ -0,0 +1,195 @@
+//! This module provides `Metadata`, the result of `FsBackend::metadata()`.
+
+use std::fs;
+use std::time::SystemTime;
+
+use crate::EntryType;
+
+/// Metadata of a file or directory in VFS: its type, length, timestamps, permissions, owner
+/// and expiry.
+///
+/// Timestamps, permission bits, owner and expiry are optional, since not every backend (or host
+/// platform) tracks all of them.
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{FsBackend, MapFS};
+///
+/// let mut fs = MapFS::new();
+/// fs.mkfile("/note.txt", Some(b"Hello")).unwrap();
+///
+/// let meta = fs.metadata("/note.txt").unwrap();
+/// assert!(meta.is_file());
+/// assert_eq!(meta.len(), 5);
+/// println!("modified at {:?}", meta.modified());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    entry_type: EntryType,
+    len: u64,
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    is_readonly: bool,
+    mode: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    expires_at: Option<SystemTime>,
+}
+
+impl Metadata {
+    /// Creates metadata of an entry with the given type and length (in bytes).
+    /// Timestamps, permission bits and owner are unknown and the entry is writable.
+    pub fn new(entry_type: EntryType, len: u64) -> Self {
+        Self {
+            entry_type,
+            len,
+            created: None,
+            modified: None,
+            accessed: None,
+            is_readonly: false,
+            mode: None,
+            uid: None,
+            gid: None,
+            expires_at: None,
+        }
+    }
+
+    pub fn entry_type(&self) -> EntryType {
+        self.entry_type
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.entry_type == EntryType::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.entry_type == EntryType::Directory
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.entry_type == EntryType::Symlink
+    }
+
+    /// Returns the length of the file contents in bytes (`0` for directories of in-memory backends).
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true, if the length is zero.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    pub fn accessed(&self) -> Option<SystemTime> {
+        self.accessed
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.is_readonly
+    }
+
+    /// Returns the Unix permission bits (`0o7777` mask, without the file type).
+    pub fn mode(&self) -> Option<u32> {
+        self.mode
+    }
+
+    /// Returns the user id of the owner.
+    pub fn uid(&self) -> Option<u32> {
+        self.uid
+    }
+
+    /// Returns the group id of the owner.
+    pub fn gid(&self) -> Option<u32> {
+        self.gid
+    }
+
+    /// Returns the moment the entry expires (see `MapFS::set_ttl()`), or `None` if it never does.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires_at
+    }
+
+    pub fn set_len(&mut self, len: u64) {
+        self.len = len;
+    }
+
+    pub fn set_created(&mut self, time: Option<SystemTime>) {
+        self.created = time;
+    }
+
+    pub fn set_modified(&mut self, time: Option<SystemTime>) {
+        self.modified = time;
+    }
+
+    pub fn set_accessed(&mut self, time: Option<SystemTime>) {
+        self.accessed = time;
+    }
+
+    pub fn set_readonly(&mut self, is_readonly: bool) {
+        self.is_readonly = is_readonly;
+    }
+
+    /// Sets the permission bits; the read-only flag is updated to match them
+    /// (read-only means no write bit is set).
+    pub fn set_mode(&mut self, mode: Option<u32>) {
+        self.mode = mode.map(|mode| mode & 0o7777);
+        if let Some(mode) = self.mode {
+            self.is_readonly = mode & 0o222 == 0;
+        }
+    }
+
+    pub fn set_owner(&mut self, uid: Option<u32>, gid: Option<u32>) {
+        self.uid = uid;
+        self.gid = gid;
+    }
+
+    pub fn set_expires_at(&mut self, time: Option<SystemTime>) {
+        self.expires_at = time;
+    }
+}
+
+impl From<fs::Metadata> for Metadata {
+    /// Converts host metadata. Anything that is not a directory or a symbolic link is treated
+    /// as a file. Permission bits and owner are known on Unix only.
+    fn from(meta: fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let (mode, uid, gid) = {
+            use std::os::unix::fs::MetadataExt;
+            (
+                Some(meta.mode() & 0o7777),
+                Some(meta.uid()),
+                Some(meta.gid()),
+            )
+        };
+        #[cfg(not(unix))]
+        let (mode, uid, gid) = (None, None, None);
+
+        let entry_type = if meta.is_symlink() {
+            EntryType::Symlink
+        } else if meta.is_dir() {
+            EntryType::Directory
+        } else {
+            EntryType::File
+        };
+        Self {
+            entry_type,
+            len: meta.len(),
+            created: meta.created().ok(),
+            modified: meta.modified().ok(),
+            accessed: meta.accessed().ok(),
+            is_readonly: meta.permissions().readonly(),
+            mode,
+            uid,
+            gid,
+            expires_at: None,
+        }
+    }
+}
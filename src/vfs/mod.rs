@@ -1,7 +1,35 @@
+#[cfg(feature = "async")]
+mod async_fs;
 mod dir_fs;
+#[cfg(feature = "encryption")]
+mod encrypted_fs;
 mod entry;
+mod file;
+mod log_fs;
 mod map_fs;
+mod metadata;
+mod mount_fs;
+mod overlay_fs;
+mod shared_fs;
+#[cfg(feature = "tar")]
+mod tar_fs;
+#[cfg(feature = "zip")]
+mod zip_fs;
 
+#[cfg(feature = "async")]
+pub use async_fs::{AsyncAdapter, AsyncFsBackend, AsyncVfsFile};
 pub use dir_fs::DirFS;
-pub use entry::{Entry, EntryType};
-pub use map_fs::MapFS;
+#[cfg(feature = "encryption")]
+pub use encrypted_fs::EncryptedFS;
+pub use entry::{Entry, EntryType, Inode, InodeId};
+pub use file::{OpenOptions, VfsFile};
+pub use log_fs::LogFS;
+pub use map_fs::{ImportOptions, MapFS, SnapshotId};
+pub use metadata::Metadata;
+pub use mount_fs::MountFS;
+pub use overlay_fs::OverlayFS;
+pub use shared_fs::{SharedFile, SharedFs};
+#[cfg(feature = "tar")]
+pub use tar_fs::TarFS;
+#[cfg(feature = "zip")]
+pub use zip_fs::ZipFS;
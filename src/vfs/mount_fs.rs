@@ -0,0 +1,865 @@
+//! This module provides a virtual filesystem (VFS) implementation that composes several backends
+//! under mount points in a single namespace.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::VfsError;
+use crate::core::{DynFsBackend, FsBackend, Result, utils};
+use crate::vfs::file::prepare_open;
+use crate::{EntryType, Metadata, OpenOptions, VfsFile};
+
+/// A virtual file system (VFS) implementation that composes several backends under mount points
+/// in one namespace.
+///
+/// Every call is routed to the backend owning the longest mount point that prefixes the path,
+/// and the path is translated into the inner path of that backend (`/data/a.txt` becomes
+/// `/a.txt` for a backend mounted at `/data`). The current working directory and path
+/// normalization are handled by `MountFS` itself, the working directories of mounted backends
+/// are never changed.
+///
+/// Mount points and their parents are shown as directories (e.g. in `ls("/")`), even if no
+/// backend is mounted at `/`. Such synthetic directories cannot hold entries of their own.
+///
+/// ### Usage notes:
+/// - On `mount()` the tree of the backend (as returned by `tree("/")`) is indexed, so mount
+///   backends after they are populated, and modify them through `MountFS` afterwards.
+/// - A mount shadows the entries of the parent backend under the mount point.
+/// - A symbolic link may point only inside the backend storing it. An absolute link target is
+///   stored relative to the root of that backend (`read_link()` returns it prefixed with the
+///   mount point again).
+/// - Mount points (and directories containing them) cannot be removed; use `umount()`.
+/// - `root()` returns `/`, use `to_host()` to get the host path of a particular entry.
+/// - Not thread‑safe (mounted backends are not required to be `Send` or `Sync`).
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{DirFS, FsBackend, MapFS, MountFS};
+///
+/// let mut fs = MountFS::new();
+/// fs.mount("/data", DirFS::new("/var/lib/app").unwrap()).unwrap();
+/// fs.mount("/tmp", MapFS::new()).unwrap();
+///
+/// fs.mkfile("/tmp/scratch.txt", Some(b"in memory")).unwrap();
+/// for path in fs.ls("/").unwrap() {
+///     println!("{}", path.display()); // "/data", "/tmp"
+/// }
+/// ```
+pub struct MountFS {
+    root: PathBuf,                                    // always `/`
+    cwd: PathBuf,                                     // inner absolute normalized path
+    mounts: BTreeMap<PathBuf, Box<dyn DynFsBackend>>, // mount point -> backend
+    entries: BTreeMap<PathBuf, EntryType>,            // inner absolute normalized paths
+}
+
+impl MountFS {
+    /// Creates new MountFS instance without any mounted backends.
+    pub fn new() -> Self {
+        Self {
+            root: PathBuf::from("/"),
+            cwd: PathBuf::from("/"),
+            mounts: BTreeMap::new(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Mounts `backend` at `path`.
+    /// * `path` can be in relative or absolute form; it does not have to exist.
+    ///
+    /// An error is returned if another backend is already mounted at `path`.
+    pub fn mount<P: AsRef<Path>, B: FsBackend + 'static>(
+        &mut self,
+        path: P,
+        backend: B,
+    ) -> Result<()> {
+        let mount_point = self.to_inner(path);
+        if self.mounts.contains_key(&mount_point) {
+            return Err(VfsError::AlreadyExists(mount_point));
+        }
+        self.mounts.insert(mount_point, Box::new(backend));
+        self.reindex()
+    }
+
+    /// Unmounts the backend mounted at `path` (the backend is dropped).
+    ///
+    /// If the current working directory disappears, it is reset to `/`.
+    /// An error is returned if no backend is mounted at `path`.
+    pub fn umount<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let mount_point = self.to_inner(path);
+        if self.mounts.remove(&mount_point).is_none() {
+            return Err(VfsError::invalid_path(
+                mount_point,
+                "the path is not a mount point",
+            ));
+        }
+        self.reindex()?;
+        if !self.exists(&self.cwd) {
+            self.cwd = PathBuf::from("/");
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator over all mount points.
+    pub fn mount_points(&self) -> impl Iterator<Item = &Path> {
+        self.mounts.keys().map(|pb| pb.as_path())
+    }
+
+    fn to_inner<P: AsRef<Path>>(&self, inner_path: P) -> PathBuf {
+        utils::normalize(self.cwd.join(inner_path))
+    }
+
+    /// Returns the mount point owning `inner_path` (the longest matching prefix).
+    fn mount_point_of(&self, inner_path: &Path) -> Option<&Path> {
+        self.mounts
+            .keys()
+            .rev()
+            .find(|mount_point| inner_path.starts_with(mount_point))
+            .map(|pb| pb.as_path())
+    }
+
+    /// Returns the mount point owning `inner_path` and the path translated into the backend.
+    fn resolve(&self, inner_path: &Path) -> Result<(PathBuf, PathBuf)> {
+        let mount_point = self
+            .mount_point_of(inner_path)
+            .ok_or_else(|| VfsError::invalid_path(inner_path, "no backend mounted at the path"))?;
+        let backend_path = Path::new("/").join(inner_path.strip_prefix(mount_point)?);
+        Ok((mount_point.to_path_buf(), backend_path))
+    }
+
+    fn backend(&self, mount_point: &Path) -> &dyn DynFsBackend {
+        self.mounts[mount_point].as_ref()
+    }
+
+    fn backend_mut(&mut self, mount_point: &Path) -> &mut dyn DynFsBackend {
+        self.mounts.get_mut(mount_point).unwrap().as_mut() // safe unwrap()
+    }
+
+    /// Rebuilds the index from the trees of all mounted backends.
+    fn reindex(&mut self) -> Result<()> {
+        let mut entries = BTreeMap::new();
+        for (mount_point, backend) in &self.mounts {
+            for ancestor in mount_point.ancestors() {
+                if !utils::is_virtual_root(ancestor) {
+                    entries.insert(ancestor.to_path_buf(), EntryType::Directory);
+                }
+            }
+            for path in backend.dyn_tree(Path::new("/"))? {
+                let inner = mount_point.join(path.strip_prefix("/")?);
+                if self.mount_point_of(&inner) != Some(mount_point) {
+                    continue; // shadowed by a nested mount
+                }
+                let entry_type = if backend.dyn_is_symlink(path)? {
+                    EntryType::Symlink
+                } else if backend.dyn_is_dir(path)? {
+                    EntryType::Directory
+                } else {
+                    EntryType::File
+                };
+                entries.entry(inner).or_insert(entry_type);
+            }
+        }
+        self.entries = entries;
+        Ok(())
+    }
+
+    /// Converts `path` into an inner absolute path with symbolic links resolved
+    /// (see `utils::resolve()`).
+    fn resolve_links<P: AsRef<Path>>(&self, path: P, follow_last: bool) -> Result<PathBuf> {
+        utils::resolve(&self.to_inner(path), follow_last, |inner| {
+            if self.entries.get(inner) != Some(&EntryType::Symlink) {
+                return Ok(None);
+            }
+            self.link_at(inner).map(Some)
+        })
+    }
+
+    /// Checks if the resolved `inner_path` is indexed (a link is not followed).
+    fn is_live(&self, inner_path: &Path) -> bool {
+        utils::is_virtual_root(inner_path) || self.entries.contains_key(inner_path)
+    }
+
+    /// Returns the target of the link at the resolved `inner_path`; an absolute target of
+    /// the backend is prefixed with its mount point.
+    fn link_at(&self, inner_path: &Path) -> Result<PathBuf> {
+        let (mount_point, backend_path) = self.resolve(inner_path)?;
+        let target = self.backend(&mount_point).dyn_read_link(&backend_path)?;
+        if target.is_absolute() {
+            Ok(mount_point.join(target.strip_prefix("/")?))
+        } else {
+            Ok(target)
+        }
+    }
+
+    /// Returns the inner path of an existing file.
+    fn existing_file<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let inner = self.resolve_links(path, true)?;
+        if self.is_dir(&inner)? {
+            // checks for existent too
+            return Err(VfsError::IsADirectory(inner));
+        }
+        Ok(inner)
+    }
+
+    /// Adds `inner_path` with all its parents into the index.
+    fn index(&mut self, inner_path: &Path, entry_type: EntryType) {
+        for ancestor in inner_path.ancestors().skip(1) {
+            if utils::is_virtual_root(ancestor) {
+                break;
+            }
+            self.entries
+                .entry(ancestor.to_path_buf())
+                .or_insert(EntryType::Directory);
+        }
+        self.entries.insert(inner_path.to_path_buf(), entry_type);
+    }
+}
+
+impl Default for MountFS {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FsBackend for MountFS {
+    /// Returns `/`: `MountFS` is not anchored to a single host path.
+    fn root(&self) -> &Path {
+        self.root.as_path()
+    }
+
+    /// Returns current working directory related to the vfs root.
+    fn cwd(&self) -> &Path {
+        self.cwd.as_path()
+    }
+
+    /// Returns the path on the host system of the backend which owns `inner_path`.
+    fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+        let (mount_point, backend_path) = self.resolve(&self.resolve_links(inner_path, true)?)?;
+        self.backend(&mount_point).dyn_to_host(&backend_path)
+    }
+
+    /// Changes the current working directory.
+    /// * `path` can be in relative or absolute form, but in both cases it must exist in VFS.
+    ///
+    /// An error is returned if the specified `path` does not exist.
+    fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let target = self.resolve_links(path, true)?;
+        if !self.is_dir(&target)? {
+            return Err(VfsError::NotADirectory(target));
+        }
+        self.cwd = target;
+        Ok(())
+    }
+
+    /// Checks if a `path` exists in the VFS.
+    /// The `path` can be in relative or absolute form.
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.resolve_links(path, true)
+            .is_ok_and(|inner| self.is_live(&inner))
+    }
+
+    /// Checks if `path` is a directory.
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve_links(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(utils::is_virtual_root(&inner) || self.entries[&inner] == EntryType::Directory)
+    }
+
+    /// Checks if `path` is a regular file.
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve_links(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(!utils::is_virtual_root(&inner) && self.entries[&inner] == EntryType::File)
+    }
+
+    /// Checks if `path` is a symbolic link.
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve_links(path, false)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(!utils::is_virtual_root(&inner) && self.entries[&inner] == EntryType::Symlink)
+    }
+
+    /// Returns metadata of `path` from the backend owning it.
+    /// Synthetic directories (mount points' parents) have no timestamps.
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        let inner = self.resolve_links(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        let synthetic = Metadata::new(EntryType::Directory, 0);
+        let Ok((mount_point, backend_path)) = self.resolve(&inner) else {
+            return Ok(synthetic);
+        };
+        match self.backend(&mount_point).dyn_metadata(&backend_path) {
+            Err(VfsError::NotFound(_)) if self.is_dir(&inner)? => Ok(synthetic),
+            result => result,
+        }
+    }
+
+    /// Sets the permission bits of `path` in the owning backend. Directories above mount
+    /// points are synthetic, so they have no permissions to set.
+    fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        let inner = self.resolve_links(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        let (mount_point, backend_path) = self.resolve(&inner)?;
+        self.backend_mut(&mount_point)
+            .dyn_set_permissions(&backend_path, mode)
+    }
+
+    /// Returns an iterator over directory entries at a specific depth (shallow listing).
+    /// Mount points are listed as directories. See `MapFS::ls()` for details.
+    fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        let inner_path = self.resolve_links(path, true)?;
+        let is_file = self.is_file(&inner_path)?; // checks for existent too
+        let component_count = if is_file {
+            inner_path.components().count()
+        } else {
+            inner_path.components().count() + 1
+        };
+        Ok(self
+            .entries
+            .keys()
+            .map(|pb| pb.as_path())
+            .filter(move |&path| {
+                path.starts_with(&inner_path)
+                    && (path != inner_path || is_file)
+                    && path.components().count() == component_count
+            }))
+    }
+
+    /// Returns a recursive iterator over the directory tree starting from a given path,
+    /// spanning all backends mounted below it. See `MapFS::tree()` for details.
+    fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        let inner_path = self.resolve_links(path, true)?;
+        let is_file = self.is_file(&inner_path)?; // checks for existent too
+        Ok(self
+            .entries
+            .keys()
+            .map(|pb| pb.as_path())
+            .filter(move |&path| path.starts_with(&inner_path) && (path != inner_path || is_file)))
+    }
+
+    /// Creates directory and all it parents (if needed) in the owning backend.
+    /// * `path` - inner vfs path.
+    fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if path.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let inner_path = self.resolve_links(path, false)?;
+        if self.is_live(&inner_path) {
+            return Err(VfsError::AlreadyExists(inner_path));
+        }
+        let (mount_point, backend_path) = self.resolve(&inner_path)?;
+        self.backend_mut(&mount_point).dyn_mkdir(&backend_path)?;
+        self.index(&inner_path, EntryType::Directory);
+        Ok(())
+    }
+
+    /// Creates new file in the owning backend.
+    /// * `file_path` must be inner VFS path. It must contain the name of the file,
+    ///   optionally preceded by parent directory.
+    ///
+    /// If the parent directory does not exist, it will be created.
+    fn mkfile<P: AsRef<Path>>(&mut self, file_path: P, content: Option<&[u8]>) -> Result<()> {
+        let file_path = self.resolve_links(file_path, false)?;
+        if self.is_live(&file_path) {
+            return Err(VfsError::AlreadyExists(file_path));
+        }
+        let (mount_point, backend_path) = self.resolve(&file_path)?;
+        self.backend_mut(&mount_point)
+            .dyn_mkfile(&backend_path, content)?;
+        self.index(&file_path, EntryType::File);
+        Ok(())
+    }
+
+    /// Reads the entire contents of a file into a byte vector.
+    /// * `path` is the inner VFS path.
+    fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        let inner = self.existing_file(path)?;
+        let (mount_point, backend_path) = self.resolve(&inner)?;
+        self.backend(&mount_point).dyn_read(&backend_path)
+    }
+
+    /// Writes bytes to an existing file, replacing its entire contents.
+    fn write<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let inner = self.existing_file(path)?;
+        let (mount_point, backend_path) = self.resolve(&inner)?;
+        self.backend_mut(&mount_point)
+            .dyn_write(&backend_path, content)
+    }
+
+    /// Appends bytes to the end of an existing file, preserving its old contents.
+    fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let inner = self.existing_file(path)?;
+        let (mount_point, backend_path) = self.resolve(&inner)?;
+        self.backend_mut(&mount_point)
+            .dyn_append(&backend_path, content)
+    }
+
+    /// Opens a file with the given `options` and returns the handle of the owning backend.
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<impl VfsFile + '_> {
+        let inner = prepare_open(self, path, options)?; // creates and truncates through `MountFS`
+        let (mount_point, backend_path) = self.resolve(&inner)?;
+        let mut options = options.clone();
+        options.create(false).create_new(false).truncate(false);
+        self.backend_mut(&mount_point)
+            .dyn_open(&backend_path, &options)
+    }
+
+    /// Moves a file or a whole directory subtree from `from` to `to` within one backend.
+    ///
+    /// An error is returned if `from` and `to` belong to different backends, or if `from`
+    /// is a mount point or contains one.
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        let (from, to) = utils::check_transfer(self, from, to)?;
+        if self.mounts.keys().any(|mp| mp.starts_with(&from)) {
+            return Err(VfsError::invalid_path(
+                from,
+                "the path is or contains a mount point",
+            ));
+        }
+        let (mount_point, backend_from) = self.resolve(&from)?;
+        let (to_mount_point, backend_to) = self.resolve(&to)?;
+        if mount_point != to_mount_point {
+            return Err(VfsError::invalid_path(
+                to,
+                "cannot rename across mount points",
+            ));
+        }
+        self.backend_mut(&mount_point)
+            .dyn_rename(&backend_from, &backend_to)?;
+
+        let moved: Vec<(PathBuf, EntryType)> = self
+            .entries
+            .iter()
+            .filter(|(path, _)| path.starts_with(&from))
+            .map(|(path, entry_type)| (path.clone(), *entry_type))
+            .collect();
+        self.entries.retain(|path, _| !path.starts_with(&from));
+        for (path, entry_type) in moved {
+            self.index(&utils::rebase(&path, &from, &to), entry_type);
+        }
+        self.cwd = utils::rebase(&self.cwd, &from, &to);
+
+        Ok(())
+    }
+
+    /// Creates a symbolic link at `link` pointing to `target` in the owning backend.
+    ///
+    /// An error is returned if `target` belongs to another backend. A relative `target` is
+    /// stored as is.
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, target: P, link: Q) -> Result<()> {
+        if link.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let target = target.as_ref();
+        let link = self.resolve_links(link, false)?;
+        if self.is_live(&link) {
+            return Err(VfsError::AlreadyExists(link));
+        }
+        let parent = link.parent().unwrap(); // safe unwrap(): the root always exists
+        let resolved = utils::link_target(parent, target)?;
+        let (mount_point, backend_link) = self.resolve(&link)?;
+        if self.mount_point_of(&resolved) != Some(mount_point.as_path()) {
+            return Err(VfsError::invalid_path(
+                target,
+                "cannot link across mount points",
+            ));
+        }
+        let backend_target = if target.is_absolute() {
+            Path::new("/").join(resolved.strip_prefix(&mount_point)?)
+        } else {
+            target.to_path_buf()
+        };
+        self.backend_mut(&mount_point)
+            .dyn_symlink(&backend_target, &backend_link)?;
+        self.index(&link, EntryType::Symlink);
+        Ok(())
+    }
+
+    /// Returns the target of the symbolic link at `path`.
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let inner = self.resolve_links(path, false)?;
+        if !self.is_symlink(&inner)? {
+            return Err(VfsError::invalid_path(inner, "not a symbolic link"));
+        }
+        self.link_at(&inner)
+    }
+
+    /// Creates a hard link `new` to the file `existing` in the owning backend.
+    ///
+    /// An error is returned if `existing` and `new` belong to different backends.
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, existing: P, new: Q) -> Result<()> {
+        if new.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let existing = self.resolve_links(existing, true)?;
+        if self.is_dir(&existing)? {
+            // checks for existent too
+            return Err(VfsError::IsADirectory(existing));
+        }
+        let new = self.resolve_links(new, false)?;
+        if self.is_live(&new) {
+            return Err(VfsError::AlreadyExists(new));
+        }
+        let (mount_point, backend_new) = self.resolve(&new)?;
+        let (existing_mount_point, backend_existing) = self.resolve(&existing)?;
+        if existing_mount_point != mount_point {
+            return Err(VfsError::invalid_path(
+                existing,
+                "cannot link across mount points",
+            ));
+        }
+        self.backend_mut(&mount_point)
+            .dyn_hard_link(&backend_existing, &backend_new)?;
+        self.index(&new, EntryType::File);
+        Ok(())
+    }
+
+    /// Removes a file or directory at the specified path.
+    ///
+    /// - `path`: can be absolute (starting with '/') or relative to the current working
+    ///   directory (cwd). If the path is a directory, all its contents are removed recursively.
+    ///
+    /// An error is returned if `path` is a mount point or contains one.
+    fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if path.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        if utils::is_virtual_root(&path) {
+            return Err(VfsError::RootProtected(PathBuf::from("/")));
+        }
+        let inner_path = self.resolve_links(path, false)?;
+        if !self.is_live(&inner_path) {
+            return Err(VfsError::NotFound(inner_path));
+        }
+        if self.mounts.keys().any(|mp| mp.starts_with(&inner_path)) {
+            return Err(VfsError::invalid_path(
+                inner_path,
+                "the path is or contains a mount point",
+            ));
+        }
+
+        let (mount_point, backend_path) = self.resolve(&inner_path)?;
+        self.backend_mut(&mount_point).dyn_rm(&backend_path)?;
+        self.entries
+            .retain(|path, _| !path.starts_with(&inner_path));
+
+        Ok(())
+    }
+
+    /// Cleans up all mounted backends (see their `cleanup()`).
+    fn cleanup(&mut self) -> bool {
+        let mut is_cleaned = true;
+        for backend in self.mounts.values_mut() {
+            is_cleaned &= backend.dyn_cleanup(); // every backend is cleaned up anyway
+        }
+        is_cleaned && self.reindex().is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirFS, MapFS};
+    use tempdir::TempDir;
+
+    fn create_map_fs(files: &[&str]) -> MapFS {
+        let mut fs = MapFS::new();
+        for file in files {
+            fs.mkfile(file, Some(file.as_bytes())).unwrap();
+        }
+        fs
+    }
+
+    mod mounts {
+        use super::*;
+
+        #[test]
+        fn test_mount_points_are_listed() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/data", create_map_fs(&["/a.txt"]))?;
+            fs.mount("/mnt/tmp", MapFS::new())?;
+
+            let root: Vec<_> = fs.ls("/")?.collect();
+            assert_eq!(root, vec![Path::new("/data"), Path::new("/mnt")]);
+            assert!(fs.is_dir("/mnt/tmp")?);
+            assert_eq!(fs.read("/data/a.txt")?, b"/a.txt");
+            assert_eq!(
+                fs.mount_points().collect::<Vec<_>>(),
+                vec![Path::new("/data"), Path::new("/mnt/tmp")]
+            );
+
+            assert!(fs.mount("/data", MapFS::new()).is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_longest_prefix_wins() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/", create_map_fs(&["/data/hidden.txt", "/top.txt"]))?;
+            fs.mount("/data", create_map_fs(&["/visible.txt"]))?;
+
+            assert!(fs.exists("/top.txt"));
+            assert!(fs.exists("/data/visible.txt"));
+            assert!(!fs.exists("/data/hidden.txt"));
+
+            fs.mkfile("/data/new.txt", None)?;
+            fs.mkfile("/new.txt", None)?;
+            fs.umount("/data")?;
+
+            assert!(!fs.exists("/data/new.txt"));
+            assert!(fs.exists("/data/hidden.txt"));
+            assert!(fs.exists("/new.txt"));
+            assert!(fs.umount("/data").is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_umount_resets_cwd() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/tmp", create_map_fs(&["/dir/a.txt"]))?;
+            fs.cd("/tmp/dir")?;
+
+            fs.umount("/tmp")?;
+
+            assert_eq!(fs.cwd(), Path::new("/"));
+            assert_eq!(fs.tree("/")?.count(), 0);
+
+            Ok(())
+        }
+    }
+
+    mod operations {
+        use super::*;
+
+        #[test]
+        fn test_routing_across_backends() -> Result<()> {
+            let temp_dir = TempDir::new("mountfs_test")?;
+            let mut fs = MountFS::new();
+            fs.mount("/data", DirFS::new(temp_dir.path())?)?;
+            fs.mount("/tmp", MapFS::new())?;
+
+            fs.mkfile("/data/disk.txt", Some(b"disk"))?;
+            fs.mkfile("/tmp/mem.txt", Some(b"mem"))?;
+            fs.append("/data/disk.txt", b"!")?;
+            fs.write("/tmp/mem.txt", b"memory")?;
+
+            assert_eq!(std::fs::read(temp_dir.path().join("disk.txt"))?, b"disk!");
+            assert_eq!(fs.read("/tmp/mem.txt")?, b"memory");
+            assert_eq!(
+                fs.to_host("/data/disk.txt")?,
+                temp_dir.path().join("disk.txt")
+            );
+
+            let tree: Vec<_> = fs.tree("/")?.collect();
+            assert_eq!(
+                tree,
+                vec![
+                    Path::new("/data"),
+                    Path::new("/data/disk.txt"),
+                    Path::new("/tmp"),
+                    Path::new("/tmp/mem.txt")
+                ]
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_relative_paths() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/a", MapFS::new())?;
+            fs.mount("/b", MapFS::new())?;
+
+            fs.cd("/a")?;
+            fs.mkdir("sub")?;
+            fs.mkfile("../b/file.txt", Some(b"b"))?;
+            fs.cd("sub")?;
+
+            assert_eq!(fs.cwd(), Path::new("/a/sub"));
+            assert_eq!(fs.read("../../b/file.txt")?, b"b");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_errors() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/mnt/data", create_map_fs(&["/a.txt"]))?;
+
+            assert!(fs.mkfile("/outside.txt", None).is_err()); // no backend at `/`
+            assert!(fs.mkdir("/mnt/other").is_err());
+            assert!(fs.mkdir("/mnt/data").is_err());
+            assert!(fs.rm("/mnt/data").is_err());
+            assert!(fs.rm("/mnt").is_err());
+            assert!(fs.rm("/").is_err());
+            assert!(fs.read("/mnt/data").is_err());
+            assert!(fs.read("/mnt/data/missing").is_err());
+
+            fs.rm("/mnt/data/a.txt")?;
+            assert!(!fs.exists("/mnt/data/a.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rename() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/a", create_map_fs(&["/dir/1.txt"]))?;
+            fs.mount("/b", create_map_fs(&["/2.txt"]))?;
+            fs.mount("/a/nested", MapFS::new())?;
+
+            fs.rename("/a/dir", "/a/moved/dir")?;
+            assert_eq!(fs.read("/a/moved/dir/1.txt")?, b"/dir/1.txt");
+            assert!(!fs.exists("/a/dir"));
+            assert!(fs.is_dir("/a/moved")?);
+
+            assert!(fs.rename("/a/moved", "/b/moved").is_err()); // across mount points
+            assert!(fs.rename("/a/moved", "/a/nested/moved").is_err());
+            assert!(fs.rename("/a/nested", "/a/other").is_err());
+            assert!(fs.rename("/b", "/c").is_err());
+            assert!(fs.exists("/a/moved/dir/1.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_copy_across_mount_points() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/a", create_map_fs(&["/dir/1.txt"]))?;
+            fs.mount("/b", MapFS::new())?;
+
+            fs.copy("/a/dir", "/b/dir")?;
+
+            assert_eq!(fs.read("/b/dir/1.txt")?, b"/dir/1.txt");
+            assert!(fs.exists("/a/dir/1.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_open() -> Result<()> {
+            use crate::OpenOptions;
+            use std::io::{Read, Write};
+
+            let temp_dir = TempDir::new("mountfs_test")?;
+            let mut fs = MountFS::new();
+            fs.mount("/host", DirFS::new(temp_dir.path())?)?;
+
+            let mut file = fs.open("/host/a.txt", OpenOptions::new().write(true).create(true))?;
+            file.write_all(b"streamed")?;
+            drop(file);
+            assert!(fs.is_file("/host/a.txt")?);
+            assert_eq!(std::fs::read(temp_dir.path().join("a.txt"))?, b"streamed");
+
+            let mut content = String::new();
+            fs.open("/host/a.txt", OpenOptions::new().read(true))?
+                .read_to_string(&mut content)?;
+            assert_eq!(content, "streamed");
+            assert!(fs.open("/host", OpenOptions::new().read(true)).is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_metadata() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/mnt/data", create_map_fs(&["/a.txt"]))?;
+
+            let meta = fs.metadata("/mnt/data/a.txt")?;
+            assert!(meta.is_file());
+            assert_eq!(meta.len(), 6);
+            assert!(meta.modified().is_some());
+
+            let meta = fs.metadata("/mnt")?; // synthetic directory
+            assert!(meta.is_dir());
+            assert_eq!(meta.modified(), None);
+            assert!(fs.metadata("/mnt/data")?.is_dir());
+            assert!(fs.metadata("/")?.is_dir());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_cleanup() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/a", create_map_fs(&["/1.txt"]))?;
+            fs.mount("/b", create_map_fs(&["/2.txt"]))?;
+
+            assert!(fs.cleanup());
+
+            let tree: Vec<_> = fs.tree("/")?.collect();
+            assert_eq!(tree, vec![Path::new("/a"), Path::new("/b")]);
+
+            Ok(())
+        }
+    }
+
+    mod symlinks {
+        use super::*;
+
+        #[test]
+        fn test_links_inside_mount() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/data", create_map_fs(&["/a.txt"]))?;
+            fs.mount("/tmp", MapFS::new())?;
+
+            fs.symlink("/data/a.txt", "/data/dir/abs")?;
+            fs.symlink("../a.txt", "/data/dir/rel")?;
+
+            assert_eq!(fs.read("/data/dir/abs")?, b"/a.txt");
+            assert_eq!(fs.read_link("/data/dir/abs")?, Path::new("/data/a.txt"));
+            assert_eq!(fs.read("/data/dir/rel")?, b"/a.txt");
+            assert!(fs.is_symlink("/data/dir/rel")?);
+
+            // the backend stores the target relative to its own root
+            let (_, backend) = fs.mounts.iter().next().unwrap();
+            assert_eq!(
+                backend.dyn_read_link(Path::new("/dir/abs"))?,
+                Path::new("/a.txt")
+            );
+
+            for (target, link) in [("/data/a.txt", "/tmp/abs"), ("../data/a.txt", "/tmp/rel")] {
+                assert!(matches!(
+                    fs.symlink(target, link),
+                    Err(VfsError::InvalidPath { .. })
+                ));
+            }
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_hard_links_inside_mount() -> Result<()> {
+            let mut fs = MountFS::new();
+            fs.mount("/data", create_map_fs(&["/a.txt"]))?;
+            fs.mount("/tmp", MapFS::new())?;
+
+            fs.hard_link("/data/a.txt", "/data/b.txt")?;
+            fs.write("/data/b.txt", b"shared")?;
+            assert_eq!(fs.read("/data/a.txt")?, b"shared");
+
+            assert!(matches!(
+                fs.hard_link("/data/a.txt", "/tmp/a.txt"),
+                Err(VfsError::InvalidPath { .. })
+            ));
+            assert!(!fs.exists("/tmp/a.txt"));
+
+            Ok(())
+        }
+    }
+}
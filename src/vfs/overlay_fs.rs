@@ -0,0 +1,956 @@
+//! This module provides a virtual filesystem (VFS) implementation that combines a read-only lower
+//! layer with a writable upper layer (like Linux overlayfs does).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::VfsError;
+use crate::core::{FsBackend, Result, utils};
+use crate::vfs::file::BufferedFile;
+use crate::{EntryType, Metadata, OpenOptions, VfsFile};
+
+/// A virtual file system (VFS) implementation that layers a writable `upper` backend over
+/// a read-only `lower` backend.
+///
+/// ### Semantics
+///
+/// - **Reads** are served by the upper layer if it has the entry, otherwise they fall through
+///   to the lower layer.
+/// - **Writes** always go to the upper layer. Writing to a file that exists only in the lower
+///   layer copies it up first, together with its permission bits (the owner is not copied).
+/// - **Removal** of an entry of the lower layer records a *whiteout* for it (and for all its
+///   descendants), so it disappears from `exists()`, `ls()`, `tree()`, etc. The lower layer
+///   itself is never modified.
+/// - An entry of the upper layer hides an entry of the lower layer with the same path, also if
+///   their types differ.
+/// - **Symbolic links** are resolved in the merged view, the target of a link is read from the
+///   layer which serves it. New links are created in the upper layer.
+///
+/// On creation, the trees of both layers (as returned by `tree("/")`) are merged into an index;
+/// all paths of `OverlayFS` are inner paths shared by both layers.
+///
+/// ### Usage notes:
+/// - **Beware of `DirFS` as the lower layer**: `DirFS` removes everything it tracks (including
+///   artifacts registered with `add()`) on drop. Call `set_auto_clean(false)` on it to keep
+///   golden data on the host intact.
+/// - `cleanup()` cleans up the upper layer and forgets all whiteouts, which brings back
+///   the pristine lower tree.
+/// - Not thread‑safe by itself (share it between threads with `SharedFs`).
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{DirFS, FsBackend, MapFS, OverlayFS};
+///
+/// let mut golden = DirFS::new("/path/to/golden").unwrap();
+/// golden.set_auto_clean(false); // never touch golden files
+/// golden.add("/").unwrap();
+///
+/// let mut fs = OverlayFS::new(golden, MapFS::new()).unwrap();
+/// fs.write("/config.toml", b"patched = true").unwrap(); // copied up into memory
+/// fs.rm("/cache").unwrap();                             // whiteout, nothing is removed
+/// ```
+pub struct OverlayFS<L: FsBackend, U: FsBackend> {
+    lower: L,
+    upper: U,
+    cwd: PathBuf,                          // inner absolute normalized path
+    entries: BTreeMap<PathBuf, EntryType>, // merged view, inner absolute normalized paths
+    whiteouts: BTreeSet<PathBuf>,          // hidden paths of the lower layer
+}
+
+impl<L: FsBackend, U: FsBackend> OverlayFS<L, U> {
+    /// Creates new OverlayFS with `lower` as the read-only layer and `upper` as the writable one.
+    pub fn new(lower: L, upper: U) -> Result<Self> {
+        let mut fs = Self {
+            lower,
+            upper,
+            cwd: PathBuf::from("/"),
+            entries: BTreeMap::new(),
+            whiteouts: BTreeSet::new(),
+        };
+        fs.reindex()?;
+        Ok(fs)
+    }
+
+    /// Returns a reference to the read-only lower layer.
+    pub fn lower(&self) -> &L {
+        &self.lower
+    }
+
+    /// Returns a reference to the writable upper layer.
+    pub fn upper(&self) -> &U {
+        &self.upper
+    }
+
+    /// Consumes OverlayFS returning its lower and upper layers.
+    pub fn into_layers(self) -> (L, U) {
+        (self.lower, self.upper)
+    }
+
+    /// Returns an iterator over paths of the lower layer hidden by whiteouts.
+    pub fn whiteouts(&self) -> impl Iterator<Item = &Path> {
+        self.whiteouts.iter().map(|pb| pb.as_path())
+    }
+
+    /// Rebuilds the merged index from the trees of both layers.
+    fn reindex(&mut self) -> Result<()> {
+        let mut entries = BTreeMap::new();
+        for path in self.lower.tree("/")? {
+            if !self.whiteouts.contains(path) {
+                entries.insert(path.to_path_buf(), Self::entry_type(&self.lower, path)?);
+            }
+        }
+        for path in self.upper.tree("/")? {
+            let entry_type = Self::entry_type(&self.upper, path)?;
+            if entry_type != EntryType::Directory {
+                // an upper file (or link) hides a lower directory with all its contents
+                entries.retain(|p: &PathBuf, _| !p.starts_with(path));
+            }
+            entries.insert(path.to_path_buf(), entry_type);
+        }
+        self.entries = entries;
+        Ok(())
+    }
+
+    fn entry_type<B: FsBackend>(fs: &B, path: &Path) -> Result<EntryType> {
+        Ok(if fs.is_symlink(path)? {
+            EntryType::Symlink
+        } else if fs.is_dir(path)? {
+            EntryType::Directory
+        } else {
+            EntryType::File
+        })
+    }
+
+    fn to_inner<P: AsRef<Path>>(&self, inner_path: P) -> PathBuf {
+        utils::normalize(self.cwd.join(inner_path))
+    }
+
+    /// Converts `path` into an inner absolute path with symbolic links of the merged view
+    /// resolved (see `utils::resolve()`).
+    fn resolve<P: AsRef<Path>>(&self, path: P, follow_last: bool) -> Result<PathBuf> {
+        utils::resolve(&self.to_inner(path), follow_last, |inner| {
+            if self.entries.get(inner) != Some(&EntryType::Symlink) {
+                return Ok(None);
+            }
+            self.link_at(inner).map(Some)
+        })
+    }
+
+    /// Checks if the resolved `inner_path` is in the merged view (a link is not followed).
+    fn is_live(&self, inner_path: &Path) -> bool {
+        utils::is_virtual_root(inner_path) || self.entries.contains_key(inner_path)
+    }
+
+    /// Returns the target of the link at the resolved `inner_path` from the layer serving it.
+    fn link_at(&self, inner_path: &Path) -> Result<PathBuf> {
+        if self.is_in_lower(inner_path) {
+            self.lower.read_link(inner_path)
+        } else {
+            self.upper.read_link(inner_path)
+        }
+    }
+
+    /// Checks if `inner_path` is served by the lower layer.
+    fn is_in_lower(&self, inner_path: &Path) -> bool {
+        !utils::lexists(&self.upper, inner_path)
+            && !self.whiteouts.contains(inner_path)
+            && utils::lexists(&self.lower, inner_path)
+    }
+
+    /// Hides `inner_path` of the lower layer with all its descendants (a link is hidden
+    /// itself, its target is kept).
+    fn whiteout(&mut self, inner_path: &Path) -> Result<()> {
+        if !utils::lexists(&self.lower, inner_path) {
+            return Ok(());
+        }
+        self.whiteouts.insert(inner_path.to_path_buf());
+        if !self.lower.is_symlink(inner_path)? {
+            self.whiteouts
+                .extend(self.lower.tree(inner_path)?.map(Path::to_path_buf));
+        }
+        Ok(())
+    }
+
+    /// Returns the inner path of an existing file.
+    fn existing_file<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let inner = self.resolve(path, true)?;
+        if self.is_dir(&inner)? {
+            // checks for existent too
+            return Err(VfsError::IsADirectory(inner));
+        }
+        Ok(inner)
+    }
+
+    /// Copies the file of the lower layer at `inner_path` up into the upper layer together
+    /// with its permission bits (the owner is not carried over, `FsBackend` cannot set it).
+    fn copy_up(&mut self, inner_path: &Path) -> Result<()> {
+        let mode = self.lower.metadata(inner_path)?.mode();
+        self.upper
+            .mkfile(inner_path, Some(&self.lower.read(inner_path)?))?;
+        if let Some(mode) = mode {
+            self.upper.set_permissions(inner_path, mode)?;
+        }
+        Ok(())
+    }
+
+    /// Adds all parents of `inner_path` as directories into the index.
+    fn index_parents(&mut self, inner_path: &Path) {
+        for ancestor in inner_path.ancestors().skip(1) {
+            if utils::is_virtual_root(ancestor) {
+                break;
+            }
+            self.entries
+                .entry(ancestor.to_path_buf())
+                .or_insert(EntryType::Directory);
+        }
+    }
+}
+
+impl<L: FsBackend, U: FsBackend> FsBackend for OverlayFS<L, U> {
+    /// Returns root path of the upper (writable) layer.
+    fn root(&self) -> &Path {
+        self.upper.root()
+    }
+
+    /// Returns current working directory related to the vfs root.
+    fn cwd(&self) -> &Path {
+        self.cwd.as_path()
+    }
+
+    /// Returns the path on the host system of the layer which serves `inner_path`.
+    fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+        let inner = self.resolve(inner_path, true)?;
+        if self.is_in_lower(&inner) {
+            self.lower.to_host(&inner)
+        } else {
+            self.upper.to_host(&inner)
+        }
+    }
+
+    /// Changes the current working directory.
+    /// * `path` can be in relative or absolute form, but in both cases it must exist in VFS.
+    ///
+    /// An error is returned if the specified `path` does not exist.
+    fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let target = self.resolve(path, true)?;
+        if !self.is_dir(&target)? {
+            return Err(VfsError::NotADirectory(target));
+        }
+        self.cwd = target;
+        Ok(())
+    }
+
+    /// Checks if a `path` exists in the merged view.
+    /// The `path` can be in relative or absolute form.
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.resolve(path, true)
+            .is_ok_and(|inner| self.is_live(&inner))
+    }
+
+    /// Checks if `path` is a directory.
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(utils::is_virtual_root(&inner) || self.entries[&inner] == EntryType::Directory)
+    }
+
+    /// Checks if `path` is a regular file.
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(!utils::is_virtual_root(&inner) && self.entries[&inner] == EntryType::File)
+    }
+
+    /// Checks if `path` is a symbolic link.
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let inner = self.resolve(path, false)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        Ok(!utils::is_virtual_root(&inner) && self.entries[&inner] == EntryType::Symlink)
+    }
+
+    /// Returns metadata of `path` from the layer which serves it.
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        if self.is_in_lower(&inner) {
+            self.lower.metadata(&inner)
+        } else {
+            self.upper.metadata(&inner)
+        }
+    }
+
+    /// Sets the permission bits in the upper layer. An entry of the lower layer is copied up
+    /// first (a directory without its contents, which stay merged from the lower layer).
+    fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        let inner = self.resolve(path, true)?;
+        if !self.is_live(&inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        if self.is_in_lower(&inner) {
+            if self.lower.is_dir(&inner)? {
+                self.upper.mkdir(&inner)?;
+            } else {
+                self.copy_up(&inner)?;
+            }
+        }
+        self.upper.set_permissions(&inner, mode)
+    }
+
+    /// Returns an iterator over directory entries of the merged view at a specific depth
+    /// (shallow listing). See `MapFS::ls()` for details.
+    fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        let inner_path = self.resolve(path, true)?;
+        let is_file = self.is_file(&inner_path)?; // checks for existent too
+        let component_count = if is_file {
+            inner_path.components().count()
+        } else {
+            inner_path.components().count() + 1
+        };
+        Ok(self
+            .entries
+            .keys()
+            .map(|pb| pb.as_path())
+            .filter(move |&path| {
+                path.starts_with(&inner_path)
+                    && (path != inner_path || is_file)
+                    && path.components().count() == component_count
+            }))
+    }
+
+    /// Returns a recursive iterator over the merged directory tree starting from a given path.
+    /// See `MapFS::tree()` for details.
+    fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        let inner_path = self.resolve(path, true)?;
+        let is_file = self.is_file(&inner_path)?; // checks for existent too
+        Ok(self
+            .entries
+            .keys()
+            .map(|pb| pb.as_path())
+            .filter(move |&path| path.starts_with(&inner_path) && (path != inner_path || is_file)))
+    }
+
+    /// Creates directory and all it parents (if needed) in the upper layer.
+    /// * `path` - inner vfs path.
+    fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if path.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let inner_path = self.resolve(path, false)?;
+        if self.is_live(&inner_path) {
+            return Err(VfsError::AlreadyExists(inner_path));
+        }
+        self.upper.mkdir(&inner_path)?;
+        self.index_parents(&inner_path);
+        self.entries.insert(inner_path, EntryType::Directory);
+        Ok(())
+    }
+
+    /// Creates new file in the upper layer.
+    /// * `file_path` must be inner VFS path. It must contain the name of the file,
+    ///   optionally preceded by parent directory.
+    ///
+    /// If the parent directory does not exist, it will be created.
+    fn mkfile<P: AsRef<Path>>(&mut self, file_path: P, content: Option<&[u8]>) -> Result<()> {
+        let file_path = self.resolve(file_path, false)?;
+        if self.is_live(&file_path) {
+            return Err(VfsError::AlreadyExists(file_path));
+        }
+        self.upper.mkfile(&file_path, content)?;
+        self.index_parents(&file_path);
+        self.entries.insert(file_path, EntryType::File);
+        Ok(())
+    }
+
+    /// Reads the entire contents of a file from the layer which serves it.
+    /// * `path` is the inner VFS path.
+    fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        let inner = self.existing_file(path)?;
+        if self.is_in_lower(&inner) {
+            self.lower.read(&inner)
+        } else {
+            self.upper.read(&inner)
+        }
+    }
+
+    /// Writes bytes to an existing file, replacing its entire contents.
+    /// A file of the lower layer is copied up first (with its permission bits, so the upper
+    /// layer may refuse to write a read-only file).
+    fn write<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let inner = self.existing_file(path)?;
+        if self.is_in_lower(&inner) {
+            self.copy_up(&inner)?;
+        }
+        self.upper.write(&inner, content)
+    }
+
+    /// Appends bytes to the end of an existing file, preserving its old contents.
+    /// A file of the lower layer is copied up first (with its permission bits).
+    fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        let inner = self.existing_file(path)?;
+        if self.is_in_lower(&inner) {
+            self.copy_up(&inner)?;
+        }
+        self.upper.append(&inner, content)
+    }
+
+    /// Opens a file with the given `options`. The handle works on a copy of the contents,
+    /// which is written back on `flush()` or drop (copying a file of the lower layer up).
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<impl VfsFile + '_> {
+        BufferedFile::open(self, path, options)
+    }
+
+    /// Moves a file or a whole directory subtree from `from` to `to`.
+    ///
+    /// If the subtree lives in the upper layer only, it is renamed there. Otherwise, it is copied
+    /// up to `to` (keeping the permission bits) and the lower entries at `from` are whited out.
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        let (from, to) = utils::check_transfer(self, from, to)?;
+        let moved: Vec<(PathBuf, EntryType)> = self
+            .entries
+            .iter()
+            .filter(|(path, _)| path.starts_with(&from))
+            .map(|(path, entry_type)| (path.clone(), *entry_type))
+            .collect();
+
+        if moved.iter().any(|(path, _)| self.is_in_lower(path)) {
+            let mut modes = Vec::new();
+            for (path, entry_type) in &moved {
+                if *entry_type != EntryType::Symlink
+                    && let Some(mode) = self.metadata(path)?.mode()
+                {
+                    modes.push((utils::rebase(path, &from, &to), mode));
+                }
+            }
+            for (path, entry_type) in &moved {
+                let target = utils::rebase(path, &from, &to);
+                match entry_type {
+                    EntryType::Directory if !self.upper.exists(&target) => {
+                        self.upper.mkdir(&target)?
+                    }
+                    EntryType::Directory => {}
+                    EntryType::File => self.upper.mkfile(&target, Some(&self.read(path)?))?,
+                    EntryType::Symlink => self.upper.symlink(self.link_at(path)?, &target)?,
+                }
+            }
+            // children first, a read-only directory must not refuse its contents
+            for (target, mode) in modes.into_iter().rev() {
+                self.upper.set_permissions(&target, mode)?;
+            }
+            if utils::lexists(&self.upper, &from) {
+                self.upper.rm(&from)?;
+            }
+        } else {
+            self.upper.rename(&from, &to)?;
+        }
+        self.whiteout(&from)?;
+
+        self.entries.retain(|path, _| !path.starts_with(&from));
+        self.index_parents(&to);
+        for (path, entry_type) in moved {
+            self.entries
+                .insert(utils::rebase(&path, &from, &to), entry_type);
+        }
+        self.cwd = utils::rebase(&self.cwd, &from, &to);
+
+        Ok(())
+    }
+
+    /// Creates a symbolic link at `link` pointing to `target` in the upper layer.
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, target: P, link: Q) -> Result<()> {
+        if link.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let target = target.as_ref();
+        let link = self.resolve(link, false)?;
+        if self.is_live(&link) {
+            return Err(VfsError::AlreadyExists(link));
+        }
+        let parent = link.parent().unwrap(); // safe unwrap(): the root always exists
+        utils::link_target(parent, target)?;
+        self.upper.symlink(target, &link)?;
+        self.index_parents(&link);
+        self.entries.insert(link, EntryType::Symlink);
+        Ok(())
+    }
+
+    /// Returns the target of the symbolic link at `path` from the layer which serves it.
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let inner = self.resolve(path, false)?;
+        if !self.is_symlink(&inner)? {
+            return Err(VfsError::invalid_path(inner, "not a symbolic link"));
+        }
+        self.link_at(&inner)
+    }
+
+    /// Creates a hard link `new` to the file `existing` in the upper layer.
+    /// A file of the lower layer is copied up first (with its permission bits), so the link
+    /// shares the copy.
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, existing: P, new: Q) -> Result<()> {
+        if new.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let existing = self.existing_file(existing)?;
+        let new = self.resolve(new, false)?;
+        if self.is_live(&new) {
+            return Err(VfsError::AlreadyExists(new));
+        }
+        if self.is_in_lower(&existing) {
+            self.copy_up(&existing)?;
+        }
+        self.upper.hard_link(&existing, &new)?;
+        self.index_parents(&new);
+        self.entries.insert(new, EntryType::File);
+        Ok(())
+    }
+
+    /// Removes a file or directory at the specified path from the merged view.
+    ///
+    /// - `path`: can be absolute (starting with '/') or relative to the current working
+    ///   directory (cwd). If the path is a directory, all its contents are removed recursively.
+    ///
+    /// Entries of the upper layer are removed, entries of the lower layer are whited out.
+    fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if path.as_ref().as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        if utils::is_virtual_root(&path) {
+            return Err(VfsError::RootProtected(PathBuf::from("/")));
+        }
+        let inner_path = self.resolve(path, false)?;
+        if !self.is_live(&inner_path) {
+            return Err(VfsError::NotFound(inner_path));
+        }
+
+        if utils::lexists(&self.upper, &inner_path) {
+            self.upper.rm(&inner_path)?;
+        }
+        self.whiteout(&inner_path)?;
+        self.entries
+            .retain(|path, _| !path.starts_with(&inner_path));
+
+        Ok(())
+    }
+
+    /// Removes all artifacts of the upper layer and forgets all whiteouts.
+    /// The lower layer is left untouched.
+    fn cleanup(&mut self) -> bool {
+        let is_cleaned = self.upper.cleanup();
+        self.whiteouts.clear();
+        is_cleaned && self.reindex().is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirFS, MapFS};
+    use tempdir::TempDir;
+
+    /// Creates a lower layer with the following structure:
+    /// ```text
+    /// /golden/a.txt  "A"
+    /// /golden/sub/b.txt  "B"
+    /// /readme.md  "readme"
+    /// ```
+    fn create_lower() -> MapFS {
+        let mut lower = MapFS::new();
+        lower.mkfile("/golden/a.txt", Some(b"A")).unwrap();
+        lower.mkfile("/golden/sub/b.txt", Some(b"B")).unwrap();
+        lower.mkfile("/readme.md", Some(b"readme")).unwrap();
+        lower
+    }
+
+    mod reads {
+        use super::*;
+
+        #[test]
+        fn test_merged_view() -> Result<()> {
+            let mut upper = MapFS::new();
+            upper.mkfile("/golden/new.txt", Some(b"new"))?;
+            upper.mkfile("/readme.md", Some(b"patched"))?;
+
+            let fs = OverlayFS::new(create_lower(), upper)?;
+
+            let listed: Vec<_> = fs.ls("/golden")?.collect();
+            assert_eq!(
+                listed,
+                vec![
+                    Path::new("/golden/a.txt"),
+                    Path::new("/golden/new.txt"),
+                    Path::new("/golden/sub")
+                ]
+            );
+            assert_eq!(fs.read("/golden/a.txt")?, b"A");
+            assert_eq!(fs.read("/readme.md")?, b"patched");
+            assert!(fs.read("/golden").is_err());
+            assert!(fs.read("/missing").is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_upper_file_hides_lower_dir() -> Result<()> {
+            let mut upper = MapFS::new();
+            upper.mkfile("/golden", Some(b"file"))?;
+
+            let fs = OverlayFS::new(create_lower(), upper)?;
+
+            assert!(fs.is_file("/golden")?);
+            assert!(!fs.exists("/golden/a.txt"));
+            assert_eq!(fs.tree("/")?.count(), 2);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_metadata_from_serving_layer() -> Result<()> {
+            let mut upper = MapFS::new();
+            upper.mkfile("/readme.md", Some(b"patched"))?;
+
+            let fs = OverlayFS::new(create_lower(), upper)?;
+
+            assert_eq!(fs.metadata("/golden/a.txt")?.len(), 1);
+            assert_eq!(fs.metadata("/readme.md")?.len(), 7);
+            assert!(fs.metadata("/golden")?.is_dir());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_cd_and_relative_paths() -> Result<()> {
+            let mut fs = OverlayFS::new(create_lower(), MapFS::new())?;
+
+            fs.cd("/golden/sub")?;
+            assert_eq!(fs.cwd(), Path::new("/golden/sub"));
+            assert_eq!(fs.read("b.txt")?, b"B");
+            assert_eq!(fs.read("../a.txt")?, b"A");
+            assert!(fs.cd("b.txt").is_err());
+
+            Ok(())
+        }
+    }
+
+    mod writes {
+        use super::*;
+
+        #[test]
+        fn test_copy_up() -> Result<()> {
+            let mut fs = OverlayFS::new(create_lower(), MapFS::new())?;
+
+            fs.write("/golden/a.txt", b"AA")?;
+            fs.append("/golden/sub/b.txt", b"B")?;
+
+            assert_eq!(fs.read("/golden/a.txt")?, b"AA");
+            assert_eq!(fs.read("/golden/sub/b.txt")?, b"BB");
+            assert_eq!(fs.upper().read("/golden/a.txt")?, b"AA");
+            assert_eq!(fs.upper().read("/golden/sub/b.txt")?, b"BB");
+            assert_eq!(fs.lower().read("/golden/a.txt")?, b"A");
+            assert_eq!(fs.lower().read("/golden/sub/b.txt")?, b"B");
+
+            fs.append("/golden/a.txt", b"A")?;
+            assert_eq!(fs.read("/golden/a.txt")?, b"AAA");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_mkdir_mkfile() -> Result<()> {
+            let mut fs = OverlayFS::new(create_lower(), MapFS::new())?;
+
+            fs.mkdir("/golden/sub/deep")?;
+            fs.mkfile("/out/log.txt", Some(b"log"))?;
+
+            assert!(fs.is_dir("/golden/sub/deep")?);
+            assert!(fs.is_dir("/out")?);
+            assert!(fs.upper().is_dir("/golden/sub/deep")?);
+            assert!(!fs.lower().exists("/out"));
+
+            assert!(fs.mkdir("/golden").is_err());
+            assert!(fs.mkdir("").is_err());
+            assert!(fs.mkfile("/readme.md", None).is_err());
+            assert!(fs.write("/golden", b"").is_err());
+
+            Ok(())
+        }
+    }
+
+    mod open {
+        use super::*;
+        use crate::OpenOptions;
+        use std::io::{Read, Write};
+
+        #[test]
+        fn test_open_copies_up_on_write() -> Result<()> {
+            let mut fs = OverlayFS::new(create_lower(), MapFS::new())?;
+
+            let mut content = String::new();
+            fs.open("/readme.md", OpenOptions::new().read(true))?
+                .read_to_string(&mut content)?;
+            assert_eq!(content, "readme");
+            assert!(!fs.upper().exists("/readme.md"));
+
+            fs.open("/readme.md", OpenOptions::new().append(true))?
+                .write_all(b"!")?;
+            assert_eq!(fs.upper().read("/readme.md")?, b"readme!");
+            assert_eq!(fs.lower().read("/readme.md")?, b"readme");
+
+            Ok(())
+        }
+    }
+
+    mod renames {
+        use super::*;
+
+        #[test]
+        fn test_rename_upper_only() -> Result<()> {
+            let mut fs = OverlayFS::new(create_lower(), MapFS::new())?;
+            fs.mkfile("/new/a.txt", Some(b"new"))?;
+
+            fs.rename("/new", "/renamed")?;
+
+            assert_eq!(fs.read("/renamed/a.txt")?, b"new");
+            assert!(fs.upper().exists("/renamed/a.txt"));
+            assert!(!fs.exists("/new"));
+            assert_eq!(fs.whiteouts().count(), 0);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rename_copies_up_lower() -> Result<()> {
+            let mut upper = MapFS::new();
+            upper.mkfile("/golden/new.txt", Some(b"new"))?;
+            let mut fs = OverlayFS::new(create_lower(), upper)?;
+
+            fs.rename("/golden", "/moved")?;
+
+            assert!(!fs.exists("/golden"));
+            assert_eq!(fs.read("/moved/a.txt")?, b"A");
+            assert_eq!(fs.read("/moved/sub/b.txt")?, b"B");
+            assert_eq!(fs.read("/moved/new.txt")?, b"new");
+            assert!(fs.lower().exists("/golden/a.txt")); // lower is untouched
+            assert!(!fs.upper().exists("/golden"));
+
+            let fs = OverlayFS::new(create_lower(), fs.into_layers().1)?;
+            assert!(fs.exists("/golden/a.txt")); // whiteouts are not persisted
+
+            Ok(())
+        }
+    }
+
+    mod removals {
+        use super::*;
+
+        #[test]
+        fn test_rm_records_whiteouts() -> Result<()> {
+            let mut fs = OverlayFS::new(create_lower(), MapFS::new())?;
+
+            fs.rm("/golden")?;
+
+            assert!(!fs.exists("/golden"));
+            assert!(!fs.exists("/golden/sub/b.txt"));
+            assert_eq!(
+                fs.tree("/")?.collect::<Vec<_>>(),
+                vec![Path::new("/readme.md")]
+            );
+            assert!(fs.lower().exists("/golden/sub/b.txt"));
+            assert_eq!(fs.whiteouts().count(), 4);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_recreate_after_rm() -> Result<()> {
+            let mut fs = OverlayFS::new(create_lower(), MapFS::new())?;
+
+            fs.rm("/golden")?;
+            fs.mkfile("/golden/a.txt", Some(b"again"))?;
+
+            assert_eq!(fs.read("/golden/a.txt")?, b"again");
+            assert!(!fs.exists("/golden/sub")); // still whited out
+
+            fs.rm("/golden/a.txt")?;
+            assert!(!fs.exists("/golden/a.txt")); // lower one stays hidden
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_rm_both_layers() -> Result<()> {
+            let mut fs = OverlayFS::new(create_lower(), MapFS::new())?;
+
+            fs.write("/readme.md", b"patched")?;
+            fs.rm("/readme.md")?;
+
+            assert!(!fs.exists("/readme.md"));
+            assert!(!fs.upper().exists("/readme.md"));
+            assert!(fs.lower().exists("/readme.md"));
+
+            assert!(fs.rm("/").is_err());
+            assert!(fs.rm("/readme.md").is_err());
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_cleanup_restores_lower() -> Result<()> {
+            let mut fs = OverlayFS::new(create_lower(), MapFS::new())?;
+
+            fs.rm("/golden")?;
+            fs.write("/readme.md", b"patched")?;
+            fs.mkfile("/new.txt", None)?;
+
+            assert!(fs.cleanup());
+            assert_eq!(fs.tree("/")?.count(), 5);
+            assert_eq!(fs.read("/readme.md")?, b"readme");
+            assert!(!fs.exists("/new.txt"));
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_golden_dir_fs_untouched() -> Result<()> {
+        let temp_dir = TempDir::new("overlayfs_test")?;
+        let root = temp_dir.path().join("golden");
+        std::fs::create_dir_all(root.join("data"))?;
+        std::fs::write(root.join("data/golden.txt"), b"golden")?;
+
+        {
+            let mut lower = DirFS::new(&root)?;
+            lower.set_auto_clean(false);
+            lower.add("/")?;
+
+            let mut fs = OverlayFS::new(lower, MapFS::new())?;
+            assert_eq!(fs.to_host("/data")?, root.join("data"));
+            fs.write("/data/golden.txt", b"mutated")?;
+            assert_eq!(
+                fs.to_host("/data/golden.txt")?,
+                Path::new("/data/golden.txt")
+            );
+            fs.mkfile("/data/extra.txt", None)?;
+            fs.rm("/data")?;
+            assert!(!fs.exists("/data"));
+        }
+
+        assert_eq!(std::fs::read(root.join("data/golden.txt"))?, b"golden");
+        assert!(!root.join("data/extra.txt").exists());
+
+        Ok(())
+    }
+
+    mod symlinks {
+        use super::*;
+
+        #[test]
+        fn test_links_of_both_layers() -> Result<()> {
+            let mut lower = create_lower();
+            lower.symlink("golden/sub", "/sub")?;
+
+            let mut fs = OverlayFS::new(lower, MapFS::new())?;
+            fs.symlink("/readme.md", "/golden/readme")?;
+
+            assert!(fs.is_symlink("/sub")?);
+            assert_eq!(fs.read("/sub/b.txt")?, b"B");
+            assert_eq!(fs.read("/golden/readme")?, b"readme");
+            assert!(fs.upper().is_symlink("/golden/readme")?);
+
+            // writing through a lower link copies up the target, not the link
+            fs.write("/sub/b.txt", b"BB")?;
+            assert_eq!(fs.upper().read("/golden/sub/b.txt")?, b"BB");
+            assert!(!fs.upper().exists("/sub"));
+
+            fs.rm("/sub")?;
+            assert!(!fs.exists("/sub"));
+            assert!(fs.exists("/golden/sub/b.txt"));
+            assert_eq!(fs.whiteouts().collect::<Vec<_>>(), vec![Path::new("/sub")]);
+
+            Ok(())
+        }
+    }
+
+    mod permissions {
+        use super::*;
+
+        #[test]
+        fn test_lower_entries_are_copied_up() -> Result<()> {
+            let lower = create_lower();
+            let mut fs = OverlayFS::new(lower, MapFS::new())?;
+
+            fs.set_permissions("/readme.md", 0o444)?;
+            fs.set_permissions("/golden", 0o555)?;
+
+            assert_eq!(fs.metadata("/readme.md")?.mode(), Some(0o444));
+            assert_eq!(fs.lower().metadata("/readme.md")?.mode(), Some(0o644));
+            assert!(matches!(
+                fs.write("/readme.md", b""),
+                Err(VfsError::PermissionDenied(_))
+            ));
+            assert_eq!(fs.metadata("/golden")?.mode(), Some(0o555));
+            assert_eq!(fs.read("/golden/a.txt")?, b"A"); // still merged from the lower layer
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_copy_up_keeps_mode() -> Result<()> {
+            let mut lower = create_lower();
+            lower.set_permissions("/readme.md", 0o755)?;
+            lower.set_permissions("/golden/a.txt", 0o444)?;
+            lower.set_permissions("/golden/sub", 0o700)?;
+            let mut fs = OverlayFS::new(lower, MapFS::new())?;
+
+            fs.append("/readme.md", b"!")?;
+            assert_eq!(fs.upper().metadata("/readme.md")?.mode(), Some(0o755));
+            fs.hard_link("/golden/a.txt", "/a.txt")?;
+            assert_eq!(fs.metadata("/a.txt")?.mode(), Some(0o444));
+
+            assert!(matches!(
+                fs.write("/golden/a.txt", b"AA"),
+                Err(VfsError::PermissionDenied(_))
+            ));
+            assert_eq!(fs.read("/golden/a.txt")?, b"A");
+
+            fs.rename("/golden", "/moved")?;
+            assert_eq!(fs.upper().metadata("/moved/a.txt")?.mode(), Some(0o444));
+            assert_eq!(fs.upper().metadata("/moved/sub")?.mode(), Some(0o700));
+            assert_eq!(fs.read("/moved/sub/b.txt")?, b"B");
+
+            Ok(())
+        }
+    }
+
+    mod hard_links {
+        use super::*;
+
+        #[test]
+        fn test_lower_file_is_copied_up() -> Result<()> {
+            let lower = create_lower();
+            let mut fs = OverlayFS::new(lower, MapFS::new())?;
+
+            fs.hard_link("/readme.md", "/docs/readme.md")?;
+            fs.write("/docs/readme.md", b"updated")?;
+
+            assert_eq!(fs.read("/readme.md")?, b"updated");
+            assert_eq!(fs.lower().read("/readme.md")?, b"readme");
+            assert_eq!(
+                fs.upper().inode_id("/readme.md"),
+                fs.upper().inode_id("/docs/readme.md")
+            );
+
+            Ok(())
+        }
+    }
+}
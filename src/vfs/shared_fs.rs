@@ -0,0 +1,511 @@
+//! This module provides a thread‑safe handle sharing one backend between threads, each handle
+//! having its own current working directory.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::VfsError;
+use crate::core::{FsBackend, Result, utils};
+use crate::{Metadata, OpenOptions, VfsFile};
+
+/// A thread‑safe, cloneable handle to a backend shared between threads.
+///
+/// The backend (its entries tree) lives behind an `RwLock`: queries (`exists()`, `read()`,
+/// `ls()`, etc.) take the read lock and run concurrently, modifications take the write lock.
+/// Every clone has its **own** current working directory: `cd()` on one handle never affects
+/// another. Relative paths are resolved against the handle's `cwd` before reaching the backend,
+/// so the backend's own `cwd` is never used or changed.
+///
+/// The methods mirror `FsBackend`, but take `&self` (except `cd()`), since the handle is meant
+/// to be used concurrently. `ls()` and `tree()` return owned paths: the lock is released before
+/// the method returns.
+///
+/// ### Usage notes:
+/// - A panic of another thread while it holds the lock does not make the handle unusable.
+/// - If the directory a handle is in is removed (or renamed) through another handle, relative
+///   paths of the first one fail until it `cd()`s elsewhere.
+/// - File handles returned by `open()` take the lock per operation, so they can be used from
+///   other threads, and do not block the backend between calls.
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{MapFS, SharedFs};
+///
+/// let fs = SharedFs::new(MapFS::new());
+/// fs.mkdir("/jobs/1").unwrap();
+///
+/// let worker = {
+///     let mut fs = fs.clone();
+///     std::thread::spawn(move || {
+///         fs.cd("/jobs/1").unwrap();
+///         fs.mkfile("result.txt", Some(b"done")).unwrap(); // `/jobs/1/result.txt`
+///     })
+/// };
+/// worker.join().unwrap();
+///
+/// assert_eq!(fs.cwd(), "/");
+/// assert_eq!(fs.read("/jobs/1/result.txt").unwrap(), b"done");
+/// ```
+pub struct SharedFs<B> {
+    fs: Arc<RwLock<B>>,
+    root: PathBuf, // root of the backend, which never changes
+    cwd: PathBuf,  // inner absolute normalized path
+}
+
+impl<B: FsBackend> SharedFs<B> {
+    /// Wraps `fs`. The new handle starts in the current working directory of `fs`.
+    pub fn new(fs: B) -> Self {
+        let root = fs.root().to_path_buf();
+        let cwd = fs.cwd().to_path_buf();
+        Self {
+            fs: Arc::new(RwLock::new(fs)),
+            root,
+            cwd,
+        }
+    }
+
+    /// Returns root path of the backend.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns current working directory of this handle.
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// Returns the path on the host system that matches the specified internal path.
+    pub fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+        self.read_lock().to_host(self.to_inner(inner_path))
+    }
+
+    /// Changes the current working directory of this handle only.
+    pub fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let fs = self.read_lock();
+        let target = fs.canonicalize(self.to_inner(path))?;
+        if !fs.is_dir(&target)? {
+            return Err(VfsError::NotADirectory(target));
+        }
+        drop(fs);
+        self.cwd = target;
+        Ok(())
+    }
+
+    /// Checks if a `path` exists.
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.read_lock().exists(self.to_inner(path))
+    }
+
+    /// Checks if `path` is a directory.
+    pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.read_lock().is_dir(self.to_inner(path))
+    }
+
+    /// Checks if `path` is a regular file.
+    pub fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.read_lock().is_file(self.to_inner(path))
+    }
+
+    /// Checks if `path` is a symbolic link.
+    pub fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.read_lock().is_symlink(self.to_inner(path))
+    }
+
+    /// Returns the target of the symbolic link at `path`.
+    pub fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        self.read_lock().read_link(self.to_inner(path))
+    }
+
+    /// Returns the inner absolute path of `path` with all symbolic links resolved.
+    pub fn canonicalize<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        self.read_lock().canonicalize(self.to_inner(path))
+    }
+
+    /// Returns metadata of the file or directory at `path`.
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        self.read_lock().metadata(self.to_inner(path))
+    }
+
+    /// Sets the permission bits of a file or directory (see `FsBackend::set_permissions()`).
+    pub fn set_permissions<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        self.write_lock().set_permissions(self.to_inner(path), mode)
+    }
+
+    /// Returns immediate children of the directory at `path` (see `FsBackend::ls()`).
+    pub fn ls<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>> {
+        let fs = self.read_lock();
+        let paths = fs.ls(self.to_inner(path))?.map(Path::to_path_buf).collect();
+        Ok(paths)
+    }
+
+    /// Returns all entries inside the directory at `path` (see `FsBackend::tree()`).
+    pub fn tree<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>> {
+        let fs = self.read_lock();
+        let paths = fs
+            .tree(self.to_inner(path))?
+            .map(Path::to_path_buf)
+            .collect();
+        Ok(paths)
+    }
+
+    /// Returns all entries matching the glob `pattern` (see `FsBackend::glob()`). A relative
+    /// pattern is resolved against the `cwd` of this handle.
+    pub fn glob(&self, pattern: &str) -> Result<Vec<PathBuf>> {
+        if pattern.is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        // not normalized here: `..` after a `{...}` group must not remove the whole group
+        let pattern = self.cwd.join(pattern);
+        self.read_lock().glob(&pattern.to_string_lossy())
+    }
+
+    /// Creates directory and all it parents (if needed).
+    pub fn mkdir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write_lock().mkdir(self.to_inner(path))
+    }
+
+    /// Creates new file with optional `content`.
+    pub fn mkfile<P: AsRef<Path>>(&self, file_path: P, content: Option<&[u8]>) -> Result<()> {
+        self.write_lock().mkfile(self.to_inner(file_path), content)
+    }
+
+    /// Reads the entire contents of a file into a byte vector.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        self.read_lock().read(self.to_inner(path))
+    }
+
+    /// Writes bytes to an existing file, replacing its entire contents.
+    pub fn write<P: AsRef<Path>>(&self, path: P, content: &[u8]) -> Result<()> {
+        self.write_lock().write(self.to_inner(path), content)
+    }
+
+    /// Appends bytes to the end of an existing file, preserving its old contents.
+    pub fn append<P: AsRef<Path>>(&self, path: P, content: &[u8]) -> Result<()> {
+        self.write_lock().append(self.to_inner(path), content)
+    }
+
+    /// Opens a file with the given `options` (see `FsBackend::open()`).
+    ///
+    /// The returned handle owns a clone of this `SharedFs` and reopens the file in the backend
+    /// (under the write lock) on every read, write or seek, keeping only the current position.
+    pub fn open<P: AsRef<Path>>(&self, path: P, options: &OpenOptions) -> Result<SharedFile<B>> {
+        let path = self.to_inner(path);
+        self.write_lock().open(&path, options).map(drop)?;
+        let mut options = options.clone();
+        options.create(false).create_new(false).truncate(false);
+        Ok(SharedFile {
+            fs: self.clone(),
+            path,
+            pos: 0,
+            options,
+        })
+    }
+
+    /// Moves a file or a whole directory subtree from `from` to `to`.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
+        self.write_lock()
+            .rename(self.to_inner(from), self.to_inner(to))
+    }
+
+    /// Copies a file or a whole directory subtree from `from` to `to`.
+    pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
+        self.write_lock()
+            .copy(self.to_inner(from), self.to_inner(to))
+    }
+
+    /// Creates a symbolic link at `link` pointing to `target` (see `FsBackend::symlink()`).
+    /// A relative `target` is kept relative to the directory of the link, not to the `cwd`.
+    pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&self, target: P, link: Q) -> Result<()> {
+        self.write_lock().symlink(target, self.to_inner(link))
+    }
+
+    /// Creates a hard link `new` to the file `existing` (see `FsBackend::hard_link()`).
+    pub fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&self, existing: P, new: Q) -> Result<()> {
+        self.write_lock()
+            .hard_link(self.to_inner(existing), self.to_inner(new))
+    }
+
+    /// Removes a file or directory at the specified path.
+    pub fn rm<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write_lock().rm(self.to_inner(path))
+    }
+
+    /// Removes all artifacts (dirs and files) in the backend, but preserve its root.
+    pub fn cleanup(&self) -> bool {
+        self.write_lock().cleanup()
+    }
+
+    /// Runs `op` with shared access to the backend (e.g. to call its own inherent methods).
+    pub fn with<T>(&self, op: impl FnOnce(&B) -> T) -> T {
+        op(&self.read_lock())
+    }
+
+    /// Runs `op` with exclusive access to the backend.
+    pub fn with_mut<T>(&self, op: impl FnOnce(&mut B) -> T) -> T {
+        op(&mut self.write_lock())
+    }
+
+    /// Resolves `path` against the handle's `cwd`. An empty path is kept empty, so the backend
+    /// rejects it as usual.
+    fn to_inner<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        if path.as_os_str().is_empty() {
+            return PathBuf::new();
+        }
+        utils::normalize(self.cwd.join(path))
+    }
+
+    // A panic in another thread can only interrupt a single backend call: the lock is taken
+    // over instead of failing every following call.
+    fn read_lock(&self) -> RwLockReadGuard<'_, B> {
+        self.fs.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_lock(&self) -> RwLockWriteGuard<'_, B> {
+        self.fs.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<B> Clone for SharedFs<B> {
+    /// Returns a new handle to the same backend, starting in the same working directory.
+    fn clone(&self) -> Self {
+        Self {
+            fs: Arc::clone(&self.fs),
+            root: self.root.clone(),
+            cwd: self.cwd.clone(),
+        }
+    }
+}
+
+/// A file handle returned by `SharedFs::open()`.
+///
+/// Remembers the position only and performs every read, write or seek on a freshly opened
+/// handle of the backend, so it holds no lock between calls and can be sent to another thread.
+pub struct SharedFile<B> {
+    fs: SharedFs<B>,
+    path: PathBuf, // inner absolute normalized path
+    pos: u64,
+    options: OpenOptions,
+}
+
+impl<B: FsBackend> SharedFile<B> {
+    /// Opens the file at the current position, runs `op` on it and remembers the new position.
+    fn with_file<T>(
+        &mut self,
+        op: impl FnOnce(&mut dyn VfsFile) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut fs = self.fs.write_lock();
+        let mut file = fs.open(&self.path, &self.options)?;
+        file.seek(SeekFrom::Start(self.pos))?;
+        let result = op(&mut file)?;
+        file.flush()?;
+        self.pos = file.stream_position()?;
+        Ok(result)
+    }
+}
+
+impl<B: FsBackend> Read for SharedFile<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.with_file(|file| file.read(buf))
+    }
+}
+
+impl<B: FsBackend> Write for SharedFile<B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.with_file(|file| file.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Every write is flushed immediately
+        Ok(())
+    }
+}
+
+impl<B: FsBackend> Seek for SharedFile<B> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.with_file(|file| file.seek(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirFS, MapFS};
+    use std::thread;
+    use tempdir::TempDir;
+
+    fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+
+    mod handles {
+        use super::*;
+
+        #[test]
+        fn test_shared_fs_is_send_sync_clone() {
+            assert_send_sync_clone::<SharedFs<MapFS>>();
+            assert_send_sync_clone::<SharedFs<DirFS>>();
+        }
+
+        #[test]
+        fn test_cwd_is_per_handle() -> Result<()> {
+            let mut first = SharedFs::new(MapFS::new());
+            first.mkdir("/a")?;
+            first.mkdir("/b")?;
+            let mut second = first.clone();
+
+            first.cd("/a")?;
+            second.cd("/b")?;
+            first.mkfile("f.txt", Some(b"first"))?;
+            second.mkfile("f.txt", Some(b"second"))?;
+
+            assert_eq!(first.cwd(), Path::new("/a"));
+            assert_eq!(second.cwd(), Path::new("/b"));
+            assert_eq!(first.read("/b/f.txt")?, b"second");
+            assert_eq!(second.read("../a/f.txt")?, b"first");
+            assert_eq!(first.ls(".")?, [PathBuf::from("/a/f.txt")]);
+            assert_eq!(first.with(|fs| fs.cwd().to_path_buf()), Path::new("/"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_clone_starts_in_same_cwd() -> Result<()> {
+            let mut fs = SharedFs::new(MapFS::new());
+            fs.mkdir("/a")?;
+            fs.cd("/a")?;
+            let clone = fs.clone();
+            assert_eq!(clone.cwd(), Path::new("/a"));
+            Ok(())
+        }
+
+        #[test]
+        fn test_glob_relative_to_handle_cwd() -> Result<()> {
+            let mut fs = SharedFs::new(MapFS::new());
+            fs.mkfile("/a/x.json", None)?;
+            fs.mkfile("/b/y.json", None)?;
+            fs.cd("/b")?;
+
+            assert_eq!(fs.glob("*.json")?, [PathBuf::from("/b/y.json")]);
+            assert_eq!(fs.glob("../{a,b}/*.json")?.len(), 2);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_errors_are_passed_through() -> Result<()> {
+            let mut fs = SharedFs::new(MapFS::new());
+            fs.mkfile("/f.txt", None)?;
+
+            assert!(matches!(fs.cd("/f.txt"), Err(VfsError::NotADirectory(_))));
+            assert!(matches!(fs.read("/missing"), Err(VfsError::NotFound(_))));
+            assert!(matches!(fs.rm(""), Err(VfsError::InvalidPath { .. })));
+            assert!(matches!(fs.rm("/"), Err(VfsError::RootProtected(_))));
+            assert!(fs.exists("/f.txt"));
+
+            Ok(())
+        }
+    }
+
+    mod concurrency {
+        use super::*;
+
+        #[test]
+        fn test_concurrent_writers_and_readers() -> Result<()> {
+            let fs = SharedFs::new(MapFS::new());
+            fs.mkfile("/log.txt", None)?;
+
+            let handles: Vec<_> = (0..8)
+                .map(|i| {
+                    let mut fs = fs.clone();
+                    thread::spawn(move || -> Result<()> {
+                        let dir = format!("/t{i}");
+                        fs.mkdir(&dir)?;
+                        fs.cd(&dir)?;
+                        for j in 0..10 {
+                            fs.mkfile(format!("{j}.txt"), Some(b"data"))?;
+                            fs.append("/log.txt", b"x")?;
+                            assert!(fs.exists("/log.txt"));
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap()?;
+            }
+
+            assert_eq!(fs.read("/log.txt")?.len(), 80);
+            assert_eq!(fs.tree("/")?.len(), 1 + 8 * 11);
+            assert_eq!(fs.cwd(), Path::new("/"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_dir_fs_shared_between_threads() -> Result<()> {
+            let temp_dir = TempDir::new("shared")?;
+            let fs = SharedFs::new(DirFS::new(temp_dir.path())?);
+
+            let handles: Vec<_> = (0..4)
+                .map(|i| {
+                    let fs = fs.clone();
+                    thread::spawn(move || fs.mkfile(format!("/dir/{i}.txt"), Some(b"data")))
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap()?;
+            }
+
+            assert_eq!(fs.ls("/dir")?.len(), 4);
+            assert!(temp_dir.path().join("dir/3.txt").is_file());
+
+            Ok(())
+        }
+    }
+
+    mod open {
+        use super::*;
+
+        #[test]
+        fn test_open_streams_through_lock() -> Result<()> {
+            let mut fs = SharedFs::new(MapFS::new());
+            fs.mkdir("/a")?;
+            fs.cd("/a")?;
+
+            let options = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .clone();
+            let mut file = fs.open("f.txt", &options)?;
+            file.write_all(b"Hello, world")?;
+            file.seek(SeekFrom::Start(7))?;
+            file.write_all(b"there")?;
+
+            // Other handles see every write immediately
+            assert_eq!(fs.clone().read("/a/f.txt")?, b"Hello, there");
+
+            file.seek(SeekFrom::Start(0))?;
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
+            assert_eq!(content, "Hello, there");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_open_handle_moves_to_another_thread() -> Result<()> {
+            let fs = SharedFs::new(MapFS::new());
+            let options = OpenOptions::new().append(true).create(true).clone();
+            let mut file = fs.open("/log.txt", &options)?;
+
+            thread::spawn(move || file.write_all(b"from thread"))
+                .join()
+                .unwrap()?;
+
+            assert_eq!(fs.read("/log.txt")?, b"from thread");
+
+            Ok(())
+        }
+    }
+}
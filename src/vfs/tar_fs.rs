@@ -0,0 +1,681 @@
+//! This module provides a read-mostly virtual filesystem (VFS) implementation over a tar archive
+//! (optionally gzip-compressed). The archive is indexed into memory on open, so no extraction
+//! to the host is needed. Any `FsBackend` tree can be serialized back into a tar stream.
+//!
+//! Available with the `tar` feature.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use tar::{Archive, Builder, EntryType, Header};
+
+use crate::VfsError;
+use crate::core::{FsBackend, Result, utils};
+use crate::{MapFS, Metadata, OpenOptions, VfsFile};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A virtual file system (VFS) implementation that exposes the contents of a tar archive.
+///
+/// On creation, all directories and regular files of the archive are indexed into an in‑memory
+/// tree (the same inner-path map `MapFS` uses). Gzip compression (`.tar.gz`, `.tgz`) is detected
+/// automatically.
+///
+/// ### Usage notes:
+/// - The archive on the host is never modified. Modifying operations (`mkdir()`, `mkfile()`,
+///   `write()`, ...) change the in‑memory tree only; use `TarFS::pack()` to save it.
+/// - Entries with unsafe names (absolute paths, `..` components) are refused on open.
+/// - Symbolic links are loaded as links; a link pointing outside the archive root is refused
+///   on open. Hard links are loaded as links sharing the contents of their file. Other entry
+///   types (devices, fifos) are skipped.
+/// - Permission bits of the entries are loaded (and enforced, see `MapFS`) and packed back;
+///   owners are not.
+/// - Not thread‑safe by itself (share it between threads with `SharedFs`).
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{FsBackend, MapFS, TarFS};
+///
+/// let fs = TarFS::new("/path/to/release.tar.gz").unwrap();
+/// for path in fs.tree("/").unwrap() {
+///     println!("{}", path.display());
+/// }
+///
+/// // Serialize any backend into a tar stream
+/// let mut mem = MapFS::new();
+/// mem.mkfile("/hello.txt", Some(b"Hello")).unwrap();
+/// let bytes = TarFS::pack(&mem, "/", Vec::new()).unwrap();
+/// ```
+pub struct TarFS {
+    archive: PathBuf, // host-related absolute normalized path
+    state: MapFS,     // indexed tree
+}
+
+impl TarFS {
+    /// Opens the tar archive at `archive` (optionally gzip-compressed) and indexes its contents.
+    /// * `archive` is an absolute host path to an existing archive.
+    ///
+    /// If `archive` is not absolute, does not exist, or is not a valid tar archive, error returns.
+    pub fn new<P: AsRef<Path>>(archive: P) -> Result<Self> {
+        let archive = archive.as_ref();
+
+        if archive.as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        if archive.is_relative() {
+            return Err(VfsError::invalid_path(
+                archive,
+                "the archive path must be absolute",
+            ));
+        }
+        if !archive.is_file() {
+            return Err(VfsError::NotFound(archive.to_path_buf()));
+        }
+
+        let archive = utils::normalize(archive);
+        let mut fs = Self::from_reader(File::open(&archive)?)?;
+        fs.state.set_root(&archive)?;
+        fs.archive = archive;
+        Ok(fs)
+    }
+
+    /// Indexes a tar stream (optionally gzip-compressed) read from `reader`.
+    /// The root path of such VFS is `/`.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let is_gzip = reader.fill_buf()?.starts_with(&GZIP_MAGIC);
+
+        let mut state = MapFS::new();
+        if is_gzip {
+            Self::load(&mut state, GzDecoder::new(reader))?;
+        } else {
+            Self::load(&mut state, reader)?;
+        }
+
+        Ok(Self {
+            archive: PathBuf::from("/"),
+            state,
+        })
+    }
+
+    /// Serializes the tree of `fs` starting from `path` into a tar stream written to `writer`.
+    /// * `path` - a directory (its contents are packed, names are relative to it) or a file
+    ///   (packed under its own name).
+    ///
+    /// Hard links are packed as separate copies of the file.
+    ///
+    /// Returns `writer` back after the archive is finished.
+    pub fn pack<B: FsBackend, P: AsRef<Path>, W: Write>(fs: &B, path: P, writer: W) -> Result<W> {
+        let path = path.as_ref();
+        let mut builder = Builder::new(writer);
+
+        if fs.is_file(path)? {
+            let name = path
+                .file_name()
+                .ok_or_else(|| VfsError::invalid_path(path, "the path has no file name"))?;
+            let mode = Self::mode_of(fs, path)?;
+            Self::append_file(&mut builder, Path::new(name), &fs.read(path)?, mode)?;
+        } else {
+            let base = utils::normalize(fs.cwd().join(path));
+            for inner in fs.tree(&base)? {
+                let name = inner.strip_prefix(&base)?;
+                if fs.is_symlink(inner)? {
+                    let mut header = Header::new_gnu();
+                    header.set_entry_type(EntryType::Symlink);
+                    header.set_mode(0o777);
+                    header.set_size(0);
+                    builder.append_link(&mut header, name, fs.read_link(inner)?)?;
+                } else if fs.is_dir(inner)? {
+                    let mut header = Header::new_gnu();
+                    header.set_entry_type(EntryType::Directory);
+                    header.set_mode(Self::mode_of(fs, inner)?);
+                    header.set_size(0);
+                    builder.append_data(&mut header, name, std::io::empty())?;
+                } else {
+                    let mode = Self::mode_of(fs, inner)?;
+                    Self::append_file(&mut builder, name, &fs.read(inner)?, mode)?;
+                }
+            }
+        }
+
+        Ok(builder.into_inner()?)
+    }
+
+    /// Same as `pack()`, but the tar stream is gzip-compressed.
+    pub fn pack_gz<B: FsBackend, P: AsRef<Path>, W: Write>(
+        fs: &B,
+        path: P,
+        writer: W,
+    ) -> Result<W> {
+        let encoder = Self::pack(fs, path, GzEncoder::new(writer, Compression::default()))?;
+        Ok(encoder.finish()?)
+    }
+
+    fn append_file<W: Write>(
+        builder: &mut Builder<W>,
+        name: &Path,
+        content: &[u8],
+        mode: u32,
+    ) -> Result<()> {
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_mode(mode);
+        header.set_size(content.len() as u64);
+        builder.append_data(&mut header, name, content)?;
+        Ok(())
+    }
+
+    /// Returns the permission bits of `inner` in `fs`, or the default ones of its type if the
+    /// backend does not track them.
+    fn mode_of<B: FsBackend>(fs: &B, inner: &Path) -> Result<u32> {
+        let meta = fs.metadata(inner)?;
+        Ok(meta
+            .mode()
+            .unwrap_or_else(|| meta.entry_type().default_mode()))
+    }
+
+    /// Indexes all directories, regular files, symbolic and hard links of a tar stream into
+    /// `state`. Permission bits are applied at the end, so a read-only directory does not
+    /// prevent loading its contents.
+    fn load<R: Read>(state: &mut MapFS, reader: R) -> Result<()> {
+        let mut archive = Archive::new(reader);
+        let mut modes = Vec::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.into_owned();
+            let inner = Self::to_inner(&name)?;
+            if utils::is_virtual_root(&inner) {
+                continue;
+            }
+
+            let entry_type = entry.header().entry_type();
+            if matches!(
+                entry_type,
+                EntryType::Directory | EntryType::Regular | EntryType::Continuous
+            ) {
+                modes.push((inner.clone(), entry.header().mode()?));
+            }
+            match entry_type {
+                EntryType::Directory if !state.exists(&inner) => state.mkdir(&inner)?,
+                EntryType::Regular | EntryType::Continuous => {
+                    let mut content = Vec::new();
+                    entry.read_to_end(&mut content)?;
+                    if state.exists(&inner) {
+                        state.write(&inner, &content)?;
+                    } else {
+                        state.mkfile(&inner, Some(&content))?;
+                    }
+                }
+                EntryType::Symlink => {
+                    let target = entry
+                        .link_name()?
+                        .ok_or_else(|| VfsError::invalid_path(&name, "the link has no target"))?;
+                    state.symlink(target, &inner)?;
+                }
+                EntryType::Link => {
+                    let existing = entry
+                        .link_name()?
+                        .ok_or_else(|| VfsError::invalid_path(&name, "the link has no target"))?;
+                    let existing = Self::to_inner(&existing)?;
+                    if state.exists(&inner) {
+                        state.rm(&inner)?;
+                    }
+                    state.hard_link(existing, &inner)?;
+                }
+                _ => {} // devices, fifos, etc. are not supported
+            }
+        }
+        for (inner, mode) in modes.into_iter().rev() {
+            state.set_permissions(&inner, mode)?;
+        }
+        Ok(())
+    }
+
+    /// Converts an entry name into an inner absolute path refusing unsafe names.
+    fn to_inner(name: &Path) -> Result<PathBuf> {
+        let mut inner = PathBuf::from("/");
+        for component in name.components() {
+            match component {
+                Component::Normal(part) => inner.push(part),
+                Component::CurDir => {}
+                _ => return Err(VfsError::invalid_path(name, "unsafe path in archive")),
+            }
+        }
+        Ok(inner)
+    }
+}
+
+impl FsBackend for TarFS {
+    /// Returns the path to the archive on the host file system
+    /// (or `/` if the VFS was created from a stream).
+    fn root(&self) -> &Path {
+        self.archive.as_path()
+    }
+
+    /// Returns current working directory related to the vfs root.
+    fn cwd(&self) -> &Path {
+        self.state.cwd()
+    }
+
+    /// Returns a hypothetical "host-path" joining the archive path and `inner_path`.
+    /// * `inner_path` must exist in VFS
+    fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+        self.state.to_host(inner_path)
+    }
+
+    /// Changes the current working directory.
+    /// * `path` can be in relative or absolute form, but in both cases it must exist in VFS.
+    ///
+    /// An error is returned if the specified `path` does not exist.
+    fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.state.cd(path)
+    }
+
+    /// Checks if a `path` exists in the VFS.
+    /// The `path` can be in relative or absolute form.
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.state.exists(path)
+    }
+
+    /// Checks if `path` is a directory.
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.state.is_dir(path)
+    }
+
+    /// Checks if `path` is a regular file.
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.state.is_file(path)
+    }
+
+    /// Checks if `path` is a symbolic link.
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.state.is_symlink(path)
+    }
+
+    /// Returns metadata of `path`. See `MapFS::metadata()` for details.
+    /// Times are tracked since the archive was loaded; those stored in the archive are not used.
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        self.state.metadata(path)
+    }
+
+    /// Sets the permission bits of a file or directory in the in-memory tree.
+    /// See `MapFS::set_permissions()` for details.
+    fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        self.state.set_permissions(path, mode)
+    }
+
+    /// Returns an iterator over directory entries at a specific depth (shallow listing).
+    /// See `MapFS::ls()` for details.
+    fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        self.state.ls(path)
+    }
+
+    /// Returns a recursive iterator over the directory tree starting from a given path.
+    /// See `MapFS::tree()` for details.
+    fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        self.state.tree(path)
+    }
+
+    /// Creates directory and all it parents (if needed) in the in-memory tree.
+    /// * `path` - inner vfs path.
+    fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.state.mkdir(path)
+    }
+
+    /// Creates new file in the in-memory tree.
+    /// * `file_path` must be inner VFS path. It must contain the name of the file,
+    ///   optionally preceded by parent directory.
+    ///
+    /// If the parent directory does not exist, it will be created.
+    fn mkfile<P: AsRef<Path>>(&mut self, file_path: P, content: Option<&[u8]>) -> Result<()> {
+        self.state.mkfile(file_path, content)
+    }
+
+    /// Reads the entire contents of a file into a byte vector.
+    /// * `path` is the inner VFS path.
+    fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        self.state.read(path)
+    }
+
+    /// Writes bytes to an existing file in the in-memory tree, replacing its entire contents.
+    fn write<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        self.state.write(path, content)
+    }
+
+    /// Appends bytes to the end of an existing file in the in-memory tree.
+    fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        self.state.append(path, content)
+    }
+
+    /// Opens a file of the in-memory tree with the given `options`.
+    /// See `MapFS::open()` for details.
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<impl VfsFile + '_> {
+        self.state.open(path, options)
+    }
+
+    /// Moves a file or a whole directory subtree from `from` to `to` in the in-memory tree.
+    /// See `MapFS::rename()` for details.
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        self.state.rename(from, to)
+    }
+
+    /// Creates a symbolic link at `link` pointing to `target` in the in-memory tree.
+    /// See `MapFS::symlink()` for details.
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, target: P, link: Q) -> Result<()> {
+        self.state.symlink(target, link)
+    }
+
+    /// Returns the target of the symbolic link at `path`.
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        self.state.read_link(path)
+    }
+
+    /// Creates a hard link `new` to the file `existing` in the in-memory tree.
+    /// See `MapFS::hard_link()` for details.
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, existing: P, new: Q) -> Result<()> {
+        self.state.hard_link(existing, new)
+    }
+
+    /// Removes a file or directory at the specified path from the in-memory tree.
+    ///
+    /// - `path`: can be absolute (starting with '/') or relative to the current working
+    ///   directory (cwd). If the path is a directory, all its contents are removed recursively.
+    fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.state.rm(path)
+    }
+
+    /// Removes all artifacts (dirs and files) from the in-memory tree.
+    fn cleanup(&mut self) -> bool {
+        self.state.cleanup()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn setup_test_env() -> TempDir {
+        TempDir::new("tarfs_test").unwrap()
+    }
+
+    /// Creates a tar stream with the following structure:
+    /// ```text
+    /// ./
+    /// ./docs/
+    /// ./docs/readme.txt   "Read me"
+    /// ./docs/api/v1.json  "{}"
+    /// ./link -> docs
+    /// ```
+    fn create_test_tar() -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+
+        for dir in ["./", "./docs/"] {
+            let mut header = Header::new_gnu();
+            header.set_entry_type(EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_size(0);
+            builder
+                .append_data(&mut header, dir, std::io::empty())
+                .unwrap();
+        }
+        for (name, content) in [
+            ("./docs/readme.txt", b"Read me".as_slice()),
+            ("./docs/api/v1.json", b"{}".as_slice()),
+        ] {
+            let mut header = Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(content.len() as u64);
+            builder.append_data(&mut header, name, content).unwrap();
+        }
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        builder.append_link(&mut header, "./link", "docs").unwrap();
+
+        builder.into_inner().unwrap()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    mod creations {
+        use super::*;
+
+        #[test]
+        fn test_from_reader_plain() -> Result<()> {
+            let fs = TarFS::from_reader(create_test_tar().as_slice())?;
+
+            assert_eq!(fs.root(), Path::new("/"));
+            assert!(fs.is_dir("/docs")?);
+            assert!(fs.is_dir("/docs/api")?);
+            assert_eq!(fs.read("/docs/readme.txt")?, b"Read me");
+            assert_eq!(fs.read("/docs/api/v1.json")?, b"{}");
+            assert!(fs.is_symlink("/link")?);
+            assert_eq!(fs.read("/link/readme.txt")?, b"Read me");
+            assert_eq!(fs.tree("/")?.count(), 5);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_from_reader_gzip() -> Result<()> {
+            let fs = TarFS::from_reader(gzip(&create_test_tar()).as_slice())?;
+
+            let docs: Vec<_> = fs.ls("/docs")?.collect();
+            assert_eq!(
+                docs,
+                vec![Path::new("/docs/api"), Path::new("/docs/readme.txt")]
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_new_from_host_file() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("bundle.tar.gz");
+            std::fs::write(&archive, gzip(&create_test_tar()))?;
+
+            let fs = TarFS::new(&archive)?;
+
+            assert_eq!(fs.root(), archive);
+            assert_eq!(fs.read("/docs/readme.txt")?, b"Read me");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_new_invalid_paths() {
+            let temp_dir = setup_test_env();
+
+            assert!(TarFS::new("").is_err());
+            assert!(TarFS::new("relative.tar").is_err());
+            assert!(TarFS::new(temp_dir.path()).is_err());
+            assert!(TarFS::new(temp_dir.path().join("missing.tar")).is_err());
+        }
+
+        #[test]
+        fn test_unsafe_entry_name() {
+            let mut builder = Builder::new(Vec::new());
+            let mut header = Header::new_gnu();
+            header.set_size(4);
+            // `append_data` refuses `..`, so the name is written into the header directly
+            header.as_old_mut().name[..13].copy_from_slice(b"../escape.txt");
+            header.set_cksum();
+            builder.append(&header, b"evil".as_slice()).unwrap();
+            let data = builder.into_inner().unwrap();
+
+            assert!(TarFS::from_reader(data.as_slice()).is_err());
+        }
+
+        #[test]
+        fn test_modifications_stay_in_memory() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("bundle.tar");
+            std::fs::write(&archive, create_test_tar())?;
+
+            let mut fs = TarFS::new(&archive)?;
+            fs.mkfile("/new.txt", Some(b"new"))?;
+            fs.rm("/docs")?;
+            assert_eq!(fs.read("/new.txt")?, b"new");
+            drop(fs);
+
+            let fs = TarFS::new(&archive)?;
+            assert!(!fs.exists("/new.txt"));
+            assert!(fs.exists("/docs/readme.txt"));
+
+            Ok(())
+        }
+    }
+
+    mod pack {
+        use super::*;
+
+        #[test]
+        fn test_pack_roundtrip() -> Result<()> {
+            let mut mem = MapFS::new();
+            mem.mkdir("/empty")?;
+            mem.mkfile("/src/main.rs", Some(b"fn main() {}"))?;
+            mem.mkfile("/README.md", Some(b"# Readme"))?;
+
+            let data = TarFS::pack(&mem, "/", Vec::new())?;
+            let fs = TarFS::from_reader(data.as_slice())?;
+
+            let original: Vec<_> = mem.tree("/")?.collect();
+            let restored: Vec<_> = fs.tree("/")?.collect();
+            assert_eq!(original, restored);
+            assert_eq!(fs.read("/src/main.rs")?, b"fn main() {}");
+            assert!(fs.is_dir("/empty")?);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_pack_subtree_gz() -> Result<()> {
+            let mut mem = MapFS::new();
+            mem.mkfile("/out/bin/tool", Some(b"binary"))?;
+            mem.mkfile("/out/notes.txt", Some(b"notes"))?;
+            mem.mkfile("/other.txt", None)?;
+            mem.cd("/out")?;
+
+            let data = TarFS::pack_gz(&mem, ".", Vec::new())?;
+            assert!(data.starts_with(&GZIP_MAGIC));
+
+            let fs = TarFS::from_reader(data.as_slice())?;
+            let restored: Vec<_> = fs.tree("/")?.collect();
+            assert_eq!(
+                restored,
+                vec![
+                    Path::new("/bin"),
+                    Path::new("/bin/tool"),
+                    Path::new("/notes.txt")
+                ]
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_pack_single_file() -> Result<()> {
+            let mut mem = MapFS::new();
+            mem.mkfile("/dir/file.txt", Some(b"content"))?;
+
+            let data = TarFS::pack(&mem, "/dir/file.txt", Vec::new())?;
+            let fs = TarFS::from_reader(data.as_slice())?;
+
+            assert_eq!(fs.read("/file.txt")?, b"content");
+            assert_eq!(fs.tree("/")?.count(), 1);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_pack_nonexistent_path() {
+            let mem = MapFS::new();
+            assert!(TarFS::pack(&mem, "/missing", Vec::new()).is_err());
+        }
+
+        #[test]
+        fn test_pack_symlinks() -> Result<()> {
+            let mut mem = MapFS::new();
+            mem.mkfile("/lib/libfoo.so.1", Some(b"elf"))?;
+            mem.symlink("libfoo.so.1", "/lib/libfoo.so")?;
+
+            let data = TarFS::pack(&mem, "/", Vec::new())?;
+            let fs = TarFS::from_reader(data.as_slice())?;
+
+            assert_eq!(fs.read_link("/lib/libfoo.so")?, Path::new("libfoo.so.1"));
+            assert_eq!(fs.read("/lib/libfoo.so")?, b"elf");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_pack_permissions() -> Result<()> {
+            let mut mem = MapFS::new();
+            mem.mkfile("/bin/tool", Some(b"elf"))?;
+            mem.set_permissions("/bin/tool", 0o755)?;
+            mem.set_permissions("/bin", 0o555)?;
+
+            let data = TarFS::pack(&mem, "/", Vec::new())?;
+            let mut fs = TarFS::from_reader(data.as_slice())?;
+
+            assert_eq!(fs.metadata("/bin")?.mode(), Some(0o555));
+            assert_eq!(fs.metadata("/bin/tool")?.mode(), Some(0o755));
+            assert!(matches!(
+                fs.mkfile("/bin/other", None),
+                Err(VfsError::PermissionDenied(_))
+            ));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_hard_links_are_loaded() -> Result<()> {
+            let mut builder = Builder::new(Vec::new());
+            let mut header = Header::new_gnu();
+            header.set_entry_type(EntryType::Regular);
+            header.set_mode(0o755);
+            header.set_size(3);
+            builder.append_data(&mut header, "bin/app", &b"elf"[..])?;
+            let mut header = Header::new_gnu();
+            header.set_entry_type(EntryType::Link);
+            header.set_size(0);
+            builder.append_link(&mut header, "bin/app-v2", "bin/app")?;
+            let data = builder.into_inner()?;
+
+            let mut fs = TarFS::from_reader(data.as_slice())?;
+            assert!(fs.is_file("/bin/app-v2")?);
+            fs.write("/bin/app-v2", b"new")?;
+            assert_eq!(fs.read("/bin/app")?, b"new");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_escaping_symlink_is_refused() {
+            let mut builder = Builder::new(Vec::new());
+            let mut header = Header::new_gnu();
+            header.set_entry_type(EntryType::Symlink);
+            header.set_size(0);
+            builder
+                .append_link(&mut header, "passwd", "../etc/passwd")
+                .unwrap();
+            let data = builder.into_inner().unwrap();
+
+            assert!(TarFS::from_reader(data.as_slice()).is_err());
+        }
+    }
+}
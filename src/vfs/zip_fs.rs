@@ -0,0 +1,650 @@
+//! This module provides a virtual filesystem (VFS) implementation that maps to a ZIP archive
+//! on the host system. The archive is indexed into memory on open, all changes are staged
+//! in memory and written back as a new archive on `commit()` (or on drop).
+//!
+//! Available with the `zip` feature.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::VfsError;
+use crate::core::{FsBackend, Result, utils};
+use crate::{MapFS, Metadata, OpenOptions, VfsFile};
+
+/// A virtual file system (VFS) implementation that exposes the contents of a ZIP archive.
+///
+/// On creation, all directories and files of the archive are loaded into an in‑memory tree
+/// (the same storage `MapFS` uses), so `ls()`, `tree()`, `read()`, etc. work without extracting
+/// anything to the disk. Modifications (`mkdir()`, `mkfile()`, `write()`, `append()`, `rm()`)
+/// are staged in memory and written back to the archive by `commit()`.
+///
+/// ### Usage notes:
+/// - The archive is rewritten as a whole: a new archive is written next to the original one and
+///   then atomically renamed over it.
+/// - If the `is_auto_commit` flag is set (default), uncommitted changes are committed on drop.
+/// - Entries with unsafe names (absolute paths, `..` components) are refused on open.
+/// - Files are stored with the `Deflated` compression method.
+/// - Hard links are shared in memory only; each of them is committed as a separate file.
+/// - Not thread‑safe by itself (share it between threads with `SharedFs`).
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{FsBackend, ZipFS};
+///
+/// let mut fs = ZipFS::new("/path/to/fixtures.zip").unwrap();
+/// for path in fs.tree("/").unwrap() {
+///     println!("{}", path.display());
+/// }
+/// fs.mkfile("/generated/report.txt", Some(b"Hello")).unwrap();
+/// fs.commit().unwrap();
+/// ```
+pub struct ZipFS {
+    archive: PathBuf, // host-related absolute normalized path
+    state: MapFS,     // staged tree
+    is_dirty: bool,
+    is_auto_commit: bool,
+}
+
+impl ZipFS {
+    /// Opens the ZIP archive at `archive` and indexes its contents.
+    /// * `archive` is an absolute host path. If it does not exist, the VFS starts empty and
+    ///   the archive will be created on `commit()`.
+    ///
+    /// If `archive` is not absolute, is a directory, or is not a valid ZIP archive, error returns.
+    /// By default, the `is_auto_commit` flag is set to `true`.
+    pub fn new<P: AsRef<Path>>(archive: P) -> Result<Self> {
+        let archive = archive.as_ref();
+
+        if archive.as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        if archive.is_relative() {
+            return Err(VfsError::invalid_path(
+                archive,
+                "the archive path must be absolute",
+            ));
+        }
+        if archive.is_dir() {
+            return Err(VfsError::IsADirectory(archive.to_path_buf()));
+        }
+
+        let archive = utils::normalize(archive);
+        let mut state = MapFS::new();
+        state.set_root(&archive)?;
+
+        if archive.exists() {
+            Self::load(&mut state, &archive)?;
+        }
+
+        Ok(Self {
+            archive,
+            state,
+            is_dirty: false,
+            is_auto_commit: true,
+        })
+    }
+
+    /// Changes auto-commit flag.
+    /// If auto-commit flag is true, uncommitted changes are written to the archive on drop.
+    pub fn set_auto_commit(&mut self, commit: bool) {
+        self.is_auto_commit = commit;
+    }
+
+    /// Returns true, if there are changes not yet written to the archive.
+    pub fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    /// Writes the current tree into the archive, replacing its previous contents.
+    /// The parent directories of the archive are created if necessary.
+    pub fn commit(&mut self) -> Result<()> {
+        if let Some(parent) = self.archive.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut tmp_name = self.archive.as_os_str().to_owned();
+        tmp_name.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+
+        // entries the current user has no access to are committed as well
+        self.state.set_enforce_permissions(false);
+        let result = self.write_archive(&tmp_path);
+        self.state.set_enforce_permissions(true);
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+            return result;
+        }
+        std::fs::rename(&tmp_path, &self.archive)?;
+
+        self.is_dirty = false;
+        Ok(())
+    }
+
+    fn write_archive(&self, path: &Path) -> Result<()> {
+        let mut writer = ZipWriter::new(File::create(path)?);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        for inner in self.state.tree("/")? {
+            let name = Self::to_zip_name(inner)?;
+            if self.state.is_symlink(inner)? {
+                let target = self.state.read_link(inner)?;
+                let target = target
+                    .to_str()
+                    .ok_or_else(|| VfsError::invalid_path(&target, "not a valid UTF-8 path"))?;
+                writer.add_symlink(name, target, options)?;
+                continue;
+            }
+            let mode = self.state.metadata(inner)?.mode();
+            let options = match mode {
+                Some(mode) => options.unix_permissions(mode),
+                None => options,
+            };
+            if self.state.is_dir(inner)? {
+                writer.add_directory(format!("{}/", name), options)?;
+            } else {
+                writer.start_file(name, options)?;
+                writer.write_all(&self.state.read(inner)?)?;
+            }
+        }
+
+        writer.finish()?.sync_all()?;
+        Ok(())
+    }
+
+    /// Loads all entries of the archive into `state`. Unix permissions stored in the archive
+    /// are applied at the end, so a read-only directory does not prevent loading its contents.
+    fn load(state: &mut MapFS, archive: &Path) -> Result<()> {
+        let mut zip = ZipArchive::new(File::open(archive)?)?;
+        let mut modes = Vec::new();
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            let name = file
+                .enclosed_name()
+                .ok_or_else(|| VfsError::invalid_path(file.name(), "unsafe path in archive"))?;
+            let inner = Path::new("/").join(name);
+            if utils::is_virtual_root(&inner) {
+                continue;
+            }
+
+            if file.is_symlink() {
+                let mut target = String::new();
+                file.read_to_string(&mut target)?;
+                state.symlink(target, &inner)?;
+                continue;
+            }
+            if let Some(mode) = file.unix_mode() {
+                modes.push((inner.clone(), mode));
+            }
+            if file.is_dir() {
+                if !state.exists(&inner) {
+                    state.mkdir(&inner)?;
+                }
+            } else {
+                let mut content = Vec::new();
+                file.read_to_end(&mut content)?;
+                if state.exists(&inner) {
+                    state.write(&inner, &content)?;
+                } else {
+                    state.mkfile(&inner, Some(&content))?;
+                }
+            }
+        }
+        for (inner, mode) in modes.into_iter().rev() {
+            state.set_permissions(&inner, mode)?;
+        }
+        Ok(())
+    }
+
+    /// Converts an inner absolute path into an entry name (`dir/file.txt`).
+    fn to_zip_name(inner: &Path) -> Result<String> {
+        let mut parts = Vec::new();
+        for component in inner.components() {
+            if let Component::Normal(part) = component {
+                let part = part
+                    .to_str()
+                    .ok_or_else(|| VfsError::invalid_path(inner, "not a valid UTF-8 path"))?;
+                parts.push(part);
+            }
+        }
+        Ok(parts.join("/"))
+    }
+}
+
+impl FsBackend for ZipFS {
+    /// Returns the path to the archive on the host file system.
+    fn root(&self) -> &Path {
+        self.archive.as_path()
+    }
+
+    /// Returns current working directory related to the vfs root.
+    fn cwd(&self) -> &Path {
+        self.state.cwd()
+    }
+
+    /// Returns a hypothetical "host-path" joining the archive path and `inner_path`.
+    /// * `inner_path` must exist in VFS
+    fn to_host<P: AsRef<Path>>(&self, inner_path: P) -> Result<PathBuf> {
+        self.state.to_host(inner_path)
+    }
+
+    /// Changes the current working directory.
+    /// * `path` can be in relative or absolute form, but in both cases it must exist in VFS.
+    ///
+    /// An error is returned if the specified `path` does not exist.
+    fn cd<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.state.cd(path)
+    }
+
+    /// Checks if a `path` exists in the VFS.
+    /// The `path` can be in relative or absolute form.
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.state.exists(path)
+    }
+
+    /// Checks if `path` is a directory.
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.state.is_dir(path)
+    }
+
+    /// Checks if `path` is a regular file.
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.state.is_file(path)
+    }
+
+    /// Checks if `path` is a symbolic link.
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.state.is_symlink(path)
+    }
+
+    /// Returns metadata of `path`. See `MapFS::metadata()` for details.
+    /// Times are tracked since the archive was loaded; those stored in the archive are not used.
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        self.state.metadata(path)
+    }
+
+    /// Sets the permission bits of a file or directory; they are stored in the archive as
+    /// Unix permissions. See `MapFS::set_permissions()` for details.
+    fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        self.state.set_permissions(path, mode)?;
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    /// Returns an iterator over directory entries at a specific depth (shallow listing).
+    /// See `MapFS::ls()` for details.
+    fn ls<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        self.state.ls(path)
+    }
+
+    /// Returns a recursive iterator over the directory tree starting from a given path.
+    /// See `MapFS::tree()` for details.
+    fn tree<P: AsRef<Path>>(&self, path: P) -> Result<impl Iterator<Item = &Path>> {
+        self.state.tree(path)
+    }
+
+    /// Creates directory and all it parents (if needed).
+    /// * `path` - inner vfs path.
+    fn mkdir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.state.mkdir(path)?;
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    /// Creates new file in VFS.
+    /// * `file_path` must be inner VFS path. It must contain the name of the file,
+    ///   optionally preceded by parent directory.
+    ///
+    /// If the parent directory does not exist, it will be created.
+    fn mkfile<P: AsRef<Path>>(&mut self, file_path: P, content: Option<&[u8]>) -> Result<()> {
+        self.state.mkfile(file_path, content)?;
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    /// Reads the entire contents of a file into a byte vector.
+    /// * `path` is the inner VFS path.
+    fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        self.state.read(path)
+    }
+
+    /// Writes bytes to an existing file, replacing its entire contents.
+    fn write<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        self.state.write(path, content)?;
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    /// Appends bytes to the end of an existing file, preserving its old contents.
+    fn append<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> Result<()> {
+        self.state.append(path, content)?;
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    /// Opens a file of the in-memory tree with the given `options`.
+    /// See `MapFS::open()` for details.
+    fn open<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<impl VfsFile + '_> {
+        if options.is_writable() {
+            self.is_dirty = true;
+        }
+        self.state.open(path, options)
+    }
+
+    /// Moves a file or a whole directory subtree from `from` to `to`.
+    /// See `MapFS::rename()` for details.
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        self.state.rename(from, to)?;
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    /// Creates a symbolic link at `link` pointing to `target`; it is stored in the archive as
+    /// a symlink entry. See `MapFS::symlink()` for details.
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, target: P, link: Q) -> Result<()> {
+        self.state.symlink(target, link)?;
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    /// Returns the target of the symbolic link at `path`.
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        self.state.read_link(path)
+    }
+
+    /// Creates a hard link `new` to the file `existing`. Zip has no hard links, so the archive
+    /// gets a separate copy of the file on commit. See `MapFS::hard_link()` for details.
+    fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, existing: P, new: Q) -> Result<()> {
+        self.state.hard_link(existing, new)?;
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    /// Removes a file or directory at the specified path.
+    ///
+    /// - `path`: can be absolute (starting with '/') or relative to the current working
+    ///   directory (cwd). If the path is a directory, all its contents are removed recursively.
+    fn rm<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.state.rm(path)?;
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    /// Removes all artifacts (dirs and files) in vfs.
+    /// The archive itself is changed on `commit()` only.
+    fn cleanup(&mut self) -> bool {
+        self.is_dirty = true;
+        self.state.cleanup()
+    }
+}
+
+impl Drop for ZipFS {
+    fn drop(&mut self) {
+        if !self.is_auto_commit || !self.is_dirty {
+            return;
+        }
+
+        if let Err(err) = self.commit() {
+            eprintln!("Unable to commit {}: {}", self.archive.display(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn setup_test_env() -> TempDir {
+        TempDir::new("zipfs_test").unwrap()
+    }
+
+    /// Creates an archive with the following structure:
+    /// ```text
+    /// docs/
+    /// docs/readme.txt   "Read me"
+    /// docs/api/v1.json  "{}"
+    /// data.bin          [0, 1, 2]
+    /// ```
+    fn create_test_archive(path: &Path) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        let options = SimpleFileOptions::default();
+        writer.add_directory("docs/", options).unwrap();
+        writer.start_file("docs/readme.txt", options).unwrap();
+        writer.write_all(b"Read me").unwrap();
+        // parent directory is not stored explicitly
+        writer.start_file("docs/api/v1.json", options).unwrap();
+        writer.write_all(b"{}").unwrap();
+        writer.start_file("data.bin", options).unwrap();
+        writer.write_all(&[0, 1, 2]).unwrap();
+        writer.finish().unwrap();
+    }
+
+    mod creations {
+        use super::*;
+
+        #[test]
+        fn test_new_indexes_archive() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("test.zip");
+            create_test_archive(&archive);
+
+            let fs = ZipFS::new(&archive)?;
+
+            assert_eq!(fs.root(), archive);
+            assert!(fs.is_dir("/docs")?);
+            assert!(fs.is_dir("/docs/api")?);
+            assert!(fs.is_file("/docs/readme.txt")?);
+            assert_eq!(fs.read("/docs/readme.txt")?, b"Read me");
+            assert_eq!(fs.read("/data.bin")?, vec![0, 1, 2]);
+            assert!(!fs.is_dirty());
+
+            let root: Vec<_> = fs.ls("/")?.collect();
+            assert_eq!(root, vec![Path::new("/data.bin"), Path::new("/docs")]);
+            assert_eq!(fs.tree("/")?.count(), 5);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_new_nonexistent_archive() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("new.zip");
+
+            let fs = ZipFS::new(&archive)?;
+
+            assert_eq!(fs.tree("/")?.count(), 0);
+            drop(fs);
+            assert!(!archive.exists()); // nothing to commit
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_new_invalid_paths() {
+            let temp_dir = setup_test_env();
+
+            assert!(ZipFS::new("").is_err());
+            assert!(ZipFS::new("relative.zip").is_err());
+            assert!(ZipFS::new(temp_dir.path()).is_err());
+        }
+
+        #[test]
+        fn test_new_not_an_archive() {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("fake.zip");
+            std::fs::write(&archive, b"definitely not a zip").unwrap();
+
+            assert!(ZipFS::new(&archive).is_err());
+        }
+
+        #[test]
+        fn test_new_unsafe_entry_name() {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("evil.zip");
+            let mut writer = ZipWriter::new(File::create(&archive).unwrap());
+            writer
+                .start_file("../escape.txt", SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(b"evil").unwrap();
+            writer.finish().unwrap();
+
+            let result = ZipFS::new(&archive);
+            assert!(result.is_err());
+        }
+    }
+
+    mod commit {
+        use super::*;
+
+        #[test]
+        fn test_commit_keeps_symlinks() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("test.zip");
+            create_test_archive(&archive);
+            {
+                let mut fs = ZipFS::new(&archive)?;
+                fs.symlink("docs/readme.txt", "/readme")?;
+                assert!(fs.is_dirty());
+            }
+
+            let fs = ZipFS::new(&archive)?;
+            assert!(fs.is_symlink("/readme")?);
+            assert_eq!(fs.read_link("/readme")?, Path::new("docs/readme.txt"));
+            assert_eq!(fs.read("/readme")?, b"Read me");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_commit_keeps_permissions() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("test.zip");
+            create_test_archive(&archive);
+            {
+                let mut fs = ZipFS::new(&archive)?;
+                fs.mkfile("/bin/run.sh", Some(b"#!/bin/sh"))?;
+                fs.set_permissions("/bin/run.sh", 0o755)?;
+                fs.set_permissions("/bin", 0o500)?;
+                assert!(fs.is_dirty());
+            }
+
+            let fs = ZipFS::new(&archive)?;
+            assert_eq!(fs.metadata("/bin")?.mode(), Some(0o500));
+            assert_eq!(fs.metadata("/bin/run.sh")?.mode(), Some(0o755));
+            assert_eq!(fs.read("/bin/run.sh")?, b"#!/bin/sh");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_commit_writes_changes() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("test.zip");
+            create_test_archive(&archive);
+
+            let mut fs = ZipFS::new(&archive)?;
+            fs.mkfile("/docs/new.txt", Some(b"New"))?;
+            fs.append("/docs/readme.txt", b"!")?;
+            fs.rm("/data.bin")?;
+            fs.mkdir("/empty")?;
+            assert!(fs.is_dirty());
+
+            fs.commit()?;
+            assert!(!fs.is_dirty());
+            drop(fs);
+
+            let fs = ZipFS::new(&archive)?;
+            assert_eq!(fs.read("/docs/new.txt")?, b"New");
+            assert_eq!(fs.read("/docs/readme.txt")?, b"Read me!");
+            assert_eq!(fs.read("/docs/api/v1.json")?, b"{}");
+            assert!(!fs.exists("/data.bin"));
+            assert!(fs.is_dir("/empty")?);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_auto_commit_on_drop() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("nested/dir/new.zip");
+            {
+                let mut fs = ZipFS::new(&archive)?;
+                fs.mkfile("/file.txt", Some(b"content"))?;
+            }
+
+            assert!(archive.exists());
+            let fs = ZipFS::new(&archive)?;
+            assert_eq!(fs.read("/file.txt")?, b"content");
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_no_auto_commit() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("test.zip");
+            create_test_archive(&archive);
+            {
+                let mut fs = ZipFS::new(&archive)?;
+                fs.set_auto_commit(false);
+                fs.rm("/docs")?;
+            }
+
+            let fs = ZipFS::new(&archive)?;
+            assert!(fs.exists("/docs/readme.txt"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_cleanup_and_commit() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("test.zip");
+            create_test_archive(&archive);
+
+            let mut fs = ZipFS::new(&archive)?;
+            assert!(fs.cleanup());
+            fs.commit()?;
+            drop(fs);
+
+            let fs = ZipFS::new(&archive)?;
+            assert_eq!(fs.tree("/")?.count(), 0);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_relative_paths() -> Result<()> {
+            let temp_dir = setup_test_env();
+            let archive = temp_dir.path().join("test.zip");
+            create_test_archive(&archive);
+
+            let mut fs = ZipFS::new(&archive)?;
+            fs.cd("/docs")?;
+            assert_eq!(fs.read("readme.txt")?, b"Read me");
+            fs.mkfile("api/v2.json", Some(b"[]"))?;
+            fs.commit()?;
+            drop(fs);
+
+            let fs = ZipFS::new(&archive)?;
+            assert_eq!(fs.read("/docs/api/v2.json")?, b"[]");
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_to_zip_name() -> Result<()> {
+        assert_eq!(ZipFS::to_zip_name(Path::new("/a/b/c.txt"))?, "a/b/c.txt");
+        assert_eq!(ZipFS::to_zip_name(Path::new("/file"))?, "file");
+        Ok(())
+    }
+}
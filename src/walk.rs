@@ -0,0 +1,562 @@
+//! This module provides a configurable directory walker over any backend (see `FsBackend::walk()`).
+
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+use crate::core::{FsBackend, Result, utils};
+use crate::{EntryType, VfsError};
+
+/// An entry yielded by `Walk`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkEntry {
+    path: PathBuf, // inner absolute path, as it was reached by the walk
+    depth: usize,
+    entry_type: EntryType,
+    is_followed_link: bool,
+}
+
+impl WalkEntry {
+    /// Returns the inner absolute path of the entry. Below a followed link, the path goes
+    /// through the link, not its target.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Consumes the entry and returns its path.
+    pub fn into_path(self) -> PathBuf {
+        self.path
+    }
+
+    /// Returns the file name of the entry (the whole path for the root).
+    pub fn file_name(&self) -> &std::ffi::OsStr {
+        self.path
+            .file_name()
+            .unwrap_or_else(|| self.path.as_os_str())
+    }
+
+    /// Returns the depth of the entry: `0` for the starting path, `1` for its children, etc.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the type of the entry. A followed link has the type of its target,
+    /// otherwise it is `EntryType::Symlink`.
+    pub fn entry_type(&self) -> EntryType {
+        self.entry_type
+    }
+
+    /// Checks if the entry is a directory (or a followed link to one).
+    pub fn is_dir(&self) -> bool {
+        self.entry_type == EntryType::Directory
+    }
+
+    /// Checks if the entry is a regular file (or a followed link to one).
+    pub fn is_file(&self) -> bool {
+        self.entry_type == EntryType::File
+    }
+
+    /// Checks if the path of the entry is a symbolic link, whether it was followed or not.
+    pub fn path_is_symlink(&self) -> bool {
+        self.is_followed_link || self.entry_type == EntryType::Symlink
+    }
+}
+
+type SortBy<'a> = Box<dyn FnMut(&WalkEntry, &WalkEntry) -> Ordering + 'a>;
+type FilterEntry<'a> = Box<dyn FnMut(&WalkEntry) -> bool + 'a>;
+
+/// A builder of a recursive directory walk returned by `FsBackend::walk()`; iterate it to get
+/// the entries (`Result<WalkEntry>`).
+///
+/// By default, the walk yields the starting path first (depth `0`), then the whole tree in
+/// pre-order (a directory before its contents), children in the order of `ls()`, without
+/// following symbolic links (except for the starting path, which is always followed).
+///
+/// An error of one directory (e.g. `PermissionDenied`) is yielded in place of its contents and
+/// the walk goes on. With `follow_links(true)`, a link leading back to a directory being
+/// walked is yielded as an `InvalidPath` error instead of being walked again.
+///
+/// ### Example:
+/// ```no_run
+/// use vfs_kit::{FsBackend, MapFS};
+///
+/// let mut fs = MapFS::new();
+/// fs.mkfile("/build/out/app.bin", None).unwrap();
+/// fs.mkfile("/build/.cache/tmp", None).unwrap();
+///
+/// // Contents before their directories, so everything can be removed one by one
+/// let entries: Vec<_> = fs
+///     .walk("/build")
+///     .min_depth(1)
+///     .contents_first(true)
+///     .filter_entry(|entry| entry.file_name() != ".cache")
+///     .into_iter()
+///     .collect::<vfs_kit::Result<_>>()
+///     .unwrap();
+/// for entry in entries {
+///     fs.rm(entry.path()).unwrap();
+/// }
+/// ```
+pub struct Walk<'a, B: ?Sized> {
+    fs: &'a B,
+    path: PathBuf, // as given, resolved on the first iteration
+    min_depth: usize,
+    max_depth: usize,
+    contents_first: bool,
+    follow_links: bool,
+    sort_by: Option<SortBy<'a>>,
+    filter_entry: Option<FilterEntry<'a>>,
+}
+
+impl<'a, B: FsBackend + ?Sized> Walk<'a, B> {
+    pub(crate) fn new<P: AsRef<Path>>(fs: &'a B, path: P) -> Self {
+        Self {
+            fs,
+            path: path.as_ref().to_path_buf(),
+            min_depth: 0,
+            max_depth: usize::MAX,
+            contents_first: false,
+            follow_links: false,
+            sort_by: None,
+            filter_entry: None,
+        }
+    }
+
+    /// Skips entries shallower than `depth` (they are still walked through).
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Does not descend deeper than `depth`: `0` yields the starting path only.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Yields the contents of a directory before the directory itself (post-order), e.g. to
+    /// remove a tree entry by entry.
+    pub fn contents_first(mut self, yes: bool) -> Self {
+        self.contents_first = yes;
+        self
+    }
+
+    /// Follows symbolic links: a link to a directory is walked into, a link gets the type of
+    /// its target. A dangling link is yielded as `EntryType::Symlink`.
+    pub fn follow_links(mut self, yes: bool) -> Self {
+        self.follow_links = yes;
+        self
+    }
+
+    /// Sorts the children of every directory with `compare` instead of the order of `ls()`.
+    pub fn sort_by<F>(mut self, compare: F) -> Self
+    where
+        F: FnMut(&WalkEntry, &WalkEntry) -> Ordering + 'a,
+    {
+        self.sort_by = Some(Box::new(compare));
+        self
+    }
+
+    /// Skips every entry for which `predicate` returns `false`, and the whole subtree of such
+    /// a directory (it is not walked at all). The predicate sees the entries of all depths,
+    /// including those skipped by `min_depth()`.
+    pub fn filter_entry<F>(mut self, predicate: F) -> Self
+    where
+        F: FnMut(&WalkEntry) -> bool + 'a,
+    {
+        self.filter_entry = Some(Box::new(predicate));
+        self
+    }
+
+    /// Creates the entry for `path` at `depth`.
+    fn entry(&self, path: PathBuf, depth: usize) -> Result<WalkEntry> {
+        let is_link = self.fs.is_symlink(&path)?;
+        // the starting path is followed anyway, the way `tree()` does it
+        let is_followed = self.follow_links || depth == 0;
+        let is_followed_link = is_link && is_followed && self.fs.exists(&path);
+        let entry_type = if is_link && !is_followed_link {
+            EntryType::Symlink
+        } else if self.fs.is_dir(&path)? {
+            EntryType::Directory
+        } else {
+            EntryType::File
+        };
+        Ok(WalkEntry {
+            path,
+            depth,
+            entry_type,
+            is_followed_link,
+        })
+    }
+
+    /// Returns the children of the directory `entry`, sorted if requested. An error of a single
+    /// child is kept in place of that child.
+    fn children(&mut self, entry: &WalkEntry) -> Result<Vec<Result<WalkEntry>>> {
+        // `ls()` returns paths under the target of a followed link, so only names are taken
+        let names: Vec<PathBuf> = self
+            .fs
+            .ls(&entry.path)?
+            .filter_map(|child| child.file_name().map(PathBuf::from))
+            .collect();
+        let mut children: Vec<_> = names
+            .into_iter()
+            .map(|name| self.entry(entry.path.join(name), entry.depth + 1))
+            .collect();
+        if let Some(compare) = &mut self.sort_by {
+            children.sort_by(|a, b| match (a, b) {
+                (Ok(a), Ok(b)) => compare(a, b),
+                (Err(_), Ok(_)) => Ordering::Less,
+                (Ok(_), Err(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => Ordering::Equal,
+            });
+        }
+        Ok(children)
+    }
+}
+
+impl<'a, B: FsBackend + ?Sized> IntoIterator for Walk<'a, B> {
+    type Item = Result<WalkEntry>;
+    type IntoIter = WalkIter<'a, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        WalkIter {
+            walk: self,
+            is_started: false,
+            stack: Vec::new(),
+        }
+    }
+}
+
+/// A directory being walked.
+struct Frame {
+    dir: Option<WalkEntry>, // kept to be yielded after the contents (`contents_first`)
+    canonical: Option<PathBuf>, // to detect loops of followed links
+    children: vec::IntoIter<Result<WalkEntry>>,
+}
+
+/// The iterator over a `Walk`.
+pub struct WalkIter<'a, B: ?Sized> {
+    walk: Walk<'a, B>,
+    is_started: bool,
+    stack: Vec<Frame>,
+}
+
+impl<B: FsBackend + ?Sized> WalkIter<'_, B> {
+    fn start(&mut self) -> Result<WalkEntry> {
+        let path = self.walk.path.as_path();
+        if path.as_os_str().is_empty() {
+            return Err(VfsError::invalid_path("", "empty"));
+        }
+        let inner = utils::normalize(self.walk.fs.cwd().join(path));
+        if !utils::lexists(self.walk.fs, &inner) {
+            return Err(VfsError::NotFound(inner));
+        }
+        self.walk.entry(inner, 0)
+    }
+
+    /// Applies the filter, descends into a directory and returns the entry if it is to be
+    /// yielded right now.
+    fn visit(&mut self, entry: WalkEntry) -> Option<WalkEntry> {
+        if let Some(predicate) = &mut self.walk.filter_entry
+            && !predicate(&entry)
+        {
+            return None;
+        }
+        if !entry.is_dir() || entry.depth >= self.walk.max_depth {
+            return self.in_range(entry);
+        }
+
+        let canonical = if self.walk.follow_links {
+            self.walk.fs.canonicalize(&entry.path).ok()
+        } else {
+            None
+        };
+        let is_loop =
+            canonical.is_some() && self.stack.iter().any(|frame| frame.canonical == canonical);
+        let children = if is_loop {
+            vec![Err(VfsError::invalid_path(
+                &entry.path,
+                "file system loop: the link points to a directory being walked",
+            ))]
+        } else {
+            self.walk
+                .children(&entry)
+                .unwrap_or_else(|err| vec![Err(err)])
+        };
+
+        let (dir, yielded) = if self.walk.contents_first {
+            (Some(entry), None)
+        } else {
+            (None, self.in_range(entry))
+        };
+        self.stack.push(Frame {
+            dir,
+            canonical,
+            children: children.into_iter(),
+        });
+        yielded
+    }
+
+    fn in_range(&self, entry: WalkEntry) -> Option<WalkEntry> {
+        (entry.depth >= self.walk.min_depth).then_some(entry)
+    }
+}
+
+impl<B: FsBackend + ?Sized> Iterator for WalkIter<'_, B> {
+    type Item = Result<WalkEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.is_started {
+            self.is_started = true;
+            match self.start() {
+                Ok(entry) => {
+                    if let Some(entry) = self.visit(entry) {
+                        return Some(Ok(entry));
+                    }
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        loop {
+            let frame = self.stack.last_mut()?;
+            match frame.children.next() {
+                Some(Ok(entry)) => {
+                    if let Some(entry) = self.visit(entry) {
+                        return Some(Ok(entry));
+                    }
+                }
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    let frame = self.stack.pop()?;
+                    if let Some(entry) = frame.dir.and_then(|dir| self.in_range(dir)) {
+                        return Some(Ok(entry));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirFS, MapFS};
+    use tempdir::TempDir;
+
+    fn create_tree() -> Result<MapFS> {
+        let mut fs = MapFS::new();
+        fs.mkfile("/a/1.txt", None)?;
+        fs.mkfile("/a/b/2.txt", None)?;
+        fs.mkfile("/a/b/c/3.txt", None)?;
+        fs.mkfile("/z.txt", None)?;
+        Ok(fs)
+    }
+
+    fn collect<B: FsBackend + ?Sized>(walk: Walk<'_, B>) -> Result<Vec<(String, usize)>> {
+        walk.into_iter()
+            .map(|entry| entry.map(|e| (e.path().display().to_string(), e.depth())))
+            .collect()
+    }
+
+    fn paths<B: FsBackend + ?Sized>(walk: Walk<'_, B>) -> Result<Vec<String>> {
+        Ok(collect(walk)?.into_iter().map(|(path, _)| path).collect())
+    }
+
+    mod order {
+        use super::*;
+
+        #[test]
+        fn test_pre_order_with_depth() -> Result<()> {
+            let fs = create_tree()?;
+            assert_eq!(
+                collect(fs.walk("/a"))?,
+                [
+                    ("/a".into(), 0),
+                    ("/a/1.txt".into(), 1),
+                    ("/a/b".into(), 1),
+                    ("/a/b/2.txt".into(), 2),
+                    ("/a/b/c".into(), 2),
+                    ("/a/b/c/3.txt".into(), 3),
+                ]
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_contents_first() -> Result<()> {
+            let mut fs = create_tree()?;
+            let entries = paths(fs.walk("/a").contents_first(true).min_depth(1))?;
+            assert_eq!(
+                entries,
+                ["/a/1.txt", "/a/b/2.txt", "/a/b/c/3.txt", "/a/b/c", "/a/b"]
+            );
+
+            // every entry can be removed in this order
+            for path in entries {
+                fs.rm(path)?;
+            }
+            assert_eq!(fs.ls("/a")?.count(), 0);
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_sort_by() -> Result<()> {
+            let fs = create_tree()?;
+            let entries = paths(
+                fs.walk("/")
+                    .max_depth(1)
+                    .sort_by(|a, b| b.file_name().cmp(a.file_name())),
+            )?;
+            assert_eq!(entries, ["/", "/z.txt", "/a"]);
+            Ok(())
+        }
+    }
+
+    mod limits {
+        use super::*;
+
+        #[test]
+        fn test_min_and_max_depth() -> Result<()> {
+            let fs = create_tree()?;
+            assert_eq!(
+                paths(fs.walk("/a").min_depth(1).max_depth(2))?,
+                ["/a/1.txt", "/a/b", "/a/b/2.txt", "/a/b/c"]
+            );
+            assert_eq!(paths(fs.walk("/a").max_depth(0))?, ["/a"]);
+            assert!(paths(fs.walk("/a").min_depth(4))?.is_empty());
+            Ok(())
+        }
+
+        #[test]
+        fn test_filter_entry_prunes_subtrees() -> Result<()> {
+            let fs = create_tree()?;
+            let mut seen = Vec::new();
+            let entries = paths(fs.walk("/a").filter_entry(|entry| {
+                seen.push(entry.depth());
+                entry.file_name() != "b"
+            }))?;
+            assert_eq!(entries, ["/a", "/a/1.txt"]);
+            assert_eq!(seen, [0, 1, 1]); // nothing below `b` is visited
+            Ok(())
+        }
+
+        #[test]
+        fn test_walk_file_and_relative_path() -> Result<()> {
+            let mut fs = create_tree()?;
+            fs.cd("/a")?;
+            let entries: Vec<_> = fs.walk("b/2.txt").into_iter().collect::<Result<_>>()?;
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].path(), Path::new("/a/b/2.txt"));
+            assert!(entries[0].is_file());
+            Ok(())
+        }
+    }
+
+    mod links {
+        use super::*;
+
+        #[test]
+        fn test_links_are_not_followed_by_default() -> Result<()> {
+            let mut fs = create_tree()?;
+            fs.symlink("/a/b", "/link")?;
+
+            let entries: Vec<_> = fs.walk("/").into_iter().collect::<Result<_>>()?;
+            let link = entries.iter().find(|e| e.path() == Path::new("/link"));
+            let link = link.unwrap();
+            assert_eq!(link.entry_type(), EntryType::Symlink);
+            assert!(link.path_is_symlink());
+            assert!(!entries.iter().any(|e| e.path() == Path::new("/link/2.txt")));
+
+            // the starting path is followed anyway
+            assert_eq!(
+                paths(fs.walk("/link").max_depth(1))?,
+                ["/link", "/link/2.txt", "/link/c"]
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_follow_links() -> Result<()> {
+            let mut fs = create_tree()?;
+            fs.symlink("/a/b/c", "/link")?;
+            fs.symlink("/missing", "/dangling")?;
+
+            let entries: Vec<_> = fs
+                .walk("/")
+                .min_depth(1)
+                .max_depth(2)
+                .follow_links(true)
+                .into_iter()
+                .collect::<Result<_>>()?;
+            let link = entries.iter().find(|e| e.path() == Path::new("/link"));
+            assert!(link.unwrap().is_dir() && link.unwrap().path_is_symlink());
+            assert!(entries.iter().any(|e| e.path() == Path::new("/link/3.txt")));
+            let dangling = entries.iter().find(|e| e.path() == Path::new("/dangling"));
+            assert_eq!(dangling.unwrap().entry_type(), EntryType::Symlink);
+            Ok(())
+        }
+
+        #[test]
+        fn test_loops_are_reported() -> Result<()> {
+            let mut fs = create_tree()?;
+            fs.symlink("/a", "/a/b/up")?;
+
+            let results: Vec<_> = fs.walk("/a").follow_links(true).into_iter().collect();
+            let errors: Vec<_> = results.iter().filter(|r| r.is_err()).collect();
+            assert_eq!(errors.len(), 1);
+            assert!(matches!(errors[0], Err(VfsError::InvalidPath { .. })));
+            assert!(
+                results
+                    .iter()
+                    .any(|r| matches!(r, Ok(e) if e.path() == Path::new("/a/b/up") && e.is_dir()))
+            );
+            Ok(())
+        }
+    }
+
+    mod errors {
+        use super::*;
+
+        #[test]
+        fn test_missing_and_empty_paths() -> Result<()> {
+            let fs = create_tree()?;
+            let mut walk = fs.walk("/missing").into_iter();
+            assert!(matches!(walk.next(), Some(Err(VfsError::NotFound(_)))));
+            assert!(walk.next().is_none());
+            assert!(matches!(
+                fs.walk("").into_iter().next(),
+                Some(Err(VfsError::InvalidPath { .. }))
+            ));
+            Ok(())
+        }
+
+        #[test]
+        fn test_unreadable_dir_does_not_stop_walk() -> Result<()> {
+            let mut fs = create_tree()?;
+            fs.set_permissions("/a/b", 0o000)?;
+
+            let results: Vec<_> = fs.walk("/").into_iter().collect();
+            assert!(
+                results
+                    .iter()
+                    .any(|r| matches!(r, Err(VfsError::PermissionDenied(_))))
+            );
+            assert!(
+                results
+                    .iter()
+                    .any(|r| matches!(r, Ok(e) if e.path() == Path::new("/z.txt")))
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_walk_dir_fs() -> Result<()> {
+            let temp_dir = TempDir::new("walk")?;
+            let mut fs = DirFS::new(temp_dir.path())?;
+            fs.mkfile("/x/y.txt", None)?;
+            assert_eq!(paths(fs.walk("/").min_depth(1))?, ["/x", "/x/y.txt"]);
+            Ok(())
+        }
+    }
+}